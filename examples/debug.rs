@@ -38,7 +38,7 @@ async fn main() {
 }
 
 // output:
-// DEBUG Requesting chat: {"messages":[{"content":"You are a helpful assistant.","role":"system"},{"content":"This is a test. Please respond with 'hello world'.","role":"user"}],"model":"meta-llama/Llama-3.3-70B-Instruct","stream":false}
+// DEBUG Requesting POST https://api.deepinfra.com/v1/openai/chat/completions with headers {authorization: ***, content-type: application/json} and body {"messages":[{"content":"You are a helpful assistant.","role":"system"},{"content":"This is a test. Please respond with 'hello world'.","role":"user"}],"model":"meta-llama/Llama-3.3-70B-Instruct","stream":false}
 // DEBUG connecting to 38.101.151.19:443
 // DEBUG connected to 38.101.151.19:443
 // DEBUG pooling idle connection for ("https", api.deepinfra.com)
@@ -0,0 +1,14 @@
+//! Run the mock server so that other programs can use it.
+
+// You can execute this example with `cargo run --example mock-server` and
+// then point `Provider::OpenAICompatible` to the printed URL.
+
+use transformrs::mock_server::MockServer;
+
+#[tokio::main]
+async fn main() {
+    let server = MockServer::start().await.unwrap();
+    println!("Mock server running at {}", server.url());
+    println!("Use Provider::OpenAICompatible(\"{}/v1\")", server.url());
+    std::future::pending::<()>().await;
+}
@@ -6,6 +6,7 @@ use serde_json::json;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
+use transformrs::audio::AudioFormat;
 use transformrs::Provider;
 
 #[tokio::main]
@@ -17,7 +18,7 @@ async fn main() {
     other.insert("seed".to_string(), json!(42));
     let config = transformrs::text_to_speech::TTSConfig {
         voice: Some("american_male".to_string()),
-        output_format: Some("mp3".to_string()),
+        output_format: Some(AudioFormat::Mp3),
         other: Some(other),
         ..Default::default()
     };
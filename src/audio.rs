@@ -0,0 +1,470 @@
+//! Audio.
+//!
+//! Functionality related to audio files, such as detecting their format,
+//! joining the audio of separately synthesized chunks of text into one file,
+//! and generating silence to put between them.
+//!
+//! Audio files cannot always be concatenated byte by byte. WAV files have a
+//! header that contains the length of the data and MP3 files may start with an
+//! ID3 tag and a Xing header that describe only the first part. [join] takes
+//! care of this.
+
+use bytes::Bytes;
+use serde::Deserialize;
+use serde::Serialize;
+use std::error::Error;
+
+/// Audio file format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    Mp3,
+    /// Opus in an Ogg container.
+    Opus,
+    /// AAC in ADTS frames.
+    Aac,
+    Flac,
+    Wav,
+    /// Raw signed 16-bit little-endian mono samples without a header.
+    Pcm {
+        sample_rate: u32,
+    },
+}
+
+impl AudioFormat {
+    /// Detect the format from the first bytes of the audio.
+    ///
+    /// Raw PCM has no header, so it is never detected.
+    pub fn detect(bytes: &[u8]) -> Option<AudioFormat> {
+        if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WAVE" {
+            Some(AudioFormat::Wav)
+        } else if bytes.starts_with(b"OggS") {
+            Some(AudioFormat::Opus)
+        } else if bytes.starts_with(b"fLaC") {
+            Some(AudioFormat::Flac)
+        } else if bytes.starts_with(b"ID3") || mp3_frame_len(bytes).is_some() {
+            Some(AudioFormat::Mp3)
+        } else if bytes.len() >= 2 && bytes[0] == 0xff && bytes[1] & 0xf6 == 0xf0 {
+            Some(AudioFormat::Aac)
+        } else {
+            None
+        }
+    }
+    /// The file extension without a dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Pcm { .. } => "pcm",
+        }
+    }
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "audio/mpeg",
+            AudioFormat::Opus => "audio/ogg",
+            AudioFormat::Aac => "audio/aac",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Pcm { .. } => "audio/pcm",
+        }
+    }
+}
+
+impl std::fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// Join audio files of the same format into one.
+///
+/// Raw PCM, AAC, and Ogg Opus audio is concatenated as is, which players
+/// handle as one stream.
+pub fn join(format: AudioFormat, chunks: &[Bytes]) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
+    match format {
+        AudioFormat::Wav => join_wav(chunks),
+        AudioFormat::Mp3 => join_mp3(chunks),
+        AudioFormat::Pcm { .. } | AudioFormat::Aac | AudioFormat::Opus => {
+            Ok(Bytes::from(chunks.concat()))
+        }
+        AudioFormat::Flac => Err(format!("Joining {format} audio is not supported").into()),
+    }
+}
+
+struct Wav<'a> {
+    fmt: &'a [u8],
+    data: &'a [u8],
+}
+
+fn parse_wav(wav: &[u8]) -> Result<Wav<'_>, Box<dyn Error + Send + Sync>> {
+    if wav.len() < 12 || &wav[..4] != b"RIFF" || &wav[8..12] != b"WAVE" {
+        return Err("audio is not a WAV file".into());
+    }
+    let mut fmt = None;
+    let mut pos = 12;
+    while pos + 8 <= wav.len() {
+        let id = &wav[pos..pos + 4];
+        let size = u32::from_le_bytes(wav[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let start = pos + 8;
+        // Streamed WAV files do not know the length of the data in advance and
+        // set it to the maximum value.
+        let end = start.saturating_add(size).min(wav.len());
+        match id {
+            b"fmt " => fmt = Some(&wav[start..end]),
+            b"data" => {
+                let fmt = fmt.ok_or("WAV file has no fmt chunk before the data")?;
+                let data = &wav[start..end];
+                return Ok(Wav { fmt, data });
+            }
+            _ => {}
+        }
+        pos = end + (size % 2);
+    }
+    Err("WAV file has no data chunk".into())
+}
+
+fn join_wav(chunks: &[Bytes]) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
+    let wavs = chunks
+        .iter()
+        .map(|chunk| parse_wav(chunk))
+        .collect::<Result<Vec<_>, _>>()?;
+    let fmt = wavs.first().ok_or("no audio to join")?.fmt;
+    if wavs.iter().any(|wav| wav.fmt != fmt) {
+        return Err("cannot join WAV files with different formats".into());
+    }
+    let data: Vec<&[u8]> = wavs.iter().map(|wav| wav.data).collect();
+    write_wav(fmt, &data)
+}
+
+/// A WAV file with the `fmt` chunk and the concatenated `data`.
+fn write_wav(fmt: &[u8], data: &[&[u8]]) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
+    let data_len: usize = data.iter().map(|data| data.len()).sum();
+    let riff_len = 4 + 8 + fmt.len() + 8 + data_len + (data_len % 2);
+    let riff_len = u32::try_from(riff_len).map_err(|_| "joined WAV file is too large")?;
+    let mut out = Vec::with_capacity(riff_len as usize + 8);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&riff_len.to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    out.extend_from_slice(fmt);
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data_len as u32).to_le_bytes());
+    for data in data {
+        out.extend_from_slice(data);
+    }
+    if data_len % 2 == 1 {
+        out.push(0);
+    }
+    Ok(Bytes::from(out))
+}
+
+struct Mp3Frame {
+    len: usize,
+    samples: u32,
+    sample_rate: u32,
+    /// Stereo, joint stereo, dual channel, or mono.
+    channel_mode: u8,
+}
+
+/// Parse the header of the MPEG audio Layer III frame that starts at `frame`.
+fn mp3_frame(frame: &[u8]) -> Option<Mp3Frame> {
+    if frame.len() < 4 || frame[0] != 0xff || frame[1] & 0xe0 != 0xe0 {
+        return None;
+    }
+    let version = (frame[1] >> 3) & 0b11;
+    let layer = (frame[1] >> 1) & 0b11;
+    if version == 0b01 || layer != 0b01 {
+        return None;
+    }
+    let mpeg1 = version == 0b11;
+    let bitrates: [u32; 15] = if mpeg1 {
+        [
+            0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ]
+    } else {
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160]
+    };
+    let bitrate = *bitrates.get((frame[2] >> 4) as usize)? * 1000;
+    let sample_rates: [u32; 3] = match version {
+        0b11 => [44100, 48000, 32000],
+        0b10 => [22050, 24000, 16000],
+        _ => [11025, 12000, 8000],
+    };
+    let sample_rate = *sample_rates.get(((frame[2] >> 2) & 0b11) as usize)?;
+    if bitrate == 0 {
+        return None;
+    }
+    let padding = ((frame[2] >> 1) & 1) as u32;
+    let (coefficient, samples) = if mpeg1 { (144, 1152) } else { (72, 576) };
+    Some(Mp3Frame {
+        len: (coefficient * bitrate / sample_rate + padding) as usize,
+        samples,
+        sample_rate,
+        channel_mode: frame[3] >> 6,
+    })
+}
+
+fn mp3_frame_len(frame: &[u8]) -> Option<usize> {
+    mp3_frame(frame).map(|frame| frame.len)
+}
+
+/// Whether the frame holds a Xing, Info, or VBRI header instead of audio.
+///
+/// These headers contain the number of frames, which is wrong after joining.
+fn is_info_frame(frame: &[u8]) -> bool {
+    let mpeg1 = (frame[1] >> 3) & 0b11 == 0b11;
+    let mono = frame[3] >> 6 == 0b11;
+    let offset = match (mpeg1, mono) {
+        (true, false) => 36,
+        (true, true) | (false, false) => 21,
+        (false, true) => 13,
+    };
+    let tag = |offset: usize| frame.get(offset..offset + 4);
+    matches!(tag(offset), Some(b"Xing") | Some(b"Info")) || tag(36) == Some(b"VBRI")
+}
+
+/// The MPEG audio frames without ID3 tags and info frames.
+fn mp3_frames(mp3: &[u8]) -> Result<Vec<&[u8]>, Box<dyn Error + Send + Sync>> {
+    let mut pos = 0;
+    if mp3.len() >= 10 && mp3.starts_with(b"ID3") {
+        // The size is a syncsafe integer that excludes the 10-byte header.
+        let size = mp3[6..10]
+            .iter()
+            .fold(0usize, |size, byte| (size << 7) | (*byte & 0x7f) as usize);
+        let footer = if mp3[5] & 0x10 != 0 { 10 } else { 0 };
+        pos = 10 + size + footer;
+    }
+    let mut frames = vec![];
+    while pos < mp3.len() {
+        let Some(len) = mp3_frame_len(&mp3[pos..]) else {
+            if frames.is_empty() {
+                // Skip garbage before the first frame.
+                pos += 1;
+                continue;
+            }
+            // For example, an ID3v1 tag at the end.
+            break;
+        };
+        let Some(frame) = mp3.get(pos..pos + len) else {
+            // Drop a truncated frame at the end.
+            break;
+        };
+        if !(frames.is_empty() && is_info_frame(frame)) {
+            frames.push(frame);
+        }
+        pos += len;
+    }
+    if frames.is_empty() {
+        return Err("no MP3 frames found in audio".into());
+    }
+    Ok(frames)
+}
+
+/// The duration of the audio in seconds.
+///
+/// Only known for WAV, PCM, and MP3 audio.
+pub fn duration(format: AudioFormat, audio: &[u8]) -> Option<f64> {
+    match format {
+        AudioFormat::Wav => {
+            let wav = parse_wav(audio).ok()?;
+            let channels = u16::from_le_bytes(wav.fmt.get(2..4)?.try_into().ok()?) as f64;
+            let sample_rate = u32::from_le_bytes(wav.fmt.get(4..8)?.try_into().ok()?) as f64;
+            let bits = u16::from_le_bytes(wav.fmt.get(14..16)?.try_into().ok()?) as f64;
+            if channels == 0.0 || sample_rate == 0.0 || bits == 0.0 {
+                return None;
+            }
+            Some(wav.data.len() as f64 / (channels * bits / 8.0) / sample_rate)
+        }
+        AudioFormat::Pcm { sample_rate } if sample_rate > 0 => {
+            Some(audio.len() as f64 / 2.0 / sample_rate as f64)
+        }
+        AudioFormat::Mp3 => {
+            let frames = mp3_frames(audio).ok()?;
+            let seconds = frames
+                .iter()
+                .filter_map(|frame| mp3_frame(frame))
+                .map(|frame| frame.samples as f64 / frame.sample_rate as f64)
+                .sum();
+            Some(seconds)
+        }
+        _ => None,
+    }
+}
+
+fn join_mp3(chunks: &[Bytes]) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
+    let mut out = vec![];
+    let mut format = None;
+    for chunk in chunks {
+        let frames = mp3_frames(chunk)?;
+        let first = mp3_frame(frames[0]).ok_or("invalid MP3 frame header")?;
+        let chunk_format = (first.sample_rate, first.channel_mode);
+        if *format.get_or_insert(chunk_format) != chunk_format {
+            return Err("cannot join MP3 files with different formats".into());
+        }
+        for frame in frames {
+            out.extend_from_slice(frame);
+        }
+    }
+    Ok(Bytes::from(out))
+}
+
+/// Silent audio of `seconds` with the same encoding as `like`, so that it can
+/// be joined with `like` via [join].
+///
+/// Only supported for WAV, PCM, and MP3 audio. MP3 silence is made of whole
+/// frames, so its duration is rounded to the nearest frame.
+pub fn silence(
+    format: AudioFormat,
+    like: &[u8],
+    seconds: f64,
+) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
+    let seconds = seconds.max(0.0);
+    match format {
+        AudioFormat::Wav => {
+            let wav = parse_wav(like)?;
+            let field =
+                |range: std::ops::Range<usize>| -> Result<u32, Box<dyn Error + Send + Sync>> {
+                    let bytes = wav.fmt.get(range).ok_or("WAV fmt chunk is too short")?;
+                    Ok(bytes
+                        .iter()
+                        .rev()
+                        .fold(0, |value, byte| (value << 8) | *byte as u32))
+                };
+            let sample_rate = field(4..8)?;
+            let block_align = field(12..14)? as usize;
+            // 8-bit samples are unsigned, so their silence is in the middle.
+            let zero = if field(14..16)? == 8 { 0x80 } else { 0 };
+            let blocks = (seconds * sample_rate as f64).round() as usize;
+            write_wav(wav.fmt, &[&vec![zero; blocks * block_align]])
+        }
+        AudioFormat::Pcm { sample_rate } => {
+            let samples = (seconds * sample_rate as f64).round() as usize;
+            Ok(Bytes::from(vec![0; samples * 2]))
+        }
+        AudioFormat::Mp3 => {
+            let first = mp3_frames(like)?[0];
+            let mut header = [first[0], first[1], first[2], first[3]];
+            // Without a CRC and without padding, so that every frame is the same.
+            header[1] |= 1;
+            header[2] &= !0b10;
+            let frame = mp3_frame(&header).ok_or("invalid MP3 frame header")?;
+            // An empty side info and main data decode to silence.
+            let mut silent = vec![0; frame.len];
+            silent[..4].copy_from_slice(&header);
+            let count = (seconds * frame.sample_rate as f64 / frame.samples as f64).round();
+            Ok(Bytes::from(silent.repeat(count as usize)))
+        }
+        _ => Err(format!("Generating silence in {format} audio is not supported").into()),
+    }
+}
+
+#[cfg(test)]
+fn wav(samples: &[i16]) -> Bytes {
+    let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    let fmt = [1, 0, 1, 0, 0x80, 0x3e, 0, 0, 0, 0x7d, 0, 0, 2, 0, 16, 0];
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&((4 + 8 + 16 + 12 + 8 + data.len()) as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&fmt);
+    out.extend_from_slice(b"LIST");
+    out.extend_from_slice(&3u32.to_le_bytes());
+    out.extend_from_slice(b"abc\0");
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&data);
+    Bytes::from(out)
+}
+
+#[test]
+fn test_join_wav() {
+    let joined = join(AudioFormat::Wav, &[wav(&[1, 2]), wav(&[3])]).unwrap();
+    let parsed = parse_wav(&joined).unwrap();
+    assert_eq!(parsed.data, &[1, 0, 2, 0, 3, 0]);
+    assert_eq!(parsed.fmt, parse_wav(&wav(&[])).unwrap().fmt);
+    let riff_len = u32::from_le_bytes(joined[4..8].try_into().unwrap()) as usize;
+    assert_eq!(riff_len + 8, joined.len());
+    assert_eq!(AudioFormat::detect(&joined), Some(AudioFormat::Wav));
+    assert!(join(AudioFormat::Wav, &[wav(&[1]), Bytes::from("not a wav")]).is_err());
+}
+
+#[test]
+fn test_join_mp3() {
+    let frame = crate::mock_server::mp3();
+    assert_eq!(mp3_frame_len(&frame), Some(frame.len()));
+    let mut info = frame.to_vec();
+    info[36..40].copy_from_slice(b"Info");
+    let mut tagged = b"ID3\x04\x00\x00\x00\x00\x00\x05hello".to_vec();
+    tagged.extend_from_slice(&info);
+    tagged.extend_from_slice(&frame);
+    tagged.extend_from_slice(b"TAG");
+    let mut truncated = frame.to_vec();
+    truncated.extend_from_slice(&frame[..100]);
+    let chunks = [Bytes::from(tagged), Bytes::from(truncated)];
+    assert_eq!(AudioFormat::detect(&chunks[0]), Some(AudioFormat::Mp3));
+    let joined = join(AudioFormat::Mp3, &chunks).unwrap();
+    assert_eq!(joined, [&frame[..], &frame[..]].concat());
+    assert!(join(AudioFormat::Mp3, &[Bytes::from("no frames")]).is_err());
+    let mut mono = frame.to_vec();
+    mono[3] |= 0b1100_0000;
+    assert!(join(AudioFormat::Mp3, &[frame.clone(), Bytes::from(mono)]).is_err());
+    let mut khz48 = frame.to_vec();
+    khz48[2] |= 0b0100;
+    assert!(join(AudioFormat::Mp3, &[frame.clone(), Bytes::from(khz48)]).is_err());
+    assert!(join(AudioFormat::Flac, &[Bytes::from("fLaC")]).is_err());
+}
+
+#[test]
+fn test_detect() {
+    let detect = |bytes: &[u8]| AudioFormat::detect(bytes);
+    assert_eq!(detect(b"OggS\0\x02"), Some(AudioFormat::Opus));
+    assert_eq!(detect(b"fLaC\0\0\0\x22"), Some(AudioFormat::Flac));
+    assert_eq!(detect(&[0xff, 0xf1, 0x50, 0x80]), Some(AudioFormat::Aac));
+    assert_eq!(detect(&[0xff, 0xf3, 0x64, 0xc4]), Some(AudioFormat::Mp3));
+    assert_eq!(detect(&[0, 0, 0, 0]), None);
+    let pcm = AudioFormat::Pcm { sample_rate: 24000 };
+    assert_eq!(pcm.to_string(), "pcm");
+    assert_eq!(
+        serde_json::to_value(pcm).unwrap()["pcm"]["sample_rate"],
+        24000
+    );
+}
+
+#[test]
+fn test_duration() {
+    let frame = crate::mock_server::mp3();
+    let seconds = duration(AudioFormat::Mp3, &[&frame[..], &frame[..]].concat()).unwrap();
+    assert!((seconds - 2.0 * 1152.0 / 44100.0).abs() < 1e-9);
+    // 16 kHz mono 16-bit.
+    assert_eq!(duration(AudioFormat::Wav, &wav(&[0; 8000])), Some(0.5));
+    let pcm = AudioFormat::Pcm { sample_rate: 24000 };
+    assert_eq!(duration(pcm, &[0; 48000]), Some(1.0));
+    assert_eq!(duration(AudioFormat::Opus, b"OggS"), None);
+}
+
+#[test]
+fn test_silence() {
+    let like = wav(&[1, 2, 3]);
+    let silent = silence(AudioFormat::Wav, &like, 0.25).unwrap();
+    assert_eq!(duration(AudioFormat::Wav, &silent), Some(0.25));
+    assert!(parse_wav(&silent)
+        .unwrap()
+        .data
+        .iter()
+        .all(|byte| *byte == 0));
+    assert!(join(AudioFormat::Wav, &[like, silent]).is_ok());
+
+    let pcm = AudioFormat::Pcm { sample_rate: 24000 };
+    assert_eq!(silence(pcm, &[], 0.5).unwrap().len(), 24000);
+
+    let frame = crate::mock_server::mp3();
+    let silent = silence(AudioFormat::Mp3, &frame, 1.0).unwrap();
+    let seconds = duration(AudioFormat::Mp3, &silent).unwrap();
+    assert!((seconds - 38.0 * 1152.0 / 44100.0).abs() < 1e-9);
+    assert_eq!(mp3_frames(&silent).unwrap().len(), 38);
+    assert!(silence(AudioFormat::Opus, b"OggS", 1.0).is_err());
+}
@@ -0,0 +1,404 @@
+//! Cassettes.
+//!
+//! Record and replay of HTTP interactions so that tests can run offline.
+//!
+//! In [Mode::Record], every request is sent to the provider and the request
+//! and response are written to a fixture file in the cassette directory.
+//! Streamed responses are stored chunk by chunk. Credentials are scrubbed
+//! before anything is written. In [Mode::Replay], no requests are sent.
+//! Instead, the response is served from the fixture file that matches the
+//! method, URL, and body of the request.
+//!
+//! For example, to record the fixtures for the tests:
+//!
+//! ```sh
+//! TRANSFORMRS_CASSETTE=record cargo test
+//! ```
+//!
+//! and to run the tests offline:
+//!
+//! ```sh
+//! TRANSFORMRS_CASSETTE=replay cargo test
+//! ```
+
+use crate::http::redact_json;
+use crate::http::redact_url;
+use crate::http::REDACTED;
+use base64::prelude::*;
+use bytes::Bytes;
+use futures::StreamExt;
+use reqwest::Client;
+use reqwest::Request;
+use reqwest::Response;
+use reqwest::Url;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+/// Whether requests are recorded or replayed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Send requests and write the interactions to the cassette directory.
+    Record,
+    /// Serve responses from the cassette directory without sending requests.
+    Replay,
+}
+
+impl FromStr for Mode {
+    type Err = Box<dyn Error + Send + Sync>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "record" => Ok(Mode::Record),
+            "replay" => Ok(Mode::Replay),
+            _ => Err(format!("Unsupported cassette mode: {s}.").into()),
+        }
+    }
+}
+
+/// A directory of recorded interactions.
+#[derive(Clone, Debug)]
+pub struct Cassette {
+    pub mode: Mode,
+    pub dir: PathBuf,
+    /// Whether requests to loopback addresses, such as the mock server, are
+    /// recorded or replayed too.
+    pub loopback: bool,
+}
+
+static CASSETTE: RwLock<Option<Cassette>> = RwLock::new(None);
+
+/// Record or replay all requests in this process.
+pub fn install(mode: Mode, dir: &str) {
+    let cassette = Cassette {
+        mode,
+        dir: PathBuf::from(dir),
+        loopback: true,
+    };
+    *CASSETTE.write().unwrap() = Some(cassette);
+}
+
+/// Send requests to the providers again.
+pub fn uninstall() {
+    *CASSETTE.write().unwrap() = None;
+}
+
+/// Install the cassette that is configured via environment variables.
+///
+/// The mode is read from `TRANSFORMRS_CASSETTE` (`record` or `replay`) and
+/// the directory from `TRANSFORMRS_CASSETTE_DIR`, which defaults to
+/// `tests/cassettes`. Does nothing when `TRANSFORMRS_CASSETTE` is not set.
+///
+/// Requests to loopback addresses are still sent, so that tests against the
+/// mock server keep working next to the recorded tests.
+pub fn install_from_env() -> Result<Option<Mode>, Box<dyn Error + Send + Sync>> {
+    let mode = match std::env::var("TRANSFORMRS_CASSETTE") {
+        Ok(mode) if !mode.is_empty() => Mode::from_str(&mode)?,
+        _ => return Ok(None),
+    };
+    let dir =
+        std::env::var("TRANSFORMRS_CASSETTE_DIR").unwrap_or_else(|_| "tests/cassettes".to_string());
+    let cassette = Cassette {
+        mode,
+        dir: PathBuf::from(dir),
+        loopback: false,
+    };
+    *CASSETTE.write().unwrap() = Some(cassette);
+    Ok(Some(mode))
+}
+
+/// The installed cassette, if any.
+pub fn current() -> Option<Cassette> {
+    CASSETTE.read().unwrap().clone()
+}
+
+/// A request or response body.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Body {
+    Json(Value),
+    Text(String),
+    Base64(String),
+}
+
+impl Body {
+    /// Convert bytes to a body with the secrets scrubbed.
+    ///
+    /// For requests, credential fields in JSON are masked too.
+    fn from_bytes(bytes: &[u8], secrets: &[String], is_request: bool) -> Self {
+        if let Ok(mut value) = serde_json::from_slice::<Value>(bytes) {
+            if value.is_object() || value.is_array() {
+                if is_request {
+                    redact_json(&mut value);
+                }
+                let text = scrub(&value.to_string(), secrets);
+                if let Ok(value) = serde_json::from_str(&text) {
+                    return Body::Json(value);
+                }
+            }
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Body::Text(scrub(text, secrets)),
+            Err(_) => Body::Base64(BASE64_STANDARD.encode(bytes)),
+        }
+    }
+    fn to_bytes(&self) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
+        let bytes = match self {
+            Body::Json(value) => Bytes::from(value.to_string()),
+            Body::Text(text) => Bytes::from(text.clone()),
+            Body::Base64(encoded) => Bytes::from(BASE64_STANDARD.decode(encoded)?),
+        };
+        Ok(bytes)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// The URL with credentials masked.
+    pub url: String,
+    pub body: Option<Body>,
+}
+
+impl RecordedRequest {
+    fn new(request: &Request, secrets: &[String]) -> Self {
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|bytes| Body::from_bytes(bytes, secrets, true));
+        Self {
+            method: request.method().to_string(),
+            url: scrub(&redact_url(request.url()), secrets),
+            body,
+        }
+    }
+    /// Name of the fixture file for this request.
+    ///
+    /// The name contains a hash of the method, URL, and body, so that
+    /// different requests to the same endpoint get different files.
+    pub fn file_name(&self) -> String {
+        let body = match &self.body {
+            Some(body) => serde_json::to_string(body).unwrap_or_default(),
+            None => String::new(),
+        };
+        let hash = fnv1a(&[self.method.as_bytes(), self.url.as_bytes(), body.as_bytes()]);
+        let path = self.url.split('?').next().unwrap_or_default();
+        let path = path.split("://").last().unwrap_or_default();
+        let slug: String = path
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let slug = slug.trim_matches('-').to_lowercase();
+        format!("{slug}-{hash:016x}.json")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    /// The body in the chunks as they were received.
+    pub chunks: Vec<Body>,
+}
+
+impl RecordedResponse {
+    async fn read(
+        resp: Response,
+        secrets: &[String],
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let status = resp.status().as_u16();
+        let content_type = resp
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let mut chunks = vec![];
+        let mut stream = resp.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| e.without_url())?;
+            chunks.push(chunk);
+        }
+        let is_json = content_type
+            .as_deref()
+            .is_some_and(|ct| ct.contains("json"));
+        let chunks = if is_json && chunks.len() > 1 {
+            // JSON is only valid as a whole, so store it as a single chunk.
+            vec![Body::from_bytes(&chunks.concat(), secrets, false)]
+        } else {
+            chunks
+                .iter()
+                .map(|chunk| match std::str::from_utf8(chunk) {
+                    Ok(text) if !is_json => Body::Text(scrub(text, secrets)),
+                    _ => Body::from_bytes(chunk, secrets, false),
+                })
+                .collect()
+        };
+        Ok(Self {
+            status,
+            content_type,
+            chunks,
+        })
+    }
+    /// Convert into a response whose body yields the recorded chunks.
+    pub fn to_response(&self) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let chunks = self
+            .chunks
+            .iter()
+            .map(|chunk| chunk.to_bytes())
+            .collect::<Result<Vec<_>, _>>()?;
+        let content_type = self
+            .content_type
+            .as_deref()
+            .unwrap_or("application/octet-stream");
+        crate::http::response(self.status, content_type, chunks)
+    }
+}
+
+/// A request with its response.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+impl Cassette {
+    /// Whether requests to `url` are recorded or replayed.
+    pub fn handles(&self, url: &Url) -> bool {
+        if self.loopback {
+            return true;
+        }
+        match url.host_str() {
+            Some("localhost") => false,
+            Some(host) => match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+                Ok(ip) => !ip.is_loopback(),
+                Err(_) => true,
+            },
+            None => true,
+        }
+    }
+    fn path(&self, request: &RecordedRequest) -> PathBuf {
+        self.dir.join(request.file_name())
+    }
+    pub fn save(&self, interaction: &Interaction) -> Result<(), Box<dyn Error + Send + Sync>> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(&interaction.request);
+        let json = serde_json::to_string_pretty(interaction)?;
+        std::fs::write(&path, json + "\n")
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+        Ok(())
+    }
+    pub fn load(
+        &self,
+        request: &RecordedRequest,
+    ) -> Result<Interaction, Box<dyn Error + Send + Sync>> {
+        let path = self.path(request);
+        let json = std::fs::read_to_string(&path).map_err(|e| {
+            format!(
+                "no cassette for {} {} at {} ({e}); record it with TRANSFORMRS_CASSETTE=record",
+                request.method,
+                request.url,
+                path.display()
+            )
+        })?;
+        Ok(serde_json::from_str(&json)?)
+    }
+    /// Record or replay the request.
+    pub(crate) async fn send(
+        &self,
+        client: Client,
+        request: Request,
+    ) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let secrets = secrets(&request);
+        let recorded_request = RecordedRequest::new(&request, &secrets);
+        let interaction = match self.mode {
+            Mode::Replay => self.load(&recorded_request)?,
+            Mode::Record => {
+                let url = recorded_request.url.clone();
+                let resp = client
+                    .execute(request)
+                    .await
+                    .map_err(|e| format!("request to {url} failed: {}", e.without_url()))?;
+                let interaction = Interaction {
+                    request: recorded_request,
+                    response: RecordedResponse::read(resp, &secrets).await?,
+                };
+                self.save(&interaction)?;
+                interaction
+            }
+        };
+        interaction.response.to_response()
+    }
+}
+
+/// Credentials in the request that should not end up in fixtures.
+fn secrets(request: &Request) -> Vec<String> {
+    let mut secrets = vec![];
+    for (name, value) in request.headers() {
+        if crate::http::is_sensitive_header(name.as_str()) || value.is_sensitive() {
+            if let Ok(value) = value.to_str() {
+                let value = value.strip_prefix("Bearer ").unwrap_or(value);
+                secrets.push(value.to_string());
+            }
+        }
+    }
+    for (name, value) in request.url().query_pairs() {
+        if crate::http::is_sensitive_name(&name) {
+            secrets.push(value.into_owned());
+        }
+    }
+    // Short values would replace too much.
+    secrets.retain(|secret| secret.len() >= 8);
+    secrets
+}
+
+fn scrub(text: &str, secrets: &[String]) -> String {
+    secrets.iter().fold(text.to_string(), |text, secret| {
+        text.replace(secret, REDACTED)
+    })
+}
+
+/// 64-bit FNV-1a hash, which unlike `DefaultHasher` is stable across Rust
+/// versions.
+pub(crate) fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.iter().chain(&[0xff]) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+#[test]
+fn test_handles() {
+    let cassette = Cassette {
+        mode: Mode::Replay,
+        dir: PathBuf::from("tests/cassettes"),
+        loopback: false,
+    };
+    let handles = |url: &str| cassette.handles(&Url::parse(url).unwrap());
+    assert!(handles("https://api.openai.com/v1/models"));
+    assert!(!handles("http://127.0.0.1:8080/v1/models"));
+    assert!(!handles("http://localhost:8080/v1/models"));
+    assert!(!handles("http://[::1]:8080/v1/models"));
+}
+
+#[test]
+fn test_file_name() {
+    let request = RecordedRequest {
+        method: "POST".to_string(),
+        url: "https://api.openai.com/v1/chat/completions".to_string(),
+        body: Some(Body::Json(serde_json::json!({"model": "gpt-4o-mini"}))),
+    };
+    let name = request.file_name();
+    assert!(name.starts_with("api-openai-com-v1-chat-completions-"));
+    let mut other = request.clone();
+    other.body = Some(Body::Json(serde_json::json!({"model": "gpt-4o"})));
+    assert_ne!(name, other.file_name());
+}
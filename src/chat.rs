@@ -14,8 +14,8 @@ use serde_json::Value;
 use std::error::Error;
 use std::pin::Pin;
 
-fn address(provider: &Provider) -> String {
-    let base_url = crate::openai_base_url(provider);
+fn address(provider: &Provider, key: &Key) -> String {
+    let base_url = crate::openai_base_url(provider, key);
     format!("{}/chat/completions", base_url)
 }
 
@@ -26,7 +26,7 @@ async fn request_chat_completion(
     stream: bool,
     messages: &[Message],
 ) -> Result<Response, Box<dyn Error + Send + Sync>> {
-    let address = address(provider);
+    let address = address(provider, key);
     let body = serde_json::json!({
         "model": model,
         "messages": messages,
@@ -40,13 +40,11 @@ async fn request_chat_completion(
     } else {
         reqwest::Client::new()
     };
-    tracing::debug!("Requesting chat: {body}");
-    let resp = client
+    let req = client
         .post(address)
         .headers(request_headers(key)?)
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    let resp = crate::http::send(req).await?;
     Ok(resp)
 }
 
@@ -83,24 +81,6 @@ pub struct ChatCompletionError {
     pub message: String,
 }
 
-fn extract_error(body: &Value) -> String {
-    if let Some(error) = body.get("error") {
-        if let Some(message) = error.get("message") {
-            return message
-                .as_str()
-                .unwrap_or(body.to_string().as_str())
-                .to_string();
-        }
-    }
-    if let Some(message) = body.get("message") {
-        return message
-            .as_str()
-            .unwrap_or(body.to_string().as_str())
-            .to_string();
-    }
-    format!("Unknown error: {body}")
-}
-
 /// Response from the OpenAI API.
 ///
 /// This is a wrapper around the `serde_json::Value` which can either be
@@ -135,7 +115,7 @@ impl ChatCompletionResponse {
         let json = match serde_json::from_str::<ChatCompletion>(&text) {
             Ok(json) => json,
             Err(_e) => match serde_json::from_str::<Value>(&text) {
-                Ok(error) => return Err(extract_error(&error).into()),
+                Ok(error) => return Err(crate::extract_error(&error).into()),
                 Err(e) => {
                     return Err(format!("Error parsing response: {} in text: '{}'", e, text).into())
                 }
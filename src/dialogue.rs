@@ -0,0 +1,295 @@
+//! Dialogue.
+//!
+//! Functionality related to synthesizing scripts with several speakers. Each
+//! turn is spoken by the voice of its speaker via
+//! [crate::text_to_speech::tts] and the turns are joined into one track with a
+//! pause between them:
+//!
+//! ```no_run
+//! use std::collections::HashMap;
+//! use transformrs::dialogue;
+//! use transformrs::dialogue::DialogueConfig;
+//! use transformrs::dialogue::Speaker;
+//! use transformrs::dialogue::Turn;
+//! use transformrs::Provider;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let keys = transformrs::load_keys(".env");
+//! let key = keys.for_provider(&Provider::OpenAI).unwrap();
+//! let mut speakers = HashMap::new();
+//! speakers.insert("host".to_string(), Speaker::new(&key, "alloy"));
+//! speakers.insert("guest".to_string(), Speaker::new(&key, "onyx"));
+//! let turns = vec![
+//!     Turn::new("host", "Welcome to the show."),
+//!     Turn::new("guest", "Thanks for having me."),
+//! ];
+//! let config = DialogueConfig::default();
+//! let dialogue = dialogue::synthesize(&turns, &speakers, &config)
+//!     .await
+//!     .unwrap();
+//! std::fs::write("dialogue.mp3", &dialogue.speech.audio).unwrap();
+//! # }
+//! ```
+
+use crate::audio;
+use crate::text_to_speech::tts;
+use crate::text_to_speech::Speech;
+use crate::text_to_speech::TTSConfig;
+use crate::text_to_speech::Timing;
+use crate::Key;
+use crate::Provider;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// A part of the script that is spoken by one speaker.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    /// The name of the speaker in the speakers of [synthesize].
+    pub speaker: String,
+    pub text: String,
+    /// Seconds of silence after this turn instead of [DialogueConfig::pause].
+    pub pause: Option<f64>,
+}
+
+impl Turn {
+    pub fn new(speaker: &str, text: &str) -> Turn {
+        Turn {
+            speaker: speaker.to_string(),
+            text: text.to_string(),
+            pause: None,
+        }
+    }
+}
+
+/// How the turns of a speaker are synthesized.
+#[derive(Clone, Debug)]
+pub struct Speaker {
+    pub provider: Provider,
+    pub key: Key,
+    pub model: Option<String>,
+    /// Used as [TTSConfig::voice].
+    pub voice: String,
+    pub config: TTSConfig,
+}
+
+impl Speaker {
+    /// A speaker with `voice` at the provider of `key`.
+    pub fn new(key: &Key, voice: &str) -> Speaker {
+        Speaker {
+            provider: key.provider.clone(),
+            key: key.clone(),
+            model: None,
+            voice: voice.to_string(),
+            config: TTSConfig::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DialogueConfig {
+    /// Seconds of silence between two turns.
+    pub pause: f64,
+    /// The maximum number of turns that are synthesized at the same time.
+    pub concurrency: usize,
+}
+
+impl Default for DialogueConfig {
+    fn default() -> Self {
+        Self {
+            pause: 0.5,
+            concurrency: 4,
+        }
+    }
+}
+
+/// When a turn is spoken in seconds from the beginning of the dialogue.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TurnOffset {
+    pub speaker: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Debug)]
+pub struct Dialogue {
+    /// The audio of all turns with the word and character timings shifted to
+    /// the start of their turn.
+    pub speech: Speech,
+    /// The offsets in the same order as the turns.
+    pub turns: Vec<TurnOffset>,
+}
+
+/// Join the speech of each turn with the pauses in between.
+fn assemble(
+    turns: &[Turn],
+    speeches: &[Speech],
+    config: &DialogueConfig,
+) -> Result<Dialogue, Box<dyn Error + Send + Sync>> {
+    let format = speeches.first().ok_or("dialogue has no turns")?.format;
+    if let Some((i, speech)) = speeches
+        .iter()
+        .enumerate()
+        .find(|(_, speech)| speech.format != format)
+    {
+        let other = speech.format;
+        return Err(format!("turn {i} is {other} audio but turn 0 is {format} audio").into());
+    }
+    let mut pieces = vec![];
+    let mut offsets = vec![];
+    let mut words = vec![];
+    let mut characters = vec![];
+    let mut marks = vec![];
+    let mut start = 0.0;
+    for (i, (turn, speech)) in turns.iter().zip(speeches).enumerate() {
+        let duration = speech
+            .duration()
+            .ok_or_else(|| format!("the duration of {format} audio is unknown"))?;
+        let shifted = |timings: &Option<Vec<Timing>>| -> Vec<Timing> {
+            let timings = timings.iter().flatten();
+            timings.map(|timing| timing.clone().shift(start)).collect()
+        };
+        words.extend(shifted(&speech.words));
+        characters.extend(shifted(&speech.characters));
+        marks.extend(shifted(&speech.marks));
+        offsets.push(TurnOffset {
+            speaker: turn.speaker.clone(),
+            start,
+            end: start + duration,
+        });
+        pieces.push(speech.audio.clone());
+        start += duration;
+        let pause = turn.pause.unwrap_or(config.pause);
+        if i + 1 < turns.len() && pause > 0.0 {
+            let silence = audio::silence(format, &speech.audio, pause)?;
+            start += audio::duration(format, &silence).unwrap_or(pause);
+            pieces.push(silence);
+        }
+    }
+    let speech = Speech {
+        request_id: None,
+        file_format: format.extension().to_string(),
+        format,
+        audio: audio::join(format, &pieces)?,
+        words: speeches
+            .iter()
+            .all(|speech| speech.words.is_some())
+            .then_some(words),
+        characters: speeches
+            .iter()
+            .all(|speech| speech.characters.is_some())
+            .then_some(characters),
+        marks: speeches
+            .iter()
+            .all(|speech| speech.marks.is_some())
+            .then_some(marks),
+    };
+    Ok(Dialogue {
+        speech,
+        turns: offsets,
+    })
+}
+
+#[test]
+fn test_assemble() {
+    let format = audio::AudioFormat::Pcm { sample_rate: 1000 };
+    let speech = |seconds: usize, word: &str| Speech {
+        request_id: None,
+        file_format: format.extension().to_string(),
+        format,
+        audio: bytes::Bytes::from(vec![1; seconds * 2000]),
+        words: Some(vec![Timing {
+            text: word.to_string(),
+            start: 0.5,
+            end: Some(1.0),
+        }]),
+        characters: None,
+        marks: Some(vec![Timing {
+            text: "mark".to_string(),
+            start: 0.25,
+            end: None,
+        }]),
+    };
+    let mut turns = vec![
+        Turn::new("a", "Hi"),
+        Turn::new("b", "Hey"),
+        Turn::new("a", "Bye"),
+    ];
+    turns[1].pause = Some(0.0);
+    let speeches = [speech(1, "Hi"), speech(2, "Hey"), speech(1, "Bye")];
+    let dialogue = assemble(&turns, &speeches, &DialogueConfig::default()).unwrap();
+    let offsets: Vec<(f64, f64)> = dialogue
+        .turns
+        .iter()
+        .map(|turn| (turn.start, turn.end))
+        .collect();
+    assert_eq!(offsets, vec![(0.0, 1.0), (1.5, 3.5), (3.5, 4.5)]);
+    assert_eq!(dialogue.speech.duration(), Some(4.5));
+    assert_eq!(dialogue.speech.audio[2000..3000], [0; 1000]);
+    let words = dialogue.speech.words.unwrap();
+    assert_eq!(words[2].start, 4.0);
+    assert_eq!(dialogue.speech.characters, None);
+    let marks = dialogue.speech.marks.unwrap();
+    let starts: Vec<f64> = marks.iter().map(|mark| mark.start).collect();
+    assert_eq!(starts, vec![0.25, 1.75, 3.75]);
+
+    let mut other = speech(1, "Hey");
+    other.format = audio::AudioFormat::Wav;
+    let err = assemble(
+        &turns[..2],
+        &[speech(1, "Hi"), other],
+        &DialogueConfig::default(),
+    );
+    assert_eq!(
+        err.unwrap_err().to_string(),
+        "turn 1 is wav audio but turn 0 is pcm audio"
+    );
+}
+
+/// Synthesize each turn with the voice of its speaker and join the audio.
+///
+/// The turns are synthesized with at most [DialogueConfig::concurrency]
+/// requests at the same time. All speakers must return the same format at the
+/// same sample rate, and the offsets require WAV, PCM, or MP3 audio.
+pub async fn synthesize(
+    turns: &[Turn],
+    speakers: &HashMap<String, Speaker>,
+    config: &DialogueConfig,
+) -> Result<Dialogue, Box<dyn Error + Send + Sync>> {
+    if turns.is_empty() {
+        return Err("dialogue has no turns".into());
+    }
+    let requests = turns
+        .iter()
+        .map(|turn| {
+            let speaker = speakers
+                .get(&turn.speaker)
+                .ok_or_else(|| format!("unknown speaker: {}", turn.speaker))?;
+            let mut tts_config = speaker.config.clone();
+            tts_config.voice = Some(speaker.voice.clone());
+            Ok((speaker, tts_config, turn))
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+    let speeches = futures::stream::iter(requests)
+        .map(|(speaker, tts_config, turn)| async move {
+            let model = speaker.model.as_deref();
+            let resp = tts(
+                &speaker.provider,
+                &speaker.key,
+                &tts_config,
+                model,
+                &turn.text,
+            )
+            .await?;
+            resp.structured()
+        })
+        .buffered(config.concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    assemble(turns, &speeches, config)
+}
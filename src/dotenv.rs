@@ -0,0 +1,231 @@
+//! Dotenv.
+//!
+//! Parsing of `.env` files such as the one passed to [crate::load_keys].
+//!
+//! Supports blank lines, `#` comments, an optional `export ` prefix, values
+//! containing `=`, inline comments after unquoted values, single-quoted
+//! (literal) values, and double-quoted values with escapes that may span
+//! multiple lines.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Return the byte index of the first unescaped `"` in `text`.
+fn closing_quote(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            return Some(i);
+        }
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some(c @ ('"' | '\\' | '$')) => out.push(c),
+            Some(c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn check_trailing(tail: &str, line_number: usize) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let tail = tail.trim();
+    if tail.is_empty() || tail.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("line {line_number}: unexpected characters after quoted value").into())
+    }
+}
+
+/// Return the byte index of the `#` that starts an inline comment, which is
+/// the first one after whitespace.
+fn inline_comment(value: &str) -> Option<usize> {
+    value
+        .match_indices('#')
+        .map(|(i, _)| i)
+        .find(|&i| value[..i].ends_with(char::is_whitespace))
+}
+
+/// Parse the line that starts at `line`, reading more lines from `lines` for
+/// multi-line values.
+fn parse_line<'a>(
+    line: &str,
+    line_number: usize,
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> Result<Option<(String, String)>, Box<dyn Error + Send + Sync>> {
+    let line = line.trim_start();
+    if line.trim_end().is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let line = match line.strip_prefix("export ") {
+        Some(rest) => rest.trim_start(),
+        None => line,
+    };
+    let (name, value) = line
+        .split_once('=')
+        .ok_or_else(|| format!("line {line_number}: expected NAME=value"))?;
+    let name = name.trim_end();
+    if !is_valid_name(name) {
+        return Err(format!("line {line_number}: invalid variable name '{name}'").into());
+    }
+    let value = value.trim_start();
+    let value = if let Some(rest) = value.strip_prefix('\'') {
+        let (inner, tail) = rest
+            .split_once('\'')
+            .ok_or_else(|| format!("line {line_number}: unterminated single quote"))?;
+        check_trailing(tail, line_number)?;
+        inner.to_string()
+    } else if let Some(rest) = value.strip_prefix('"') {
+        let mut raw = rest.to_string();
+        loop {
+            if let Some(end) = closing_quote(&raw) {
+                check_trailing(&raw[end + 1..], line_number)?;
+                break unescape(&raw[..end]);
+            }
+            match lines.next() {
+                Some((_, next)) => {
+                    raw.push('\n');
+                    raw.push_str(next);
+                }
+                None => {
+                    return Err(format!("line {line_number}: unterminated double quote").into());
+                }
+            }
+        }
+    } else {
+        let value = match inline_comment(value) {
+            Some(start) => &value[..start],
+            None => value,
+        };
+        value.trim_end().to_string()
+    };
+    Ok(Some((name.to_string(), value)))
+}
+
+/// Parse the content of a dotenv file.
+///
+/// When a name occurs multiple times, the last value wins.
+pub fn parse(content: &str) -> Result<HashMap<String, String>, Box<dyn Error + Send + Sync>> {
+    parse_with(content, Err)
+}
+
+/// Parse the content of a dotenv file like [parse], but pass the error of
+/// each malformed line to `on_error`, which decides whether parsing should
+/// stop. The malformed lines are skipped otherwise.
+pub fn parse_with(
+    content: &str,
+    on_error: impl Fn(Box<dyn Error + Send + Sync>) -> Result<(), Box<dyn Error + Send + Sync>>,
+) -> Result<HashMap<String, String>, Box<dyn Error + Send + Sync>> {
+    let mut vars = HashMap::new();
+    let mut lines = content.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        match parse_line(line, index + 1, &mut lines) {
+            Ok(Some((name, value))) => {
+                vars.insert(name, value);
+            }
+            Ok(None) => {}
+            Err(e) => on_error(e)?,
+        }
+    }
+    Ok(vars)
+}
+
+/// Read and parse the dotenv file at `path`.
+///
+/// A missing file is not an error and results in an empty map.
+pub fn load(path: &Path) -> Result<HashMap<String, String>, Box<dyn Error + Send + Sync>> {
+    load_with(path, Err)
+}
+
+/// Read and parse the dotenv file at `path` like [load], but pass the errors
+/// to `on_error` like [parse_with].
+pub fn load_with(
+    path: &Path,
+    on_error: impl Fn(Box<dyn Error + Send + Sync>) -> Result<(), Box<dyn Error + Send + Sync>>,
+) -> Result<HashMap<String, String>, Box<dyn Error + Send + Sync>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => parse_with(&content, |e| {
+            on_error(format!("{}: {e}", path.display()).into())
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(format!("failed to read {}: {e}", path.display()).into()),
+    }
+}
+
+#[test]
+fn test_parse() {
+    let content = r#"
+# A comment.
+export OPENAI_KEY=sk-abc==
+DEEPINFRA_KEY = di-123 # inline comment
+GROQ_KEY='literal # not a comment \n'
+GOOGLE_KEY="line one
+line \"two\"" # trailing comment
+
+EMPTY=
+"#;
+    let vars = parse(content).unwrap();
+    assert_eq!(vars["OPENAI_KEY"], "sk-abc==");
+    assert_eq!(vars["DEEPINFRA_KEY"], "di-123");
+    assert_eq!(vars["GROQ_KEY"], "literal # not a comment \\n");
+    assert_eq!(vars["GOOGLE_KEY"], "line one\nline \"two\"");
+    assert_eq!(vars["EMPTY"], "");
+    assert_eq!(vars.len(), 5);
+    let vars = parse("URL=https://example.com/#anchor\t# after a tab\n").unwrap();
+    assert_eq!(vars["URL"], "https://example.com/#anchor");
+}
+
+#[test]
+fn test_parse_errors() {
+    let err = parse("OPENAI_KEY\n").unwrap_err();
+    assert!(err.to_string().contains("line 1"));
+    let err = parse("A=1\nB=\"unterminated\n").unwrap_err();
+    assert!(err.to_string().contains("line 2"));
+    let err = parse("1A=1\n").unwrap_err();
+    assert!(err.to_string().contains("invalid variable name"));
+}
+
+#[test]
+fn test_parse_with_skips_lines() {
+    let content = "A=1\nnot a variable\nB='unterminated\nC=3\n";
+    let errors = std::sync::Mutex::new(vec![]);
+    let vars = parse_with(content, |e| {
+        errors.lock().unwrap().push(e.to_string());
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(vars.len(), 2);
+    assert_eq!(vars["C"], "3");
+    let errors = errors.into_inner().unwrap();
+    assert!(errors[0].starts_with("line 2:"));
+    assert!(errors[1].starts_with("line 3:"));
+}
@@ -0,0 +1,240 @@
+//! Embeddings.
+//!
+//! Functionality related to embeddings.
+//!
+//! Uses the OpenAI-compatible `/embeddings` endpoint for most providers and
+//! the native `batchEmbedContents` endpoint for Google.
+
+use crate::request_headers;
+use crate::Key;
+use crate::Provider;
+use base64::prelude::*;
+use bytes::Bytes;
+use reqwest;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// How the embeddings are encoded in the response.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingFormat {
+    #[default]
+    Float,
+    /// Little-endian `f32` values encoded as base64, which is smaller.
+    Base64,
+}
+
+/// Configuration for embeddings.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingsConfig {
+    /// Number of dimensions of the output for models that support it.
+    pub dimensions: Option<u32>,
+    pub encoding_format: Option<EncodingFormat>,
+    /// Extra fields for the body, or for each input's request for Google.
+    pub other: Option<HashMap<String, Value>>,
+}
+
+fn address(provider: &Provider, key: &Key, model: &str) -> String {
+    if provider == &Provider::Google {
+        format!(
+            "{}/v1beta/models/{model}:batchEmbedContents",
+            crate::domain(provider, key)
+        )
+    } else {
+        format!("{}/embeddings", crate::openai_base_url(provider, key))
+    }
+}
+
+fn body(
+    provider: &Provider,
+    config: &EmbeddingsConfig,
+    model: &str,
+    input: &[&str],
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let body = if provider == &Provider::Google {
+        if config.encoding_format == Some(EncodingFormat::Base64) {
+            return Err("Google does not support the base64 encoding format".into());
+        }
+        let requests: Vec<Value> = input
+            .iter()
+            .map(|text| {
+                let mut request = json!({
+                    "model": format!("models/{model}"),
+                    "content": {"parts": [{"text": text}]},
+                });
+                if let Some(dimensions) = config.dimensions {
+                    request["outputDimensionality"] = Value::from(dimensions);
+                }
+                // Options such as `taskType` are set per request.
+                if let Some(other) = &config.other {
+                    for (key, value) in other {
+                        request[key] = value.clone();
+                    }
+                }
+                request
+            })
+            .collect();
+        json!({ "requests": requests })
+    } else {
+        let mut body = json!({
+            "model": model,
+            "input": input,
+        });
+        if let Some(dimensions) = config.dimensions {
+            body["dimensions"] = Value::from(dimensions);
+        }
+        if let Some(encoding_format) = config.encoding_format {
+            body["encoding_format"] = serde_json::to_value(encoding_format)?;
+        }
+        if let Some(other) = &config.other {
+            for (key, value) in other {
+                body[key] = value.clone();
+            }
+        }
+        body
+    };
+    Ok(body)
+}
+
+/// The embedding of one input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Embedding {
+    /// Position of the input that this embedding belongs to.
+    pub index: usize,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingsUsage {
+    pub prompt_tokens: u64,
+    pub total_tokens: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Embeddings {
+    pub model: Option<String>,
+    /// The embeddings in the same order as the inputs.
+    pub data: Vec<Embedding>,
+    pub usage: Option<EmbeddingsUsage>,
+}
+
+/// Decode little-endian `f32` values from base64.
+fn decode_base64(encoded: &str) -> Result<Vec<f32>, Box<dyn Error + Send + Sync>> {
+    let bytes = BASE64_STANDARD.decode(encoded)?;
+    if bytes.len() % 4 != 0 {
+        return Err(format!("invalid base64 embedding of {} bytes", bytes.len()).into());
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+fn parse_vector(value: &Value) -> Result<Vec<f32>, Box<dyn Error + Send + Sync>> {
+    match value {
+        Value::String(encoded) => decode_base64(encoded),
+        Value::Array(_) => Ok(serde_json::from_value(value.clone())?),
+        _ => Err(format!("unexpected embedding: {value}").into()),
+    }
+}
+
+pub struct EmbeddingsResponse {
+    provider: Provider,
+    resp: Bytes,
+}
+
+impl EmbeddingsResponse {
+    pub fn bytes(&self) -> &Bytes {
+        &self.resp
+    }
+    pub fn raw_value(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        Ok(serde_json::from_slice::<Value>(&self.resp)?)
+    }
+    pub fn structured(&self) -> Result<Embeddings, Box<dyn Error + Send + Sync>> {
+        let resp = self.raw_value()?;
+        if resp.get("error").is_some() || resp.get("detail").is_some() {
+            return Err(crate::extract_error(&resp).into());
+        }
+        if self.provider == Provider::Google {
+            let embeddings = resp["embeddings"]
+                .as_array()
+                .ok_or_else(|| format!("no embeddings in response: {resp}"))?;
+            let data = embeddings
+                .iter()
+                .enumerate()
+                .map(|(index, embedding)| {
+                    Ok(Embedding {
+                        index,
+                        embedding: parse_vector(&embedding["values"])?,
+                    })
+                })
+                .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+            return Ok(Embeddings {
+                model: None,
+                data,
+                usage: None,
+            });
+        }
+        let items = resp["data"]
+            .as_array()
+            .ok_or_else(|| format!("no data in response: {resp}"))?;
+        let mut data = items
+            .iter()
+            .enumerate()
+            .map(|(position, item)| {
+                let index = item["index"].as_u64().map(|i| i as usize);
+                Ok(Embedding {
+                    index: index.unwrap_or(position),
+                    embedding: parse_vector(&item["embedding"])?,
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+        data.sort_by_key(|embedding| embedding.index);
+        let usage = match resp.get("usage") {
+            Some(usage) if !usage.is_null() => serde_json::from_value(usage.clone()).ok(),
+            _ => None,
+        };
+        Ok(Embeddings {
+            model: resp["model"].as_str().map(|model| model.to_string()),
+            data,
+            usage,
+        })
+    }
+}
+
+/// Get the embeddings for a batch of inputs.
+pub async fn embeddings(
+    provider: &Provider,
+    key: &Key,
+    config: &EmbeddingsConfig,
+    model: &str,
+    input: &[&str],
+) -> Result<EmbeddingsResponse, Box<dyn Error + Send + Sync>> {
+    let address = address(provider, key, model);
+    let headers = if provider == &Provider::Google {
+        crate::key_header(key, "x-goog-api-key")?
+    } else {
+        request_headers(key)?
+    };
+    let body = body(provider, config, model, input)?;
+    let client = reqwest::Client::new();
+    let req = client.post(address).headers(headers).json(&body);
+    let resp = crate::http::send(req).await?;
+    let embeddings_response = EmbeddingsResponse {
+        provider: provider.clone(),
+        resp: resp.bytes().await?,
+    };
+    Ok(embeddings_response)
+}
+
+#[test]
+fn test_decode_base64() {
+    let values = [0.5f32, -1.25];
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let encoded = BASE64_STANDARD.encode(bytes);
+    assert_eq!(decode_base64(&encoded).unwrap(), values);
+}
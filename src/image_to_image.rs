@@ -0,0 +1,222 @@
+//! Image-to-image.
+//!
+//! Functionality related to editing images and creating variations of them.
+//!
+//! OpenAI uses the multipart `/images/edits` and `/images/variations`
+//! endpoints. Hyperbolic takes a source image and a `strength` in its
+//! `/v1/image/generation` endpoint.
+
+use crate::http::Multipart;
+use crate::request_headers;
+use crate::text_to_image::Image;
+use crate::text_to_image::ImageResponse;
+use crate::text_to_image::ImageResponseFormat;
+use crate::Key;
+use crate::Provider;
+use base64::prelude::*;
+use reqwest;
+use reqwest::header::HeaderValue;
+use reqwest::header::CONTENT_TYPE;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Configuration for image-to-image.
+///
+/// Options that a provider does not support result in an error instead of
+/// being dropped. Options that are not listed here can be passed via `other`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImageEditConfig {
+    pub model: String,
+    /// Number of images.
+    pub n: Option<u32>,
+    /// Size such as `1024x1024`.
+    pub size: Option<String>,
+    pub response_format: Option<ImageResponseFormat>,
+    /// How much to change the source image from 0 (not at all) to 1.
+    ///
+    /// Only supported by Hyperbolic.
+    pub strength: Option<f64>,
+    pub steps: Option<u32>,
+    pub cfg_scale: Option<f64>,
+    pub height: Option<u32>,
+    pub width: Option<u32>,
+    pub other: Option<HashMap<String, Value>>,
+}
+
+fn openai_form(
+    provider: &Provider,
+    config: &ImageEditConfig,
+) -> Result<Multipart, Box<dyn Error + Send + Sync>> {
+    crate::reject(
+        provider,
+        "image-to-image",
+        &[
+            ("strength", config.strength.is_some()),
+            ("steps", config.steps.is_some()),
+            ("cfg_scale", config.cfg_scale.is_some()),
+            ("height", config.height.is_some()),
+            ("width", config.width.is_some()),
+        ],
+    )?;
+    let mut form = Multipart::new();
+    if !config.model.is_empty() {
+        form = form.text("model", &config.model);
+    }
+    if let Some(n) = config.n {
+        form = form.text("n", n);
+    }
+    if let Some(size) = &config.size {
+        form = form.text("size", size);
+    }
+    if let Some(response_format) = config.response_format {
+        let response_format = match response_format {
+            ImageResponseFormat::Url => "url",
+            ImageResponseFormat::B64Json => "b64_json",
+        };
+        form = form.text("response_format", response_format);
+    }
+    if let Some(other) = &config.other {
+        let mut other: Vec<_> = other.iter().collect();
+        other.sort_by_key(|(key, _)| key.as_str());
+        for (key, value) in other {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            form = form.text(key, value);
+        }
+    }
+    Ok(form)
+}
+
+fn with_image(form: Multipart, name: &str, image: &Image) -> Multipart {
+    let file_name = format!("{name}.{}", image.filetype);
+    let content_type = image
+        .format
+        .map_or("application/octet-stream", |format| format.mime_type());
+    form.file(name, &file_name, content_type, &image.image)
+}
+
+async fn send_form(
+    key: &Key,
+    endpoint: &str,
+    form: Multipart,
+) -> Result<ImageResponse, Box<dyn Error + Send + Sync>> {
+    let address = format!(
+        "{}/images/{endpoint}",
+        crate::openai_base_url(&key.provider, key)
+    );
+    let (content_type, body) = form.finish();
+    let mut headers = request_headers(key)?;
+    headers.insert(CONTENT_TYPE, HeaderValue::from_str(&content_type)?);
+    let client = reqwest::Client::new();
+    let req = client.post(address).headers(headers).body(body);
+    let resp = crate::http::send(req).await?;
+    Ok(ImageResponse {
+        provider: key.provider.clone(),
+        resp: resp.bytes().await?,
+    })
+}
+
+fn hyperbolic_body(
+    provider: &Provider,
+    config: &ImageEditConfig,
+    image: &Image,
+    prompt: &str,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    crate::reject(
+        provider,
+        "image-to-image",
+        &[
+            ("size", config.size.is_some()),
+            ("response_format", config.response_format.is_some()),
+            ("n", config.n.is_some()),
+        ],
+    )?;
+    let mut body = json!({
+        "model_name": config.model,
+        "prompt": prompt,
+        "image": BASE64_STANDARD.encode(&image.image),
+    });
+    if let Some(strength) = config.strength {
+        body["strength"] = Value::from(strength);
+    }
+    if let Some(steps) = config.steps {
+        body["steps"] = Value::from(steps);
+    }
+    if let Some(cfg_scale) = config.cfg_scale {
+        body["cfg_scale"] = Value::from(cfg_scale);
+    }
+    if let Some(height) = config.height {
+        body["height"] = Value::from(height);
+    }
+    if let Some(width) = config.width {
+        body["width"] = Value::from(width);
+    }
+    if let Some(other) = &config.other {
+        for (key, value) in other {
+            body[key] = value.clone();
+        }
+    }
+    Ok(body)
+}
+
+/// Edit `image` according to `prompt`.
+///
+/// With OpenAI, the transparent areas of `mask` mark which parts of the image
+/// to edit. Hyperbolic does not support masks.
+pub async fn edit_image(
+    key: &Key,
+    config: &ImageEditConfig,
+    image: &Image,
+    mask: Option<&Image>,
+    prompt: &str,
+) -> Result<ImageResponse, Box<dyn Error + Send + Sync>> {
+    match &key.provider {
+        Provider::Hyperbolic => {
+            if mask.is_some() {
+                return Err("Hyperbolic does not support masks".into());
+            }
+            let address = format!("{}/v1/image/generation", crate::domain(&key.provider, key));
+            let body = hyperbolic_body(&key.provider, config, image, prompt)?;
+            let client = reqwest::Client::new();
+            let req = client
+                .post(address)
+                .headers(request_headers(key)?)
+                .json(&body);
+            let resp = crate::http::send(req).await?;
+            Ok(ImageResponse {
+                provider: key.provider.clone(),
+                resp: resp.bytes().await?,
+            })
+        }
+        Provider::OpenAI | Provider::OpenAICompatible(_) => {
+            let mut form = openai_form(&key.provider, config)?.text("prompt", prompt);
+            form = with_image(form, "image", image);
+            if let Some(mask) = mask {
+                form = with_image(form, "mask", mask);
+            }
+            send_form(key, "edits", form).await
+        }
+        provider => Err(format!("Unsupported image edit provider: {provider}").into()),
+    }
+}
+
+/// Create variations of `image`.
+pub async fn image_variations(
+    key: &Key,
+    config: &ImageEditConfig,
+    image: &Image,
+) -> Result<ImageResponse, Box<dyn Error + Send + Sync>> {
+    match &key.provider {
+        Provider::OpenAI | Provider::OpenAICompatible(_) => {
+            let form = with_image(openai_form(&key.provider, config)?, "image", image);
+            send_form(key, "variations", form).await
+        }
+        provider => Err(format!("Unsupported image variations provider: {provider}").into()),
+    }
+}
@@ -0,0 +1,292 @@
+//! Jobs.
+//!
+//! Functionality related to long-running generations that providers run as
+//! asynchronous jobs, such as Replicate predictions. A job is submitted,
+//! polled until it finishes, and its outputs are then downloaded:
+//!
+//! ```no_run
+//! use serde_json::json;
+//! use transformrs::jobs;
+//! use transformrs::jobs::PollConfig;
+//! use transformrs::Provider;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let keys = transformrs::load_keys(".env");
+//! let key = keys.for_provider(&Provider::Replicate).unwrap();
+//! let model = "black-forest-labs/flux-schnell";
+//! let input = json!({"prompt": "A sunset"});
+//! let job = jobs::submit(&key, model, &input).await.unwrap();
+//! let job = jobs::wait(&key, &job, &PollConfig::default()).await.unwrap();
+//! let outputs = job.download_outputs().await.unwrap();
+//! # }
+//! ```
+//!
+//! The functions take the provider from the key, so the same code works for
+//! every provider that supports jobs. Only Replicate does for now.
+
+use crate::Key;
+use crate::Provider;
+use bytes::Bytes;
+use reqwest;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::time::Duration;
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Starting,
+    Processing,
+    Succeeded,
+    Failed,
+    Canceled,
+    Aborted,
+}
+
+impl JobStatus {
+    /// Whether the job has stopped, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Succeeded | JobStatus::Failed | JobStatus::Canceled | JobStatus::Aborted
+        )
+    }
+}
+
+/// A job, such as a Replicate prediction.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    pub status: JobStatus,
+    /// The output, which is a URL, a list of URLs, or another value depending
+    /// on the model.
+    #[serde(default)]
+    pub output: Option<Value>,
+    #[serde(default)]
+    pub error: Option<Value>,
+    #[serde(default)]
+    pub logs: Option<String>,
+}
+
+impl Job {
+    /// The output as a list of strings, which are usually URLs.
+    pub fn outputs(&self) -> Vec<String> {
+        match &self.output {
+            Some(Value::String(output)) => vec![output.clone()],
+            Some(Value::Array(outputs)) => outputs
+                .iter()
+                .filter_map(|output| output.as_str().map(|output| output.to_string()))
+                .collect(),
+            _ => vec![],
+        }
+    }
+    /// An error if the job did not succeed.
+    pub fn check(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self.status {
+            JobStatus::Succeeded => Ok(()),
+            status => {
+                let error = match &self.error {
+                    Some(Value::String(error)) => error.clone(),
+                    Some(error) if !error.is_null() => error.to_string(),
+                    _ => "no error message".to_string(),
+                };
+                Err(format!("job {} is {status:?}: {error}", self.id).into())
+            }
+        }
+    }
+    /// Download the outputs of a succeeded job.
+    pub async fn download_outputs(&self) -> Result<Vec<Bytes>, Box<dyn Error + Send + Sync>> {
+        self.check()?;
+        let client = reqwest::Client::new();
+        let mut outputs = vec![];
+        for url in self.outputs() {
+            let resp = crate::http::send(client.get(&url)).await?;
+            let status = resp.status();
+            if !status.is_success() {
+                return Err(format!("downloading output failed with status {status}").into());
+            }
+            outputs.push(resp.bytes().await?);
+        }
+        Ok(outputs)
+    }
+}
+
+/// How often to poll a job.
+///
+/// The interval starts at `initial_interval` and is multiplied by
+/// `multiplier` after each poll up to `max_interval`. Intervals are at least
+/// 10 milliseconds, and a multiplier that is below 1, not finite, or
+/// overflows the interval makes the job poll every `max_interval`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PollConfig {
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub multiplier: f64,
+    /// Give up after this long. The job is not canceled.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(5),
+            multiplier: 1.5,
+            timeout: Some(Duration::from_secs(600)),
+        }
+    }
+}
+
+/// Shortest interval between polls, so that a zero interval does not
+/// busy-poll the provider.
+const MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+impl PollConfig {
+    fn max(&self) -> Duration {
+        self.max_interval.max(MIN_INTERVAL)
+    }
+    fn first_interval(&self) -> Duration {
+        self.initial_interval.clamp(MIN_INTERVAL, self.max())
+    }
+    fn next_interval(&self, interval: Duration) -> Duration {
+        let next = if self.multiplier >= 1.0 {
+            // Fails for an infinite multiplier or when the result overflows.
+            Duration::try_from_secs_f64(interval.as_secs_f64() * self.multiplier)
+                .unwrap_or(self.max())
+        } else {
+            self.max()
+        };
+        next.clamp(MIN_INTERVAL, self.max())
+    }
+}
+
+fn unsupported(provider: &Provider) -> Box<dyn Error + Send + Sync> {
+    format!("Unsupported jobs provider: {provider}").into()
+}
+
+/// Create a job that runs `model` on `input`.
+///
+/// For Replicate, the model is either `owner/name` for the latest version of
+/// an official model or `owner/name:version` for a specific version.
+pub async fn submit(
+    key: &Key,
+    model: &str,
+    input: &Value,
+) -> Result<Job, Box<dyn Error + Send + Sync>> {
+    match key.provider {
+        Provider::Replicate => crate::replicate::submit(key, model, input).await,
+        ref provider => Err(unsupported(provider)),
+    }
+}
+
+/// Get the current state of the job with `id`.
+pub async fn get(key: &Key, id: &str) -> Result<Job, Box<dyn Error + Send + Sync>> {
+    match key.provider {
+        Provider::Replicate => crate::replicate::get(key, id).await,
+        ref provider => Err(unsupported(provider)),
+    }
+}
+
+/// Cancel the job with `id`.
+pub async fn cancel(key: &Key, id: &str) -> Result<Job, Box<dyn Error + Send + Sync>> {
+    match key.provider {
+        Provider::Replicate => crate::replicate::cancel(key, id).await,
+        ref provider => Err(unsupported(provider)),
+    }
+}
+
+/// Poll the job until it is finished.
+///
+/// The finished job is returned whether it succeeded or not; use [Job::check]
+/// to turn a failure into an error.
+pub async fn wait(
+    key: &Key,
+    job: &Job,
+    config: &PollConfig,
+) -> Result<Job, Box<dyn Error + Send + Sync>> {
+    let start = Instant::now();
+    let mut interval = config.first_interval();
+    let mut job = job.clone();
+    while !job.status.is_finished() {
+        if let Some(timeout) = config.timeout {
+            let remaining = timeout.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                return Err(format!("job {} did not finish within {timeout:?}", job.id).into());
+            }
+            interval = interval.min(remaining);
+        }
+        tokio::time::sleep(interval).await;
+        job = get(key, &job.id).await?;
+        tracing::debug!("Job {} is {:?}", job.id, job.status);
+        interval = config.next_interval(interval);
+    }
+    Ok(job)
+}
+
+/// Submit a job and wait until it is finished.
+pub async fn run(
+    key: &Key,
+    model: &str,
+    input: &Value,
+    config: &PollConfig,
+) -> Result<Job, Box<dyn Error + Send + Sync>> {
+    let job = submit(key, model, input).await?;
+    wait(key, &job, config).await
+}
+
+#[test]
+fn test_outputs() {
+    let job: Job = serde_json::from_value(serde_json::json!({
+        "id": "abc",
+        "status": "succeeded",
+        "output": ["https://example.com/0.png", "https://example.com/1.png"],
+    }))
+    .unwrap();
+    assert!(job.status.is_finished());
+    assert_eq!(job.outputs().len(), 2);
+    assert!(job.check().is_ok());
+    let job: Job = serde_json::from_value(serde_json::json!({
+        "id": "abc",
+        "status": "failed",
+        "error": "out of memory",
+    }))
+    .unwrap();
+    assert_eq!(job.outputs(), Vec::<String>::new());
+    assert_eq!(
+        job.check().unwrap_err().to_string(),
+        "job abc is Failed: out of memory"
+    );
+}
+
+#[test]
+fn test_poll_intervals() {
+    let config = PollConfig::default();
+    assert_eq!(config.first_interval(), Duration::from_millis(500));
+    let next = config.next_interval(Duration::from_millis(500));
+    assert_eq!(next, Duration::from_millis(750));
+    assert_eq!(
+        config.next_interval(Duration::from_secs(4)),
+        config.max_interval
+    );
+
+    let zero = PollConfig {
+        initial_interval: Duration::ZERO,
+        max_interval: Duration::ZERO,
+        ..config.clone()
+    };
+    assert_eq!(zero.first_interval(), MIN_INTERVAL);
+    assert_eq!(zero.next_interval(MIN_INTERVAL), MIN_INTERVAL);
+
+    for multiplier in [-1.0, 0.5, f64::NAN, f64::INFINITY, f64::MAX] {
+        let config = PollConfig {
+            multiplier,
+            ..config.clone()
+        };
+        let next = config.next_interval(Duration::from_secs(1));
+        assert_eq!(next, config.max_interval, "multiplier {multiplier}");
+    }
+}
@@ -1,43 +1,128 @@
 //! Transformrs is a Rust library for interacting with various AI APIs.
 
+pub mod audio;
+pub mod cassette;
 pub mod chat;
+pub mod dialogue;
+pub mod dotenv;
+pub mod embeddings;
+mod http;
+pub mod image_to_image;
+pub mod jobs;
+#[cfg(feature = "mock-server")]
+pub mod mock_server;
 pub mod models;
+pub mod moderation;
+mod replicate;
+pub mod rerank;
+pub mod scripted;
+pub mod secret;
+pub mod speech_to_text;
+pub mod subtitles;
 pub mod text_to_image;
 pub mod text_to_speech;
 
+pub use secret::Secret;
+
 use base64::prelude::BASE64_STANDARD;
 use base64::Engine;
 use reqwest::header::HeaderMap;
 use reqwest::header::HeaderValue;
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::Value;
 use std::collections::HashMap;
 use std::error::Error;
-use std::fs::File;
-use std::io::Read;
+use std::path::PathBuf;
+use std::process::Command;
 use std::str::FromStr;
+use zeroize::Zeroize;
 
 pub(crate) fn request_headers(key: &Key) -> Result<HeaderMap, Box<dyn Error + Send + Sync>> {
     let mut headers = HeaderMap::new();
-    headers.insert(
-        "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", key.key))?,
-    );
+    let mut authorization = HeaderValue::from_str(&format!("Bearer {}", key.key.expose()))?;
+    authorization.set_sensitive(true);
+    headers.insert("Authorization", authorization);
     headers.insert("Content-Type", HeaderValue::from_str("application/json")?);
     Ok(headers)
 }
 
-pub(crate) fn openai_base_url(provider: &Provider) -> String {
+/// Headers for providers that take the key in `name` instead of `Authorization`.
+pub(crate) fn key_header(
+    key: &Key,
+    name: &'static str,
+) -> Result<HeaderMap, Box<dyn Error + Send + Sync>> {
+    let mut headers = request_headers(key)?;
+    headers.remove("Authorization");
+    let mut value = HeaderValue::from_str(key.key.expose())?;
+    value.set_sensitive(true);
+    headers.insert(name, value);
+    Ok(headers)
+}
+
+/// Return an error for the options that are set but not supported.
+///
+/// `what` names the task in the message, such as "text-to-image".
+pub(crate) fn reject(
+    provider: &Provider,
+    what: &str,
+    options: &[(&str, bool)],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let unsupported: Vec<&str> = options
+        .iter()
+        .filter(|(_, is_set)| *is_set)
+        .map(|(name, _)| *name)
+        .collect();
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        let options = unsupported.join(", ");
+        Err(format!("{provider} does not support the {what} options: {options}").into())
+    }
+}
+
+/// Extract the error message from an error response.
+pub(crate) fn extract_error(body: &Value) -> String {
+    if let Some(error) = body.get("error") {
+        if let Some(message) = error.get("message") {
+            return message
+                .as_str()
+                .unwrap_or(body.to_string().as_str())
+                .to_string();
+        }
+    }
+    if let Some(message) = body.get("message") {
+        return message
+            .as_str()
+            .unwrap_or(body.to_string().as_str())
+            .to_string();
+    }
+    format!("Unknown error: {body}")
+}
+
+/// The domain of `provider`, or the base URL of `key` if it has one.
+pub(crate) fn domain(provider: &Provider, key: &Key) -> String {
+    match &key.base_url {
+        Some(base_url) => base_url.clone(),
+        None => provider.domain(),
+    }
+}
+
+pub(crate) fn openai_base_url(provider: &Provider, key: &Key) -> String {
+    let domain = domain(provider, key);
     match provider {
-        Provider::Google => format!("{}/v1beta/openai", provider.domain()),
-        Provider::Groq => format!("{}/openai/v1", provider.domain()),
-        Provider::Hyperbolic => format!("{}/v1", provider.domain()),
-        Provider::Mistral => format!("{}/v1", provider.domain()),
-        Provider::OpenAI => format!("{}/v1", provider.domain()),
-        Provider::OpenAICompatible(domain) => domain.clone(),
-        Provider::SambaNova => format!("{}/v1", provider.domain()),
-        Provider::TogetherAI => format!("{}/v1", provider.domain()),
-        _ => format!("{}/v1/openai", provider.domain()),
+        Provider::Google => format!("{domain}/v1beta/openai"),
+        Provider::Cohere => format!("{domain}/compatibility/v1"),
+        Provider::Fireworks => format!("{domain}/inference/v1"),
+        Provider::Groq => format!("{domain}/openai/v1"),
+        Provider::Hyperbolic => format!("{domain}/v1"),
+        Provider::Mistral => format!("{domain}/v1"),
+        Provider::Nebius => format!("{domain}/v1"),
+        Provider::OpenAI => format!("{domain}/v1"),
+        Provider::OpenAICompatible(_) => domain,
+        Provider::SambaNova => format!("{domain}/v1"),
+        Provider::TogetherAI => format!("{domain}/v1"),
+        _ => format!("{domain}/v1/openai"),
     }
 }
 
@@ -47,6 +132,7 @@ pub enum Provider {
     Amazon,
     Azure,
     Cerebras,
+    Cohere,
     DeepInfra,
     ElevenLabs,
     Fireworks,
@@ -62,6 +148,7 @@ pub enum Provider {
     ///
     /// For example, "https://api.deepinfra.com/v1/openai".
     OpenAICompatible(String),
+    Replicate,
     SambaNova,
     TogetherAI,
 }
@@ -78,6 +165,7 @@ impl Provider {
             Provider::Amazon => "https://api.amazon.com",
             Provider::Azure => "https://api.azure.com",
             Provider::Cerebras => "https://api.cerebras.ai",
+            Provider::Cohere => "https://api.cohere.com",
             Provider::DeepInfra => "https://api.deepinfra.com",
             Provider::ElevenLabs => "https://api.elevenlabs.io",
             Provider::Fireworks => "https://api.fireworks.ai",
@@ -86,10 +174,11 @@ impl Provider {
             Provider::Groq => "https://api.groq.com",
             Provider::Hyperbolic => "https://api.hyperbolic.xyz",
             Provider::Mistral => "https://api.mistral.ai",
-            Provider::Nebius => "https://api.nebi.us",
+            Provider::Nebius => "https://api.studio.nebius.com",
             Provider::Novita => "https://api.novita.ai",
             Provider::OpenAI => "https://api.openai.com",
             Provider::OpenAICompatible(base_url) => base_url,
+            Provider::Replicate => "https://api.replicate.com",
             Provider::SambaNova => "https://api.sambanova.ai",
             Provider::TogetherAI => "https://api.together.xyz",
         }
@@ -125,6 +214,7 @@ impl FromStr for Provider {
             "amazon" => Ok(Provider::Amazon),
             "azure" => Ok(Provider::Azure),
             "cerebras" => Ok(Provider::Cerebras),
+            "cohere" => Ok(Provider::Cohere),
             "deepinfra" => Ok(Provider::DeepInfra),
             "elevenlabs" => Ok(Provider::ElevenLabs),
             "fireworks" => Ok(Provider::Fireworks),
@@ -136,6 +226,7 @@ impl FromStr for Provider {
             "nebi" => Ok(Provider::Nebius),
             "novita" => Ok(Provider::Novita),
             "openai" => Ok(Provider::OpenAI),
+            "replicate" => Ok(Provider::Replicate),
             "sambanova" => Ok(Provider::SambaNova),
             "togetherai" => Ok(Provider::TogetherAI),
             _ => Err(format!("Unsupported provider: {s}.").into()),
@@ -261,10 +352,52 @@ impl Message {
     }
 }
 
+/// Where a [Key] was loaded from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeySource {
+    /// An environment variable such as `OPENAI_KEY`.
+    Environment,
+    /// The dotenv file that was passed to [load_keys].
+    DotEnv(PathBuf),
+    /// The user config file at `$XDG_CONFIG_HOME/transformrs/keys.env`.
+    ConfigFile(PathBuf),
+    /// The output of the command in a `*_KEY_CMD` variable such as
+    /// `OPENAI_KEY_CMD="pass show openai"` from the environment or the config
+    /// file.
+    Command(String),
+    /// Created in code via [Key::new].
+    Manual,
+}
+
 #[derive(Clone, Debug)]
 pub struct Key {
     pub provider: Provider,
-    pub key: String,
+    pub key: Secret,
+    pub source: KeySource,
+    /// Where the requests with this key are sent instead of the domain of the
+    /// provider. Set via [Key::with_base_url].
+    pub base_url: Option<String>,
+}
+
+impl Key {
+    pub fn new(provider: Provider, key: &str) -> Self {
+        Self {
+            provider,
+            key: Secret::new(key),
+            source: KeySource::Manual,
+            base_url: None,
+        }
+    }
+    /// Send the requests with this key to `base_url` instead of the domain of
+    /// the provider, for example, to route them via a proxy or to the mock
+    /// server of the `mock-server` feature.
+    ///
+    /// The paths of the provider are kept, so with Groq, chat requests go to
+    /// `{base_url}/openai/v1/chat/completions`.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.trim_end_matches('/').to_string());
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -287,35 +420,44 @@ impl Keys {
     }
 }
 
-fn load_env_file(path: &str) -> HashMap<String, String> {
-    let mut env_content = String::new();
-    if let Ok(mut file) = File::open(path) {
-        file.read_to_string(&mut env_content)
-            .expect("Failed to read .env file");
-    }
-    env_content
-        .lines()
-        .filter_map(|line| {
-            let mut parts = line.split('=');
-            if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
-                Some((key.to_string(), value.to_string()))
-            } else {
-                None
-            }
-        })
-        .collect()
+/// Path of the user config file, which uses the same format as `.env`.
+fn config_file() -> Option<PathBuf> {
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("transformrs").join("keys.env"))
 }
 
-/// Load the keys from either the .env file or environment variables.
-pub fn load_keys(path: &str) -> Keys {
-    let env_map = load_env_file(path);
-
-    let mut keys = vec![];
+fn run_key_command(command: &str) -> Result<Secret, Box<dyn Error + Send + Sync>> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").arg("/C").arg(command).output()?
+    } else {
+        Command::new("sh").arg("-c").arg(command).output()?
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "key command `{command}` failed ({}): {}",
+            output.status,
+            stderr.trim()
+        )
+        .into());
+    }
+    let stdout = Secret::from(String::from_utf8(output.stdout)?);
+    let key = Secret::new(stdout.expose().trim());
+    if key.is_empty() {
+        return Err(format!("key command `{command}` returned no output").into());
+    }
+    Ok(key)
+}
 
-    let providers = [
+fn providers() -> Vec<Provider> {
+    vec![
         Provider::Amazon,
         Provider::Azure,
         Provider::Cerebras,
+        Provider::Cohere,
         Provider::DeepInfra,
         Provider::ElevenLabs,
         Provider::Fireworks,
@@ -328,21 +470,149 @@ pub fn load_keys(path: &str) -> Keys {
         Provider::Novita,
         Provider::OpenAI,
         Provider::OpenAICompatible("".to_string()),
+        Provider::Replicate,
         Provider::SambaNova,
         Provider::TogetherAI,
-    ];
-    for provider in providers {
-        if let Ok(key_value) = std::env::var(provider.key_name()) {
-            keys.push(Key {
-                provider: provider.clone(),
-                key: key_value,
-            });
-        } else if let Some(key_value) = env_map.get(&provider.key_name()) {
+    ]
+}
+
+/// Collect the keys from all sources.
+///
+/// Errors are passed to `on_error`, which decides whether loading should stop.
+fn collect_keys(
+    path: &str,
+    on_error: impl Fn(Box<dyn Error + Send + Sync>) -> Result<(), Box<dyn Error + Send + Sync>>,
+) -> Result<Keys, Box<dyn Error + Send + Sync>> {
+    let env_path = PathBuf::from(path);
+    let mut env_map = match dotenv::load_with(&env_path, &on_error) {
+        Ok(map) => map,
+        Err(e) => {
+            on_error(e)?;
+            HashMap::new()
+        }
+    };
+    let config_path = config_file();
+    let mut config_map = match &config_path {
+        Some(config_path) => match dotenv::load_with(config_path, &on_error) {
+            Ok(map) => map,
+            Err(e) => {
+                on_error(e)?;
+                HashMap::new()
+            }
+        },
+        None => HashMap::new(),
+    };
+    let lookup = |name: &str| -> Option<(String, KeySource)> {
+        if let Ok(value) = std::env::var(name) {
+            Some((value, KeySource::Environment))
+        } else if let Some(value) = env_map.get(name) {
+            Some((value.clone(), KeySource::DotEnv(env_path.clone())))
+        } else {
+            let config_path = config_path.clone()?;
+            let value = config_map.get(name)?;
+            Some((value.clone(), KeySource::ConfigFile(config_path)))
+        }
+    };
+    // A dotenv file may come with a cloned repository, so running a command
+    // from it would execute code from whoever wrote the repository.
+    let command_lookup = |name: &str| -> Option<String> {
+        if let Ok(value) = std::env::var(name) {
+            return Some(value);
+        }
+        if env_map.contains_key(name) {
+            tracing::warn!(
+                "Ignoring {name} in {}; commands are only read from the environment and the config file",
+                env_path.display()
+            );
+        }
+        config_map.get(name).cloned()
+    };
+
+    let mut keys = vec![];
+    for provider in providers() {
+        let key_name = provider.key_name();
+        if let Some((key, source)) = lookup(&key_name) {
             keys.push(Key {
-                provider: provider.clone(),
-                key: key_value.to_string(),
+                provider,
+                key: Secret::from(key),
+                source,
+                base_url: None,
             });
+        } else if let Some(command) = command_lookup(&format!("{key_name}_CMD")) {
+            match run_key_command(&command) {
+                Ok(key) => keys.push(Key {
+                    provider,
+                    key,
+                    source: KeySource::Command(command),
+                    base_url: None,
+                }),
+                Err(e) => on_error(e)?,
+            }
         }
     }
-    Keys { keys }
+    // The maps hold copies of the keys.
+    for value in env_map.values_mut().chain(config_map.values_mut()) {
+        value.zeroize();
+    }
+    Ok(Keys { keys })
+}
+
+/// Load the keys for all providers.
+///
+/// For each provider, the key (for example, `OPENAI_KEY`) is taken from the
+/// first source that defines it:
+///
+/// 1. The environment variables.
+/// 2. The dotenv file at `path`.
+/// 3. The config file at `$XDG_CONFIG_HOME/transformrs/keys.env`
+///    (defaults to `~/.config/transformrs/keys.env`).
+/// 4. The output of the command in `*_KEY_CMD` (for example,
+///    `OPENAI_KEY_CMD="pass show openai"`), which is only read from the
+///    environment variables and the config file. A `*_KEY_CMD` in the dotenv
+///    file is ignored, since that file may come from an untrusted repository.
+///
+/// Sources that fail to load are skipped with a warning, and so are the
+/// malformed lines of the dotenv and config files. Use [try_load_keys] to get
+/// an error instead.
+pub fn load_keys(path: &str) -> Keys {
+    let result = collect_keys(path, |e| {
+        tracing::warn!("Skipping key source: {e}");
+        Ok(())
+    });
+    result.expect("errors are skipped")
+}
+
+/// Load the keys like [load_keys], but return the first error.
+pub fn try_load_keys(path: &str) -> Result<Keys, Box<dyn Error + Send + Sync>> {
+    collect_keys(path, Err)
+}
+
+#[test]
+fn test_key_command_not_from_dotenv() {
+    let dir = std::env::temp_dir().join(format!("transformrs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let marker = dir.join("marker");
+    let env_path = dir.join(".env");
+    let command = format!("touch {} && echo key", marker.display());
+    std::fs::write(&env_path, format!("SAMBANOVA_KEY_CMD=\"{command}\"\n")).unwrap();
+    let keys = try_load_keys(env_path.to_str().unwrap()).unwrap();
+    assert!(!marker.exists());
+    let key = keys.for_provider(&Provider::SambaNova);
+    assert!(!matches!(key, Some(key) if key.source == KeySource::Command(command)));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_load_keys_skips_malformed_lines() {
+    let dir = std::env::temp_dir().join(format!("transformrs-lines-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let env_path = dir.join(".env");
+    std::fs::write(&env_path, "not a variable\nNOVITA_KEY=nv-123\n").unwrap();
+    let path = env_path.to_str().unwrap();
+    let keys = load_keys(path);
+    let key = keys.for_provider(&Provider::Novita).unwrap();
+    assert_eq!(key.source, KeySource::DotEnv(env_path.clone()));
+    let err = try_load_keys(path).unwrap_err();
+    assert!(err.to_string().contains("line 1"));
+    std::fs::remove_dir_all(&dir).unwrap();
 }
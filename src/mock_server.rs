@@ -0,0 +1,1009 @@
+//! Mock server.
+//!
+//! A local stand-in for the providers, for integration tests that should not
+//! depend on keys or network access. This module requires the `mock-server`
+//! feature.
+//!
+//! The server emulates the OpenAI-compatible endpoints (chat with and without
+//! streaming, models, embeddings, rerank, moderations, image generations and
+//! edits, audio speech, transcriptions, and translations) and the DeepInfra,
+//! Hyperbolic, ElevenLabs, Google, and Replicate response shapes that this
+//! crate parses. By default, chat responses echo the last message, except for
+//! Llama Guard models, and embeddings are derived from the bytes of the input.
+//! Scripted responses can be queued with [MockServer::enqueue] to inject
+//! errors, latency, or partial SSE frames.
+//!
+//! ```
+//! use transformrs::chat;
+//! use transformrs::mock_server::MockServer;
+//! use transformrs::Key;
+//! use transformrs::Message;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let server = MockServer::start().await.unwrap();
+//! let provider = server.provider();
+//! let key = Key::new(provider.clone(), "mock");
+//! let messages = vec![Message::from_str("user", "Hello!")];
+//! let resp = chat::chat_completion(&provider, &key, "mock-model", &messages)
+//!     .await
+//!     .unwrap();
+//! let content = &resp.structured().unwrap().choices[0].message.content;
+//! assert_eq!(content.to_string(), "Hello!");
+//! # }
+//! ```
+
+use crate::Key;
+use crate::Provider;
+use base64::prelude::*;
+use bytes::Bytes;
+use regex::Regex;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+
+/// A 1x1 transparent PNG.
+pub const PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x00, 0x01, 0x00, 0x00,
+    0x05, 0x00, 0x01, 0x0d, 0x0a, 0x2d, 0xb4, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae,
+    0x42, 0x60, 0x82,
+];
+
+/// A single silent MPEG-1 Layer III frame (128 kbps, 44.1 kHz).
+pub fn mp3() -> Bytes {
+    let mut frame = vec![0xff, 0xfb, 0x90, 0x64];
+    frame.resize(417, 0);
+    Bytes::from(frame)
+}
+
+/// A short silent audio file in the format with the given file extension.
+///
+/// Unknown extensions return MP3.
+pub fn audio(extension: &str) -> Bytes {
+    let silence = [0u8; 480];
+    let audio = match extension {
+        "wav" | "LINEAR16" => {
+            let mut wav = b"RIFF".to_vec();
+            wav.extend_from_slice(&(36 + silence.len() as u32).to_le_bytes());
+            wav.extend_from_slice(b"WAVEfmt ");
+            // 16-bit mono PCM at 24 kHz.
+            wav.extend_from_slice(&[16, 0, 0, 0, 1, 0, 1, 0, 0xc0, 0x5d, 0, 0]);
+            wav.extend_from_slice(&[0x80, 0xbb, 0, 0, 2, 0, 16, 0]);
+            wav.extend_from_slice(b"data");
+            wav.extend_from_slice(&(silence.len() as u32).to_le_bytes());
+            wav.extend_from_slice(&silence);
+            wav
+        }
+        "pcm" | "PCM" => silence.to_vec(),
+        "opus" | "OGG_OPUS" => [b"OggS".as_slice(), &silence].concat(),
+        "flac" => [b"fLaC".as_slice(), &silence].concat(),
+        "aac" => [[0xff, 0xf1, 0x50, 0x80].as_slice(), &silence].concat(),
+        _ => return mp3(),
+    };
+    Bytes::from(audio)
+}
+
+/// A request as received by the server.
+#[derive(Clone, Debug)]
+pub struct ReceivedRequest {
+    pub method: String,
+    /// The path including the query string.
+    pub path: String,
+    /// The headers with lowercase names.
+    pub headers: HashMap<String, String>,
+    pub body: Bytes,
+}
+
+impl ReceivedRequest {
+    pub fn json(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+    /// The parts of a `multipart/form-data` body.
+    pub fn form(&self) -> Result<Vec<FormPart>, Box<dyn Error + Send + Sync>> {
+        let content_type = self
+            .headers
+            .get("content-type")
+            .ok_or("no content-type header")?;
+        let boundary = content_type
+            .split("boundary=")
+            .nth(1)
+            .ok_or_else(|| format!("no boundary in {content_type}"))?;
+        let delimiter = format!("--{boundary}");
+        let mut parts = vec![];
+        for section in split_bytes(&self.body, delimiter.as_bytes())
+            .into_iter()
+            .skip(1)
+        {
+            if section.starts_with(b"--") {
+                break;
+            }
+            let section = section.strip_prefix(b"\r\n").unwrap_or(section);
+            let section = section.strip_suffix(b"\r\n").unwrap_or(section);
+            let head_len = split_bytes(section, b"\r\n\r\n")[0].len();
+            if head_len == section.len() {
+                return Err("invalid multipart section".into());
+            }
+            let (head, body) = (&section[..head_len], &section[head_len + 4..]);
+            let head = String::from_utf8_lossy(head);
+            let param = |name: &str| -> Option<String> {
+                let pattern = format!("{name}=\"");
+                let start = head.find(&pattern)? + pattern.len();
+                let end = head[start..].find('"')? + start;
+                Some(head[start..end].to_string())
+            };
+            let content_type = head.lines().find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                (name.eq_ignore_ascii_case("content-type")).then(|| value.trim().to_string())
+            });
+            parts.push(FormPart {
+                name: param(" name").ok_or("no name in multipart section")?,
+                file_name: param("filename"),
+                content_type,
+                body: Bytes::copy_from_slice(body),
+            });
+        }
+        Ok(parts)
+    }
+    /// The value of the text field `name` in a `multipart/form-data` body.
+    pub fn form_text(&self, name: &str) -> Option<String> {
+        let parts = self.form().ok()?;
+        let part = parts.into_iter().find(|part| part.name == name)?;
+        Some(String::from_utf8_lossy(&part.body).to_string())
+    }
+    fn path_without_query(&self) -> &str {
+        self.path.split('?').next().unwrap_or_default()
+    }
+}
+
+/// A part of a `multipart/form-data` body.
+#[derive(Clone, Debug)]
+pub struct FormPart {
+    pub name: String,
+    pub file_name: Option<String>,
+    pub content_type: Option<String>,
+    pub body: Bytes,
+}
+
+fn split_bytes<'a>(bytes: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut sections = vec![];
+    let mut start = 0;
+    let mut i = 0;
+    while i + delimiter.len() <= bytes.len() {
+        if &bytes[i..i + delimiter.len()] == delimiter {
+            sections.push(&bytes[start..i]);
+            i += delimiter.len();
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    sections.push(&bytes[start..]);
+    sections
+}
+
+/// A scripted response.
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    pub status: u16,
+    pub content_type: String,
+    /// The body, which is sent in separate chunks.
+    pub chunks: Vec<Bytes>,
+    /// Delay before sending each chunk.
+    pub chunk_delay: Duration,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, value: &Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json".to_string(),
+            chunks: vec![Bytes::from(value.to_string())],
+            chunk_delay: Duration::ZERO,
+        }
+    }
+    pub fn bytes(content_type: &str, bytes: &[u8]) -> Self {
+        Self {
+            status: 200,
+            content_type: content_type.to_string(),
+            chunks: vec![Bytes::copy_from_slice(bytes)],
+            chunk_delay: Duration::ZERO,
+        }
+    }
+    /// An error in the shape that OpenAI uses.
+    pub fn error(status: u16, message: &str) -> Self {
+        let body = json!({
+            "error": {
+                "message": message,
+                "type": "mock_error",
+            }
+        });
+        Self::json(status, &body)
+    }
+    /// Server-sent events with one `data:` frame per event followed by
+    /// `data: [DONE]`.
+    pub fn sse(events: &[Value]) -> Self {
+        let chunks = events
+            .iter()
+            .map(|event| format!("data: {event}\n\n"))
+            .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+            .map(Bytes::from)
+            .collect();
+        Self {
+            status: 200,
+            content_type: "text/event-stream".to_string(),
+            chunks,
+            chunk_delay: Duration::ZERO,
+        }
+    }
+    /// Split the body into chunks of at most `size` bytes.
+    ///
+    /// Combined with [MockResponse::with_chunk_delay], this makes SSE frames
+    /// arrive partially.
+    pub fn split_chunks(mut self, size: usize) -> Self {
+        let body: Vec<u8> = self.chunks.concat();
+        self.chunks = body
+            .chunks(size.max(1))
+            .map(Bytes::copy_from_slice)
+            .collect();
+        self
+    }
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = delay;
+        self
+    }
+}
+
+#[derive(Default)]
+struct State {
+    queue: VecDeque<MockResponse>,
+    requests: Vec<ReceivedRequest>,
+    latency: Duration,
+}
+
+/// A local HTTP server that emulates the providers.
+///
+/// The server stops when it is dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Start the server on a free port on localhost.
+    pub async fn start() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let server_state = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, state).await {
+                        tracing::debug!("Mock server connection failed: {e}");
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            addr,
+            state,
+            handle,
+        })
+    }
+    /// The base URL, for example, `http://127.0.0.1:1234`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+    /// An OpenAI-compatible provider that points to this server.
+    pub fn provider(&self) -> Provider {
+        Provider::OpenAICompatible(format!("{}/v1", self.url()))
+    }
+    /// A key for `provider` whose requests are sent to this server.
+    ///
+    /// Only the requests with this key are affected, so tests that run in
+    /// parallel can each use their own server for the same provider.
+    pub fn key(&self, provider: &Provider) -> Key {
+        Key::new(provider.clone(), "mock").with_base_url(&self.url())
+    }
+    /// Respond to the next request with `response` instead of the default.
+    ///
+    /// Queued responses are used in order.
+    pub fn enqueue(&self, response: MockResponse) {
+        self.state.lock().unwrap().queue.push_back(response);
+    }
+    /// Wait this long before responding to each request.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+    /// The requests that were received so far.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn read_request(
+    reader: &mut BufReader<TcpStream>,
+) -> Result<Option<ReceivedRequest>, Box<dyn Error + Send + Sync>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or("missing method")?.to_string();
+    let path = parts.next().ok_or("missing path")?.to_string();
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    let length: usize = match headers.get("content-length") {
+        Some(length) => length.parse()?,
+        None => 0,
+    };
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(ReceivedRequest {
+        method,
+        path,
+        headers,
+        body: Bytes::from(body),
+    }))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    response: &MockResponse,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\ncontent-type: {}\r\nconnection: close\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type
+    );
+    if response.chunks.len() <= 1 {
+        let body = response.chunks.first().cloned().unwrap_or_default();
+        head.push_str(&format!("content-length: {}\r\n\r\n", body.len()));
+        tokio::time::sleep(response.chunk_delay).await;
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(&body).await?;
+    } else {
+        head.push_str("transfer-encoding: chunked\r\n\r\n");
+        stream.write_all(head.as_bytes()).await?;
+        stream.flush().await?;
+        for chunk in &response.chunks {
+            tokio::time::sleep(response.chunk_delay).await;
+            stream
+                .write_all(format!("{:x}\r\n", chunk.len()).as_bytes())
+                .await?;
+            stream.write_all(chunk).await?;
+            stream.write_all(b"\r\n").await?;
+            stream.flush().await?;
+        }
+        stream.write_all(b"0\r\n\r\n").await?;
+    }
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    state: Arc<Mutex<State>>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut reader = BufReader::new(stream);
+    let request = match read_request(&mut reader).await? {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+    tracing::debug!("Mock server received {} {}", request.method, request.path);
+    let (response, latency) = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        let response = match state.queue.pop_front() {
+            Some(response) => response,
+            None => route(&request),
+        };
+        (response, state.latency)
+    };
+    tokio::time::sleep(latency).await;
+    write_response(reader.get_mut(), &response).await
+}
+
+/// The text of the last message in a chat request.
+fn last_message(body: &Value) -> String {
+    let content = body["messages"]
+        .as_array()
+        .and_then(|messages| messages.last())
+        .map(|message| message["content"].clone())
+        .unwrap_or_default();
+    match content {
+        Value::String(text) => text,
+        other => other.to_string(),
+    }
+}
+
+/// Whether the mock considers `text` harmful.
+fn is_violent(text: &str) -> bool {
+    text.to_lowercase().contains("kill")
+}
+
+fn chat(body: &Value) -> MockResponse {
+    let model = body["model"].as_str().unwrap_or("mock-model");
+    let mut content = last_message(body);
+    if model.to_lowercase().contains("guard") {
+        // Llama Guard models answer with a classification.
+        content = if is_violent(&content) {
+            "unsafe\nS1".to_string()
+        } else {
+            "safe".to_string()
+        };
+    }
+    if body["stream"].as_bool().unwrap_or(false) {
+        let chunk = |delta: Value, finish_reason: Value| {
+            json!({
+                "id": "mock",
+                "object": "chat.completion.chunk",
+                "created": 0,
+                "model": model,
+                "choices": [{
+                    "index": 0,
+                    "delta": delta,
+                    "finish_reason": finish_reason,
+                }]
+            })
+        };
+        let mut events = vec![chunk(json!({"role": "assistant"}), Value::Null)];
+        for word in content.split_inclusive(' ') {
+            events.push(chunk(json!({"content": word}), Value::Null));
+        }
+        events.push(chunk(json!({}), json!("stop")));
+        return MockResponse::sse(&events);
+    }
+    let tokens = content.split_whitespace().count() as u64;
+    let completion = json!({
+        "id": "mock",
+        "object": "chat.completion",
+        "created": 0,
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": "stop",
+        }],
+        "usage": {
+            "prompt_tokens": tokens,
+            "completion_tokens": tokens,
+            "total_tokens": 2 * tokens,
+        }
+    });
+    MockResponse::json(200, &completion)
+}
+
+fn models() -> MockResponse {
+    let models = json!({
+        "object": "list",
+        "data": [
+            {"id": "mock-model", "object": "model", "owned_by": "transformrs"},
+        ]
+    });
+    MockResponse::json(200, &models)
+}
+
+/// A deterministic embedding of `text`.
+fn embedding(text: &str, dimensions: usize) -> Vec<f32> {
+    let bytes = text.as_bytes();
+    (0..dimensions)
+        .map(|i| match bytes.len() {
+            0 => 0.0,
+            n => bytes[i % n] as f32 / 255.0,
+        })
+        .collect()
+}
+
+fn embeddings(body: &Value) -> MockResponse {
+    let input: Vec<String> = match &body["input"] {
+        Value::String(text) => vec![text.clone()],
+        input => serde_json::from_value(input.clone()).unwrap_or_default(),
+    };
+    let dimensions = body["dimensions"].as_u64().unwrap_or(8) as usize;
+    let base64 = body["encoding_format"] == "base64";
+    let data: Vec<Value> = input
+        .iter()
+        .enumerate()
+        .map(|(index, text)| {
+            let values = embedding(text, dimensions);
+            let embedding = if base64 {
+                let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+                json!(BASE64_STANDARD.encode(bytes))
+            } else {
+                json!(values)
+            };
+            json!({"object": "embedding", "index": index, "embedding": embedding})
+        })
+        .collect();
+    let tokens: usize = input
+        .iter()
+        .map(|text| text.split_whitespace().count())
+        .sum();
+    let resp = json!({
+        "object": "list",
+        "model": body["model"],
+        "data": data,
+        "usage": {"prompt_tokens": tokens, "total_tokens": tokens},
+    });
+    MockResponse::json(200, &resp)
+}
+
+fn google_embeddings(body: &Value) -> MockResponse {
+    let requests = body["requests"].as_array().cloned().unwrap_or_default();
+    let embeddings: Vec<Value> = requests
+        .iter()
+        .map(|request| {
+            let text = request["content"]["parts"][0]["text"]
+                .as_str()
+                .unwrap_or("");
+            let dimensions = request["outputDimensionality"].as_u64().unwrap_or(8) as usize;
+            json!({"values": embedding(text, dimensions)})
+        })
+        .collect();
+    MockResponse::json(200, &json!({ "embeddings": embeddings }))
+}
+
+fn moderations(body: &Value) -> MockResponse {
+    let inputs = match &body["input"] {
+        Value::String(text) => vec![json!({"type": "text", "text": text})],
+        Value::Array(inputs) => inputs.clone(),
+        _ => vec![],
+    };
+    let results: Vec<Value> = inputs
+        .iter()
+        .map(|input| {
+            let text = match input {
+                Value::String(text) => text.as_str(),
+                input => input["text"].as_str().unwrap_or(""),
+            };
+            let violent = is_violent(text);
+            let score = if violent { 0.9 } else { 0.01 };
+            json!({
+                "flagged": violent,
+                "categories": {"harassment": false, "violence": violent},
+                "category_scores": {"harassment": 0.01, "violence": score},
+            })
+        })
+        .collect();
+    let resp = json!({
+        "id": "modr-mock",
+        "model": body["model"],
+        "results": results,
+    });
+    MockResponse::json(200, &resp)
+}
+
+/// A Replicate prediction whose id holds the number of outputs.
+///
+/// Predictions are stateless: they start when created and have succeeded on
+/// the first poll.
+fn prediction(request: &ReceivedRequest, id: &str, status: &str) -> Value {
+    let host = request.headers.get("host").cloned().unwrap_or_default();
+    let outputs = id.trim_start_matches("mock-").parse().unwrap_or(1);
+    let output: Vec<String> = (0..outputs)
+        .map(|index| format!("http://{host}/files/{id}-{index}.png"))
+        .collect();
+    let output = if status == "succeeded" {
+        json!(output)
+    } else {
+        Value::Null
+    };
+    json!({
+        "id": id,
+        "status": status,
+        "output": output,
+        "error": null,
+        "logs": "",
+    })
+}
+
+/// OpenAI-style images with URLs that point to this server.
+fn images_generations(request: &ReceivedRequest, body: &Value) -> MockResponse {
+    let n = body["n"].as_u64().unwrap_or(1);
+    let url = body["response_format"].as_str() == Some("url");
+    let host = request.headers.get("host").cloned().unwrap_or_default();
+    let data: Vec<Value> = (0..n)
+        .map(|index| {
+            if url {
+                json!({"url": format!("http://{host}/files/image-{index}.png")})
+            } else {
+                json!({"b64_json": BASE64_STANDARD.encode(PNG)})
+            }
+        })
+        .collect();
+    MockResponse::json(200, &json!({"created": 0, "data": data}))
+}
+
+/// Fraction of the words in `query` that occur in `document`.
+fn relevance(query: &str, document: &str) -> f64 {
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect()
+    };
+    let query = words(query);
+    let document = words(document);
+    if query.is_empty() {
+        return 0.0;
+    }
+    let matches = query.iter().filter(|word| document.contains(word)).count();
+    matches as f64 / query.len() as f64
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    serde_json::from_value(value.clone()).unwrap_or_default()
+}
+
+fn rerank(body: &Value) -> MockResponse {
+    let query = body["query"].as_str().unwrap_or("");
+    let documents = strings(&body["documents"]);
+    let mut results: Vec<(usize, f64)> = documents
+        .iter()
+        .enumerate()
+        .map(|(index, document)| (index, relevance(query, document)))
+        .collect();
+    results.sort_by(|a, b| b.1.total_cmp(&a.1));
+    if let Some(top_n) = body["top_n"].as_u64() {
+        results.truncate(top_n as usize);
+    }
+    let return_documents = body["return_documents"].as_bool().unwrap_or(false);
+    let results: Vec<Value> = results
+        .iter()
+        .map(|(index, score)| {
+            let mut result = json!({"index": index, "relevance_score": score});
+            if return_documents {
+                result["document"] = json!({"text": documents[*index]});
+            }
+            result
+        })
+        .collect();
+    MockResponse::json(200, &json!({ "results": results }))
+}
+
+/// The transcription of the uploaded file in a mock.
+fn transcription_text(request: &ReceivedRequest) -> String {
+    let size = request
+        .form()
+        .ok()
+        .and_then(|parts| parts.into_iter().find(|part| part.name == "file"))
+        .map(|part| part.body.len())
+        .unwrap_or_default();
+    format!("Transcription of {size} bytes.")
+}
+
+/// A response of `/audio/transcriptions` or `/audio/translations`.
+fn transcription(request: &ReceivedRequest, task: &str) -> MockResponse {
+    let text = transcription_text(request);
+    let format = request.form_text("response_format");
+    let words = request
+        .form()
+        .unwrap_or_default()
+        .iter()
+        .any(|part| part.name == "timestamp_granularities[]" && part.body.as_ref() == b"word");
+    match format.as_deref().unwrap_or("json") {
+        "text" => MockResponse::bytes("text/plain", format!("{text}\n").as_bytes()),
+        "srt" => {
+            let srt = format!("1\n00:00:00,000 --> 00:00:01,000\n{text}\n\n");
+            MockResponse::bytes("text/plain", srt.as_bytes())
+        }
+        "vtt" => {
+            let vtt = format!("WEBVTT\n\n00:00:00.000 --> 00:00:01.000\n{text}\n\n");
+            MockResponse::bytes("text/vtt", vtt.as_bytes())
+        }
+        "verbose_json" => {
+            let mut resp = json!({
+                "task": task,
+                "language": request.form_text("language").unwrap_or("english".to_string()),
+                "duration": 1.0,
+                "text": text,
+                "segments": [{
+                    "id": 0,
+                    "seek": 0,
+                    "start": 0.0,
+                    "end": 1.0,
+                    "text": text,
+                    "tokens": [],
+                    "temperature": 0.0,
+                    "avg_logprob": -0.1,
+                    "compression_ratio": 1.0,
+                    "no_speech_prob": 0.01,
+                }],
+            });
+            if words {
+                let count = text.split_whitespace().count();
+                let words: Vec<Value> = text
+                    .split_whitespace()
+                    .enumerate()
+                    .map(|(i, word)| {
+                        let start = i as f64 / count as f64;
+                        let end = (i + 1) as f64 / count as f64;
+                        json!({"word": word, "start": start, "end": end})
+                    })
+                    .collect();
+                resp["words"] = json!(words);
+            }
+            MockResponse::json(200, &resp)
+        }
+        _ => MockResponse::json(200, &json!({ "text": text })),
+    }
+}
+
+fn elevenlabs_transcription(request: &ReceivedRequest) -> MockResponse {
+    let text = transcription_text(request);
+    let mut words = vec![];
+    for (i, word) in text.split_whitespace().enumerate() {
+        if i > 0 {
+            let start = i as f64 * 0.25;
+            words.push(json!({"text": " ", "start": start, "end": start, "type": "spacing"}));
+        }
+        words.push(json!({
+            "text": word,
+            "start": i as f64 * 0.25,
+            "end": (i + 1) as f64 * 0.25,
+            "type": "word",
+            "speaker_id": "speaker_0",
+        }));
+    }
+    let resp = json!({
+        "language_code": request.form_text("language_code").unwrap_or("eng".to_string()),
+        "language_probability": 0.99,
+        "text": text,
+        "words": words,
+    });
+    MockResponse::json(200, &resp)
+}
+
+/// Each character takes 50 ms.
+fn elevenlabs_with_timestamps(body: &Value, audio: &[u8]) -> MockResponse {
+    let characters: Vec<String> = body["text"]
+        .as_str()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| c.to_string())
+        .collect();
+    let starts: Vec<f64> = (0..characters.len()).map(|i| i as f64 * 0.05).collect();
+    let ends: Vec<f64> = starts.iter().map(|start| start + 0.05).collect();
+    let alignment = json!({
+        "characters": characters,
+        "character_start_times_seconds": starts,
+        "character_end_times_seconds": ends,
+    });
+    let resp = json!({
+        "audio_base64": BASE64_STANDARD.encode(audio),
+        "alignment": alignment,
+        "normalized_alignment": alignment,
+    });
+    MockResponse::json(200, &resp)
+}
+
+/// Each SSML mark is 250 ms after the previous one.
+fn google_synthesize(body: &Value) -> MockResponse {
+    let encoding = body["audioConfig"]["audioEncoding"]
+        .as_str()
+        .unwrap_or("MP3");
+    let mut resp = json!({ "audioContent": BASE64_STANDARD.encode(audio(encoding)) });
+    if body.get("enableTimePointing").is_some() {
+        let ssml = body["input"]["ssml"].as_str().unwrap_or_default();
+        let marks = Regex::new(r#"<mark name="([^"]*)"/>"#).unwrap();
+        let timepoints: Vec<Value> = marks
+            .captures_iter(ssml)
+            .enumerate()
+            .map(|(i, mark)| json!({"markName": &mark[1], "timeSeconds": i as f64 * 0.25}))
+            .collect();
+        resp["timepoints"] = json!(timepoints);
+    }
+    MockResponse::json(200, &resp)
+}
+
+/// Google and ElevenLabs both list voices at `/v1/voices`.
+fn voices(request: &ReceivedRequest) -> MockResponse {
+    if request.headers.contains_key("x-goog-api-key") {
+        let language_code = request
+            .path
+            .split_once("languageCode=")
+            .map_or("en-US", |(_, language_code)| language_code);
+        let voices = json!({"voices": [
+            {
+                "languageCodes": [language_code],
+                "name": format!("{language_code}-Studio-O"),
+                "ssmlGender": "FEMALE",
+                "naturalSampleRateHertz": 24000,
+            },
+            {
+                "languageCodes": [language_code],
+                "name": format!("{language_code}-Studio-Q"),
+                "ssmlGender": "MALE",
+                "naturalSampleRateHertz": 24000,
+            },
+        ]});
+        return MockResponse::json(200, &voices);
+    }
+    let voices = json!({"voices": [
+        {
+            "voice_id": "nPczCjzI2devNBz1zQrb",
+            "name": "Brian",
+            "labels": {"gender": "male", "accent": "american"},
+            "preview_url": "https://example.com/brian.mp3",
+            "verified_languages": [{"language": "en", "locale": "en-US"}],
+        },
+        {
+            "voice_id": "XrExE9yKIg1WjnnlVkGX",
+            "name": "Matilda",
+            "labels": {"gender": "female", "language": "de"},
+            "preview_url": "https://example.com/matilda.mp3",
+        },
+    ]});
+    MockResponse::json(200, &voices)
+}
+
+/// DeepInfra uses `/v1/inference/{model}` for images, speech, and reranking.
+fn deepinfra_inference(body: &Value) -> MockResponse {
+    if let Some(queries) = body.get("queries") {
+        let query = strings(queries).join(" ");
+        let scores: Vec<f64> = strings(&body["documents"])
+            .iter()
+            .map(|document| relevance(&query, document))
+            .collect();
+        return MockResponse::json(200, &json!({ "scores": scores }));
+    }
+    if body.get("prompt").is_some() {
+        let image = format!("data:image/png;base64,{}", BASE64_STANDARD.encode(PNG));
+        return MockResponse::json(200, &json!({"images": [image]}));
+    }
+    let output_format = body["output_format"].as_str().unwrap_or("mp3");
+    let prefix = match output_format {
+        "opus" => "data:audio/ogg; codec=\"opus\";base64,",
+        "mp3" => "data:audio/mp3;base64,",
+        _ => &format!("data:audio/{output_format};base64,"),
+    };
+    let audio = format!("{prefix}{}", BASE64_STANDARD.encode(audio(output_format)));
+    let resp = json!({
+        "request_id": "mock",
+        "output_format": output_format,
+        "audio": audio,
+    });
+    MockResponse::json(200, &resp)
+}
+
+fn route(request: &ReceivedRequest) -> MockResponse {
+    let path = request.path_without_query();
+    let body = request.json().unwrap_or(Value::Null);
+    match request.method.as_str() {
+        "GET" if path.ends_with("/models") => models(),
+        "POST" if path.ends_with("/chat/completions") => chat(&body),
+        "POST" if path.ends_with("/embeddings") => embeddings(&body),
+        "POST" if path.ends_with(":batchEmbedContents") => google_embeddings(&body),
+        "POST" if path.ends_with("/rerank") => rerank(&body),
+        "POST" if path.ends_with("/moderations") => moderations(&body),
+        "POST" if path.ends_with("/audio/speech") => {
+            let audio = audio(body["response_format"].as_str().unwrap_or("mp3"));
+            MockResponse::bytes("application/octet-stream", &audio).split_chunks(128)
+        }
+        "POST" if path.ends_with("/audio/transcriptions") => transcription(request, "transcribe"),
+        "POST" if path.ends_with("/audio/translations") => transcription(request, "translate"),
+        "POST" if path.ends_with("/v1/speech-to-text") => elevenlabs_transcription(request),
+        "POST" if path.contains("/v1/inference/") => deepinfra_inference(&body),
+        "POST" if path.ends_with("/v1/audio/generation") => {
+            let audio = BASE64_STANDARD.encode(mp3());
+            MockResponse::json(200, &json!({"audio": audio}))
+        }
+        "POST" if path.starts_with("/v1/predictions/") && path.ends_with("/cancel") => {
+            let id = path.split('/').nth(3).unwrap_or_default();
+            MockResponse::json(200, &prediction(request, id, "canceled"))
+        }
+        "POST" if path.ends_with("/predictions") => {
+            let outputs = body["input"]["num_outputs"].as_u64().unwrap_or(1);
+            let id = format!("mock-{outputs}");
+            MockResponse::json(201, &prediction(request, &id, "starting"))
+        }
+        "GET" if path.starts_with("/v1/predictions/") => {
+            let id = path.trim_start_matches("/v1/predictions/");
+            MockResponse::json(200, &prediction(request, id, "succeeded"))
+        }
+        "POST" if path.ends_with("/images/generations") => images_generations(request, &body),
+        "POST" if path.ends_with("/images/edits") || path.ends_with("/images/variations") => {
+            let mut body = json!({});
+            if let Some(n) = request.form_text("n") {
+                body["n"] = json!(n.parse::<u64>().unwrap_or(1));
+            }
+            if let Some(response_format) = request.form_text("response_format") {
+                body["response_format"] = json!(response_format);
+            }
+            images_generations(request, &body)
+        }
+        "GET" if path.starts_with("/files/") => MockResponse::bytes("image/png", PNG),
+        "POST" if path.ends_with("/v1/image/generation") => {
+            let image = BASE64_STANDARD.encode(PNG);
+            let images = json!({
+                "images": [{"index": 0, "random_seed": 42, "image": image}]
+            });
+            MockResponse::json(200, &images)
+        }
+        "POST" if path.contains("/v1/text-to-speech/") => {
+            // For example, `output_format=pcm_24000`.
+            let output_format = request
+                .path
+                .split_once("output_format=")
+                .map_or("mp3", |(_, format)| {
+                    format.split('_').next().unwrap_or("mp3")
+                });
+            let audio = audio(output_format);
+            if path.ends_with("/with-timestamps") {
+                return elevenlabs_with_timestamps(&body, &audio);
+            }
+            let resp = MockResponse::bytes("application/octet-stream", &audio);
+            if path.ends_with("/stream") {
+                resp.split_chunks(128)
+            } else {
+                resp
+            }
+        }
+        "POST" if path.ends_with("/text:synthesize") => google_synthesize(&body),
+        "GET" if path == "/v1/voices" => voices(request),
+        "POST" if path == "/cognitiveservices/v1" => {
+            // For example, `riff-24khz-16bit-mono-pcm`.
+            let output_format = request.headers.get("x-microsoft-outputformat").map_or(
+                "mp3",
+                |format| match format.split('-').next() {
+                    Some("riff") => "wav",
+                    Some("raw") => "pcm",
+                    Some("ogg") => "opus",
+                    _ => "mp3",
+                },
+            );
+            MockResponse::bytes("application/octet-stream", &audio(output_format))
+        }
+        method => MockResponse::error(404, &format!("No mock for {method} {path}")),
+    }
+}
@@ -10,8 +10,8 @@ use serde::Deserialize;
 use serde_json::Value;
 use std::error::Error;
 
-fn address(provider: &Provider) -> String {
-    let base_url = crate::openai_base_url(provider);
+fn address(provider: &Provider, key: &Key) -> String {
+    let base_url = crate::openai_base_url(provider, key);
     format!("{}/models", base_url)
 }
 
@@ -54,13 +54,10 @@ pub async fn models(
     provider: &Provider,
     key: &Key,
 ) -> Result<ModelsResponse, Box<dyn Error + Send + Sync>> {
-    let address = address(provider);
+    let address = address(provider, key);
     let client = reqwest::Client::new();
-    let resp = client
-        .get(address)
-        .headers(request_headers(key)?)
-        .send()
-        .await?;
+    let req = client.get(address).headers(request_headers(key)?);
+    let resp = crate::http::send(req).await?;
     let models_response = ModelsResponse {
         resp: resp.json::<Value>().await?,
     };
@@ -0,0 +1,381 @@
+//! Moderation.
+//!
+//! Functionality related to content moderation.
+//!
+//! OpenAI and compatible providers use the `/moderations` endpoint. Groq and
+//! DeepInfra moderate via a Llama Guard chat model whose `safe` or
+//! `unsafe S1,S10` output is parsed into the same [Moderation] type.
+
+use crate::chat::ChatCompletion;
+use crate::request_headers;
+use crate::Key;
+use crate::Message;
+use crate::Provider;
+use base64::prelude::*;
+use bytes::Bytes;
+use reqwest;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+
+/// A harm category.
+///
+/// The first variants are the OpenAI categories. The variants after
+/// [Category::ViolenceGraphic] are Llama Guard hazards without an OpenAI
+/// equivalent.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Category {
+    Harassment,
+    HarassmentThreatening,
+    Hate,
+    HateThreatening,
+    Illicit,
+    IllicitViolent,
+    SelfHarm,
+    SelfHarmIntent,
+    SelfHarmInstructions,
+    Sexual,
+    SexualMinors,
+    Violence,
+    ViolenceGraphic,
+    ViolentCrimes,
+    NonViolentCrimes,
+    SexCrimes,
+    Defamation,
+    SpecializedAdvice,
+    Privacy,
+    IntellectualProperty,
+    IndiscriminateWeapons,
+    Elections,
+    CodeInterpreterAbuse,
+    /// A category that this crate does not know.
+    Other(String),
+}
+
+impl Category {
+    /// The category for a Llama Guard hazard code such as `S1`.
+    pub fn from_llama_guard(code: &str) -> Category {
+        match code.trim() {
+            "S1" => Category::ViolentCrimes,
+            "S2" => Category::NonViolentCrimes,
+            "S3" => Category::SexCrimes,
+            "S4" => Category::SexualMinors,
+            "S5" => Category::Defamation,
+            "S6" => Category::SpecializedAdvice,
+            "S7" => Category::Privacy,
+            "S8" => Category::IntellectualProperty,
+            "S9" => Category::IndiscriminateWeapons,
+            "S10" => Category::Hate,
+            "S11" => Category::SelfHarm,
+            "S12" => Category::Sexual,
+            "S13" => Category::Elections,
+            "S14" => Category::CodeInterpreterAbuse,
+            code => Category::Other(code.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Category::Harassment => "harassment",
+            Category::HarassmentThreatening => "harassment/threatening",
+            Category::Hate => "hate",
+            Category::HateThreatening => "hate/threatening",
+            Category::Illicit => "illicit",
+            Category::IllicitViolent => "illicit/violent",
+            Category::SelfHarm => "self-harm",
+            Category::SelfHarmIntent => "self-harm/intent",
+            Category::SelfHarmInstructions => "self-harm/instructions",
+            Category::Sexual => "sexual",
+            Category::SexualMinors => "sexual/minors",
+            Category::Violence => "violence",
+            Category::ViolenceGraphic => "violence/graphic",
+            Category::ViolentCrimes => "violent-crimes",
+            Category::NonViolentCrimes => "non-violent-crimes",
+            Category::SexCrimes => "sex-crimes",
+            Category::Defamation => "defamation",
+            Category::SpecializedAdvice => "specialized-advice",
+            Category::Privacy => "privacy",
+            Category::IntellectualProperty => "intellectual-property",
+            Category::IndiscriminateWeapons => "indiscriminate-weapons",
+            Category::Elections => "elections",
+            Category::CodeInterpreterAbuse => "code-interpreter-abuse",
+            Category::Other(name) => name,
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Category {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let category = match s {
+            "harassment" => Category::Harassment,
+            "harassment/threatening" => Category::HarassmentThreatening,
+            "hate" => Category::Hate,
+            "hate/threatening" => Category::HateThreatening,
+            "illicit" => Category::Illicit,
+            "illicit/violent" => Category::IllicitViolent,
+            "self-harm" => Category::SelfHarm,
+            "self-harm/intent" => Category::SelfHarmIntent,
+            "self-harm/instructions" => Category::SelfHarmInstructions,
+            "sexual" => Category::Sexual,
+            "sexual/minors" => Category::SexualMinors,
+            "violence" => Category::Violence,
+            "violence/graphic" => Category::ViolenceGraphic,
+            "violent-crimes" => Category::ViolentCrimes,
+            "non-violent-crimes" => Category::NonViolentCrimes,
+            "sex-crimes" => Category::SexCrimes,
+            "defamation" => Category::Defamation,
+            "specialized-advice" => Category::SpecializedAdvice,
+            "privacy" => Category::Privacy,
+            "intellectual-property" => Category::IntellectualProperty,
+            "indiscriminate-weapons" => Category::IndiscriminateWeapons,
+            "elections" => Category::Elections,
+            "code-interpreter-abuse" => Category::CodeInterpreterAbuse,
+            name => Category::Other(name.to_string()),
+        };
+        Ok(category)
+    }
+}
+
+/// Content to moderate.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModerationInput {
+    Text(String),
+    /// A URL or a `data:` URL of an image.
+    ImageUrl(String),
+}
+
+impl ModerationInput {
+    pub fn text(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+    pub fn image_url(image_url: &str) -> Self {
+        Self::ImageUrl(image_url.to_string())
+    }
+    pub fn image_bytes(image_type: &str, image: &[u8]) -> Self {
+        let base64 = BASE64_STANDARD.encode(image);
+        Self::ImageUrl(format!("data:image/{image_type};base64,{base64}"))
+    }
+}
+
+impl Serialize for ModerationInput {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let json = match self {
+            ModerationInput::Text(text) => json!({"type": "text", "text": text}),
+            ModerationInput::ImageUrl(url) => {
+                json!({"type": "image_url", "image_url": {"url": url}})
+            }
+        };
+        json.serialize(serializer)
+    }
+}
+
+/// The moderation of one input.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    /// The flagged categories.
+    pub categories: Vec<Category>,
+    /// Scores between 0 and 1 per category.
+    ///
+    /// Empty for Llama Guard, which does not return scores.
+    pub category_scores: HashMap<Category, f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Moderation {
+    pub id: Option<String>,
+    pub model: Option<String>,
+    pub results: Vec<ModerationResult>,
+}
+
+impl Moderation {
+    /// Whether any of the results is flagged.
+    pub fn flagged(&self) -> bool {
+        self.results.iter().any(|result| result.flagged)
+    }
+}
+
+/// Parse Llama Guard output such as `safe` or `unsafe\nS1,S10`.
+pub fn parse_llama_guard(output: &str) -> Result<ModerationResult, Box<dyn Error + Send + Sync>> {
+    let mut lines = output.trim().lines();
+    match lines.next().map(|line| line.trim()) {
+        Some("safe") => Ok(ModerationResult::default()),
+        Some("unsafe") => {
+            let categories = lines
+                .flat_map(|line| line.split(','))
+                .filter(|code| !code.trim().is_empty())
+                .map(Category::from_llama_guard)
+                .collect();
+            Ok(ModerationResult {
+                flagged: true,
+                categories,
+                category_scores: HashMap::new(),
+            })
+        }
+        _ => Err(format!("unexpected Llama Guard output: {output}").into()),
+    }
+}
+
+fn uses_llama_guard(provider: &Provider) -> bool {
+    matches!(provider, Provider::Groq | Provider::DeepInfra)
+}
+
+fn address(provider: &Provider, key: &Key) -> Result<String, Box<dyn Error + Send + Sync>> {
+    match provider {
+        Provider::OpenAI | Provider::OpenAICompatible(_) => Ok(format!(
+            "{}/moderations",
+            crate::openai_base_url(provider, key)
+        )),
+        _ => Err(format!("Unsupported moderation provider: {provider}").into()),
+    }
+}
+
+fn openai_result(result: &Value) -> Result<ModerationResult, Box<dyn Error + Send + Sync>> {
+    let flagged = result["flagged"]
+        .as_bool()
+        .ok_or_else(|| format!("no flagged in result: {result}"))?;
+    let mut categories: Vec<Category> = result["categories"]
+        .as_object()
+        .map(|categories| {
+            categories
+                .iter()
+                .filter(|(_, flagged)| flagged.as_bool() == Some(true))
+                .map(|(name, _)| Category::from_str(name).unwrap())
+                .collect()
+        })
+        .unwrap_or_default();
+    categories.sort();
+    let category_scores = result["category_scores"]
+        .as_object()
+        .map(|scores| {
+            scores
+                .iter()
+                .filter_map(|(name, score)| {
+                    Some((Category::from_str(name).unwrap(), score.as_f64()?))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(ModerationResult {
+        flagged,
+        categories,
+        category_scores,
+    })
+}
+
+pub struct ModerationResponse {
+    provider: Provider,
+    resp: Bytes,
+}
+
+impl ModerationResponse {
+    pub fn bytes(&self) -> &Bytes {
+        &self.resp
+    }
+    pub fn raw_value(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        Ok(serde_json::from_slice::<Value>(&self.resp)?)
+    }
+    pub fn structured(&self) -> Result<Moderation, Box<dyn Error + Send + Sync>> {
+        let resp = self.raw_value()?;
+        if resp.get("error").is_some() {
+            return Err(crate::extract_error(&resp).into());
+        }
+        if let Some(detail) = resp.get("detail") {
+            return Err(format!("{} returned an error: {detail}", self.provider).into());
+        }
+        if uses_llama_guard(&self.provider) {
+            let completion: ChatCompletion = serde_json::from_value(resp)?;
+            let choice = completion
+                .choices
+                .first()
+                .ok_or("no choices in Llama Guard response")?;
+            let result = parse_llama_guard(&choice.message.content.to_string())?;
+            return Ok(Moderation {
+                id: completion.id,
+                model: Some(completion.model),
+                results: vec![result],
+            });
+        }
+        let results = resp["results"]
+            .as_array()
+            .ok_or_else(|| format!("no results in response: {resp}"))?
+            .iter()
+            .map(openai_result)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Moderation {
+            id: resp["id"].as_str().map(|id| id.to_string()),
+            model: resp["model"].as_str().map(|model| model.to_string()),
+            results,
+        })
+    }
+}
+
+/// Moderate the inputs.
+///
+/// For Groq and DeepInfra, `model` is a Llama Guard model such as
+/// `meta-llama/Llama-Guard-4-12B` and the text inputs are moderated together
+/// as one user message, which gives a single result.
+pub async fn moderate(
+    provider: &Provider,
+    key: &Key,
+    model: &str,
+    input: &[ModerationInput],
+) -> Result<ModerationResponse, Box<dyn Error + Send + Sync>> {
+    let resp = if uses_llama_guard(provider) {
+        let texts = input
+            .iter()
+            .map(|input| match input {
+                ModerationInput::Text(text) => Ok(text.as_str()),
+                ModerationInput::ImageUrl(_) => {
+                    Err("Llama Guard moderation only supports text inputs")
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let messages = vec![Message::from_str("user", &texts.join("\n\n"))];
+        let resp = crate::chat::chat_completion(provider, key, model, &messages).await?;
+        resp.bytes().clone()
+    } else {
+        let address = address(provider, key)?;
+        let body = json!({
+            "model": model,
+            "input": input,
+        });
+        let client = reqwest::Client::new();
+        let req = client
+            .post(address)
+            .headers(request_headers(key)?)
+            .json(&body);
+        crate::http::send(req).await?.bytes().await?
+    };
+    let moderation_response = ModerationResponse {
+        provider: provider.clone(),
+        resp,
+    };
+    Ok(moderation_response)
+}
+
+#[test]
+fn test_parse_llama_guard() {
+    let result = parse_llama_guard("safe").unwrap();
+    assert!(!result.flagged);
+    let result = parse_llama_guard("\nunsafe\nS1,S10").unwrap();
+    assert!(result.flagged);
+    assert_eq!(
+        result.categories,
+        vec![Category::ViolentCrimes, Category::Hate]
+    );
+    let result = parse_llama_guard("unsafe\nS15").unwrap();
+    assert_eq!(result.categories, vec![Category::Other("S15".to_string())]);
+    assert!(parse_llama_guard("I cannot help with that.").is_err());
+}
@@ -0,0 +1,66 @@
+//! Replicate.
+//!
+//! The Replicate implementation of [crate::jobs], where a job is a
+//! prediction.
+
+use crate::jobs::Job;
+use crate::request_headers;
+use crate::Key;
+use serde_json::json;
+use serde_json::Value;
+use std::error::Error;
+
+async fn send(req: reqwest::RequestBuilder) -> Result<Job, Box<dyn Error + Send + Sync>> {
+    let resp = crate::http::send(req).await?;
+    let status = resp.status();
+    let body = resp.bytes().await?;
+    let value: Value = serde_json::from_slice(&body)
+        .map_err(|e| format!("{e} in response with status {status}"))?;
+    if !status.is_success() {
+        // Errors are reported as problem details.
+        let detail = value["detail"].as_str().map(|detail| detail.to_string());
+        let message = detail.unwrap_or_else(|| crate::extract_error(&value));
+        return Err(format!("job request failed with status {status}: {message}").into());
+    }
+    // The prediction fields match the job fields.
+    Ok(serde_json::from_value(value)?)
+}
+
+pub(crate) async fn submit(
+    key: &Key,
+    model: &str,
+    input: &Value,
+) -> Result<Job, Box<dyn Error + Send + Sync>> {
+    let domain = crate::domain(&key.provider, key);
+    let (address, body) = match model.split_once(':') {
+        Some((_, version)) => (
+            format!("{domain}/v1/predictions"),
+            json!({"version": version, "input": input}),
+        ),
+        None => (
+            format!("{domain}/v1/models/{model}/predictions"),
+            json!({ "input": input }),
+        ),
+    };
+    let client = reqwest::Client::new();
+    let req = client
+        .post(address)
+        .headers(request_headers(key)?)
+        .json(&body);
+    send(req).await
+}
+
+pub(crate) async fn get(key: &Key, id: &str) -> Result<Job, Box<dyn Error + Send + Sync>> {
+    let address = format!("{}/v1/predictions/{id}", crate::domain(&key.provider, key));
+    let client = reqwest::Client::new();
+    send(client.get(address).headers(request_headers(key)?)).await
+}
+
+pub(crate) async fn cancel(key: &Key, id: &str) -> Result<Job, Box<dyn Error + Send + Sync>> {
+    let address = format!(
+        "{}/v1/predictions/{id}/cancel",
+        crate::domain(&key.provider, key)
+    );
+    let client = reqwest::Client::new();
+    send(client.post(address).headers(request_headers(key)?)).await
+}
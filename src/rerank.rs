@@ -0,0 +1,212 @@
+//! Reranking.
+//!
+//! Functionality related to reranking documents by relevance to a query.
+
+use crate::request_headers;
+use crate::Key;
+use crate::Provider;
+use bytes::Bytes;
+use reqwest;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Configuration for reranking.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RerankConfig {
+    /// Only return the `top_n` most relevant documents.
+    pub top_n: Option<usize>,
+    /// Include the text of the documents in the results.
+    pub return_documents: Option<bool>,
+    pub other: Option<HashMap<String, Value>>,
+}
+
+fn address(
+    provider: &Provider,
+    key: &Key,
+    model: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let address = match provider {
+        Provider::Cohere => format!("{}/v2/rerank", crate::domain(provider, key)),
+        Provider::DeepInfra => format!("{}/v1/inference/{model}", crate::domain(provider, key)),
+        Provider::TogetherAI => format!("{}/v1/rerank", crate::domain(provider, key)),
+        Provider::OpenAICompatible(_) => {
+            format!("{}/rerank", crate::openai_base_url(provider, key))
+        }
+        _ => return Err(format!("Unsupported rerank provider: {provider}").into()),
+    };
+    Ok(address)
+}
+
+fn body(
+    provider: &Provider,
+    config: &RerankConfig,
+    model: &str,
+    query: &str,
+    documents: &[&str],
+) -> Value {
+    let mut body = if provider == &Provider::DeepInfra {
+        // DeepInfra returns the scores for all documents in input order.
+        json!({
+            "queries": [query],
+            "documents": documents,
+        })
+    } else {
+        let mut body = json!({
+            "model": model,
+            "query": query,
+            "documents": documents,
+        });
+        if let Some(top_n) = config.top_n {
+            body["top_n"] = Value::from(top_n);
+        }
+        // Cohere v2 no longer returns documents, so they are added later.
+        if provider != &Provider::Cohere {
+            if let Some(return_documents) = config.return_documents {
+                body["return_documents"] = Value::from(return_documents);
+            }
+        }
+        body
+    };
+    if let Some(other) = &config.other {
+        for (key, value) in other {
+            body[key] = value.clone();
+        }
+    }
+    body
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RerankResult {
+    /// Position of the document in the input.
+    pub index: usize,
+    pub relevance_score: f64,
+    /// The document, if `return_documents` was set.
+    pub document: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rerank {
+    /// The results sorted from most to least relevant.
+    pub results: Vec<RerankResult>,
+}
+
+impl Rerank {
+    /// The indices of the documents from most to least relevant.
+    pub fn indices(&self) -> Vec<usize> {
+        self.results.iter().map(|result| result.index).collect()
+    }
+}
+
+pub struct RerankResponse {
+    provider: Provider,
+    config: RerankConfig,
+    documents: Vec<String>,
+    resp: Bytes,
+}
+
+impl RerankResponse {
+    pub fn bytes(&self) -> &Bytes {
+        &self.resp
+    }
+    pub fn raw_value(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        Ok(serde_json::from_slice::<Value>(&self.resp)?)
+    }
+    pub fn structured(&self) -> Result<Rerank, Box<dyn Error + Send + Sync>> {
+        let resp = self.raw_value()?;
+        if resp.get("error").is_some() {
+            return Err(crate::extract_error(&resp).into());
+        }
+        if let Some(detail) = resp.get("detail") {
+            return Err(format!("{} returned an error: {detail}", self.provider).into());
+        }
+        let mut results = if self.provider == Provider::DeepInfra {
+            let scores = resp["scores"]
+                .as_array()
+                .ok_or_else(|| format!("no scores in response: {resp}"))?;
+            scores
+                .iter()
+                .enumerate()
+                .map(|(index, score)| {
+                    let relevance_score = score
+                        .as_f64()
+                        .ok_or_else(|| format!("invalid score: {score}"))?;
+                    Ok(RerankResult {
+                        index,
+                        relevance_score,
+                        document: None,
+                    })
+                })
+                .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?
+        } else {
+            let items = resp["results"]
+                .as_array()
+                .ok_or_else(|| format!("no results in response: {resp}"))?;
+            items
+                .iter()
+                .map(|item| {
+                    let index = item["index"]
+                        .as_u64()
+                        .ok_or_else(|| format!("no index in result: {item}"))?;
+                    let relevance_score = item["relevance_score"]
+                        .as_f64()
+                        .ok_or_else(|| format!("no relevance_score in result: {item}"))?;
+                    let document = match &item["document"] {
+                        Value::String(text) => Some(text.clone()),
+                        Value::Object(document) => document
+                            .get("text")
+                            .and_then(|text| text.as_str())
+                            .map(|text| text.to_string()),
+                        _ => None,
+                    };
+                    Ok(RerankResult {
+                        index: index as usize,
+                        relevance_score,
+                        document,
+                    })
+                })
+                .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?
+        };
+        results.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+        if let Some(top_n) = self.config.top_n {
+            results.truncate(top_n);
+        }
+        for result in results.iter_mut() {
+            if self.config.return_documents == Some(true) && result.document.is_none() {
+                result.document = self.documents.get(result.index).cloned();
+            } else if self.config.return_documents != Some(true) {
+                result.document = None;
+            }
+        }
+        Ok(Rerank { results })
+    }
+}
+
+/// Rerank the documents by relevance to the query.
+pub async fn rerank(
+    provider: &Provider,
+    key: &Key,
+    config: &RerankConfig,
+    model: &str,
+    query: &str,
+    documents: &[&str],
+) -> Result<RerankResponse, Box<dyn Error + Send + Sync>> {
+    let address = address(provider, key, model)?;
+    let body = body(provider, config, model, query, documents);
+    let client = reqwest::Client::new();
+    let req = client
+        .post(address)
+        .headers(request_headers(key)?)
+        .json(&body);
+    let resp = crate::http::send(req).await?;
+    let rerank_response = RerankResponse {
+        provider: provider.clone(),
+        config: config.clone(),
+        documents: documents.iter().map(|d| d.to_string()).collect(),
+        resp: resp.bytes().await?,
+    };
+    Ok(rerank_response)
+}
@@ -0,0 +1,277 @@
+//! Scripted provider.
+//!
+//! An in-process fake provider for unit tests. No HTTP requests are sent.
+//! Instead, queued responses are returned in order and every request is
+//! recorded so that tests can assert on what was sent.
+//!
+//! The provider is used through the same public functions as the real
+//! providers:
+//!
+//! ```
+//! use transformrs::chat;
+//! use transformrs::scripted::ScriptedProvider;
+//! use transformrs::Message;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let scripted = ScriptedProvider::new();
+//! scripted.push_chat_reply("Hi there!");
+//! let provider = scripted.provider();
+//! let messages = vec![Message::from_str("user", "Hello!")];
+//! let resp = chat::chat_completion(&provider, &scripted.key(), "model", &messages)
+//!     .await
+//!     .unwrap();
+//! let content = &resp.structured().unwrap().choices[0].message.content;
+//! assert_eq!(content.to_string(), "Hi there!");
+//!
+//! let requests = scripted.requests();
+//! assert_eq!(requests[0].json().unwrap()["model"], "model");
+//! assert_eq!(requests[0].messages().unwrap()[0].content.to_string(), "Hello!");
+//! # }
+//! ```
+
+use crate::chat::ChatCompletion;
+use crate::chat::ChatCompletionChunk;
+use crate::chat::Choice;
+use crate::chat::Usage;
+use crate::Content;
+use crate::Key;
+use crate::Message;
+use crate::Provider;
+use base64::prelude::*;
+use bytes::Bytes;
+use reqwest::Request;
+use reqwest::Response;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// URL scheme for requests that are handled by a [ScriptedProvider].
+pub(crate) const SCHEME: &str = "scripted";
+
+/// A queued response.
+#[derive(Clone, Debug)]
+pub enum ScriptedResponse {
+    ChatCompletion(Value),
+    ChatStream(Vec<Value>),
+    Speech(Bytes),
+    Images(Vec<Bytes>),
+    /// A raw JSON response with the given status code.
+    Json(u16, Value),
+    /// An error in the shape that OpenAI uses.
+    Error(u16, String),
+}
+
+/// A request as received by a [ScriptedProvider].
+#[derive(Clone, Debug)]
+pub struct ScriptedRequest {
+    pub method: String,
+    /// The path of the endpoint, for example, `/chat/completions`.
+    pub path: String,
+    pub body: Bytes,
+}
+
+impl ScriptedRequest {
+    pub fn json(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+    /// The messages in a chat request.
+    pub fn messages(&self) -> Result<Vec<Message>, Box<dyn Error + Send + Sync>> {
+        let body = self.json()?;
+        Ok(serde_json::from_value(body["messages"].clone())?)
+    }
+}
+
+#[derive(Default)]
+struct Script {
+    queue: VecDeque<ScriptedResponse>,
+    requests: Vec<ScriptedRequest>,
+}
+
+type Registry = Vec<(u64, Arc<Mutex<Script>>)>;
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A fake provider that returns queued responses.
+///
+/// The provider is removed when this is dropped.
+pub struct ScriptedProvider {
+    id: u64,
+    script: Arc<Mutex<Script>>,
+}
+
+impl Default for ScriptedProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptedProvider {
+    pub fn new() -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let script = Arc::new(Mutex::new(Script::default()));
+        REGISTRY.lock().unwrap().push((id, script.clone()));
+        Self { id, script }
+    }
+    /// The provider to pass to functions such as [crate::chat::chat_completion].
+    pub fn provider(&self) -> Provider {
+        Provider::OpenAICompatible(format!("{SCHEME}://{}", self.id))
+    }
+    /// A key for [ScriptedProvider::provider].
+    pub fn key(&self) -> Key {
+        Key::new(self.provider(), "scripted")
+    }
+    pub fn push(&self, response: ScriptedResponse) {
+        self.script.lock().unwrap().queue.push_back(response);
+    }
+    pub fn push_chat_completion(&self, completion: &ChatCompletion) {
+        let value = serde_json::to_value(completion).expect("serializable");
+        self.push(ScriptedResponse::ChatCompletion(value));
+    }
+    /// Queue a chat completion with a single assistant message.
+    pub fn push_chat_reply(&self, content: &str) {
+        let completion = ChatCompletion {
+            id: Some("scripted".to_string()),
+            object: "chat.completion".to_string(),
+            created: 0,
+            model: "scripted".to_string(),
+            system_fingerprint: None,
+            choices: vec![Choice {
+                index: 0,
+                message: Message {
+                    role: "assistant".to_string(),
+                    content: Content::Text(content.to_string()),
+                },
+                logprobs: None,
+                finish_reason: Some("stop".to_string()),
+            }],
+            service_tier: None,
+            usage: Usage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+            },
+        };
+        self.push_chat_completion(&completion);
+    }
+    pub fn push_chat_stream(&self, chunks: &[ChatCompletionChunk]) {
+        let chunks = chunks
+            .iter()
+            .map(|chunk| serde_json::to_value(chunk).expect("serializable"))
+            .collect();
+        self.push(ScriptedResponse::ChatStream(chunks));
+    }
+    pub fn push_speech(&self, audio: &[u8]) {
+        self.push(ScriptedResponse::Speech(Bytes::copy_from_slice(audio)));
+    }
+    pub fn push_images(&self, images: &[Bytes]) {
+        self.push(ScriptedResponse::Images(images.to_vec()));
+    }
+    pub fn push_error(&self, status: u16, message: &str) {
+        self.push(ScriptedResponse::Error(status, message.to_string()));
+    }
+    /// The requests that were received so far.
+    pub fn requests(&self) -> Vec<ScriptedRequest> {
+        self.script.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for ScriptedProvider {
+    fn drop(&mut self) {
+        REGISTRY.lock().unwrap().retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Render the response in the shape of the endpoint at `path`.
+fn render(
+    path: &str,
+    response: ScriptedResponse,
+) -> Result<Response, Box<dyn Error + Send + Sync>> {
+    let json = |status: u16, value: &Value| {
+        crate::http::response(
+            status,
+            "application/json",
+            vec![Bytes::from(value.to_string())],
+        )
+    };
+    match response {
+        ScriptedResponse::ChatCompletion(value) => json(200, &value),
+        ScriptedResponse::ChatStream(chunks) => {
+            let chunks = chunks
+                .iter()
+                .map(|chunk| format!("data: {chunk}\n\n"))
+                .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+                .map(Bytes::from)
+                .collect();
+            crate::http::response(200, "text/event-stream", chunks)
+        }
+        ScriptedResponse::Speech(audio) => crate::http::response(200, "audio/mpeg", vec![audio]),
+        ScriptedResponse::Images(images) if path.ends_with("/images/generations") => {
+            let data: Vec<Value> = images
+                .iter()
+                .map(|image| json!({"b64_json": BASE64_STANDARD.encode(image)}))
+                .collect();
+            json(200, &json!({"created": 0, "data": data}))
+        }
+        ScriptedResponse::Images(images) => {
+            let images: Vec<Value> = images
+                .iter()
+                .enumerate()
+                .map(|(index, image)| {
+                    json!({
+                        "index": index,
+                        "random_seed": null,
+                        "image": BASE64_STANDARD.encode(image),
+                    })
+                })
+                .collect();
+            json(200, &json!({ "images": images }))
+        }
+        ScriptedResponse::Json(status, value) => json(status, &value),
+        ScriptedResponse::Error(status, message) => {
+            json(status, &json!({"error": {"message": message}}))
+        }
+    }
+}
+
+/// Handle a request to a `scripted://` URL.
+pub(crate) fn respond(request: &Request) -> Result<Response, Box<dyn Error + Send + Sync>> {
+    let url = request.url();
+    let id: u64 = url.host_str().unwrap_or_default().parse()?;
+    let script = REGISTRY
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(i, _)| *i == id)
+        .map(|(_, script)| script.clone())
+        .ok_or_else(|| format!("scripted provider {id} no longer exists"))?;
+    let path = url.path().to_string();
+    let method = request.method().to_string();
+    let body = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map(Bytes::copy_from_slice)
+        .unwrap_or_default();
+    let response = {
+        let mut script = script.lock().unwrap();
+        script.requests.push(ScriptedRequest {
+            method: method.clone(),
+            path: path.clone(),
+            body,
+        });
+        script.queue.pop_front()
+    };
+    match response {
+        Some(response) => render(&path, response),
+        None => {
+            let message = format!("no scripted response left for {method} {path}");
+            render(&path, ScriptedResponse::Error(500, message))
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Secrets.
+//!
+//! A string type for API keys that does not leak into logs.
+
+use zeroize::Zeroize;
+
+/// A secret string such as an API key.
+///
+/// `Debug` and `Display` print `***` instead of the value and the memory is
+/// zeroed when the secret is dropped. Use [Secret::expose] to get the value.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: &str) -> Self {
+        Self(value.to_string())
+    }
+    /// The secret value.
+    ///
+    /// Take care not to log or store the result.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+impl std::fmt::Display for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "***")
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[test]
+fn test_secret_is_redacted() {
+    let secret = Secret::new("sk-abc");
+    assert_eq!(format!("{secret}"), "***");
+    assert_eq!(format!("{secret:?}"), "Secret(***)");
+    assert_eq!(secret.expose(), "sk-abc");
+}
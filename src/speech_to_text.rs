@@ -0,0 +1,430 @@
+//! Speech-to-text.
+//!
+//! Functionality related to speech-to-text.
+//!
+//! The audio is uploaded as `multipart/form-data` to the OpenAI-compatible
+//! `/audio/transcriptions` and `/audio/translations` endpoints for most
+//! providers and to the native `/v1/speech-to-text` endpoint for ElevenLabs.
+
+use crate::http::Multipart;
+use crate::request_headers;
+use crate::subtitles;
+use crate::subtitles::Cue;
+use crate::subtitles::SubtitleConfig;
+use crate::Key;
+use crate::Provider;
+use bytes::Bytes;
+use reqwest;
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderValue;
+use reqwest::header::CONTENT_TYPE;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Format of the response.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    /// Plain text without timestamps.
+    Text,
+    /// JSON with the language, duration, segments, and optionally words.
+    VerboseJson,
+    Srt,
+    Vtt,
+}
+
+impl std::fmt::Display for ResponseFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format = match self {
+            ResponseFormat::Json => "json",
+            ResponseFormat::Text => "text",
+            ResponseFormat::VerboseJson => "verbose_json",
+            ResponseFormat::Srt => "srt",
+            ResponseFormat::Vtt => "vtt",
+        };
+        write!(f, "{format}")
+    }
+}
+
+/// Level of detail of the timestamps in a `verbose_json` response.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampGranularity {
+    Segment,
+    Word,
+}
+
+impl std::fmt::Display for TimestampGranularity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let granularity = match self {
+            TimestampGranularity::Segment => "segment",
+            TimestampGranularity::Word => "word",
+        };
+        write!(f, "{granularity}")
+    }
+}
+
+/// Speech-to-text config.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct STTConfig {
+    /// Language of the audio as an ISO-639-1 code such as `en`.
+    pub language: Option<String>,
+    /// Text to guide the style or to continue a previous segment.
+    pub prompt: Option<String>,
+    pub temperature: Option<f64>,
+    pub response_format: Option<ResponseFormat>,
+    pub timestamp_granularities: Option<Vec<TimestampGranularity>>,
+    pub other: Option<HashMap<String, Value>>,
+}
+
+/// Audio to upload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioFile {
+    /// The file name such as `speech.mp3`.
+    ///
+    /// Providers use the extension to determine the format.
+    pub name: String,
+    pub audio: Bytes,
+}
+
+impl AudioFile {
+    pub fn new(name: &str, audio: &[u8]) -> Self {
+        Self {
+            name: name.to_string(),
+            audio: Bytes::copy_from_slice(audio),
+        }
+    }
+    /// The MIME type based on the extension of the file name.
+    pub fn content_type(&self) -> &'static str {
+        let extension = self.name.rsplit('.').next().unwrap_or_default();
+        match extension.to_lowercase().as_str() {
+            "flac" => "audio/flac",
+            "m4a" | "mp4" => "audio/mp4",
+            "mp3" | "mpga" | "mpeg" => "audio/mpeg",
+            "ogg" | "oga" | "opus" => "audio/ogg",
+            "wav" => "audio/wav",
+            "webm" => "audio/webm",
+            _ => "application/octet-stream",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Task {
+    Transcribe,
+    /// Transcribe and translate to English.
+    Translate,
+}
+
+fn address(provider: &Provider, key: &Key, task: Task) -> String {
+    if provider == &Provider::ElevenLabs {
+        format!("{}/v1/speech-to-text", crate::domain(provider, key))
+    } else {
+        let endpoint = match task {
+            Task::Transcribe => "transcriptions",
+            Task::Translate => "translations",
+        };
+        format!("{}/audio/{endpoint}", crate::openai_base_url(provider, key))
+    }
+}
+
+fn headers(provider: &Provider, key: &Key) -> Result<HeaderMap, Box<dyn Error + Send + Sync>> {
+    if provider == &Provider::ElevenLabs {
+        crate::key_header(key, "xi-api-key")
+    } else {
+        request_headers(key)
+    }
+}
+
+fn form(
+    provider: &Provider,
+    config: &STTConfig,
+    model: &str,
+    file: &AudioFile,
+    task: Task,
+) -> Result<Multipart, Box<dyn Error + Send + Sync>> {
+    let mut form = Multipart::new();
+    if task == Task::Translate {
+        if provider == &Provider::ElevenLabs {
+            return Err("ElevenLabs does not support translations".into());
+        }
+        if config.language.is_some() {
+            return Err("translations are always to English, so language cannot be set".into());
+        }
+        if config.timestamp_granularities.is_some() {
+            return Err("translations do not support timestamp granularities".into());
+        }
+    }
+    if provider == &Provider::ElevenLabs {
+        form = form.text("model_id", model);
+        if let Some(language) = &config.language {
+            form = form.text("language_code", language);
+        }
+        if config.prompt.is_some() {
+            return Err("ElevenLabs does not support a prompt".into());
+        }
+        if let Some(temperature) = config.temperature {
+            form = form.text("temperature", temperature);
+        }
+        match config.response_format {
+            None | Some(ResponseFormat::Json) | Some(ResponseFormat::VerboseJson) => {}
+            Some(format) => {
+                return Err(format!("ElevenLabs does not support response format {format}").into())
+            }
+        }
+        if let Some(granularities) = &config.timestamp_granularities {
+            if granularities.contains(&TimestampGranularity::Word) {
+                form = form.text("timestamps_granularity", "word");
+            }
+        }
+    } else {
+        form = form.text("model", model);
+        if let Some(language) = &config.language {
+            form = form.text("language", language);
+        }
+        if let Some(prompt) = &config.prompt {
+            form = form.text("prompt", prompt);
+        }
+        if let Some(temperature) = config.temperature {
+            form = form.text("temperature", temperature);
+        }
+        if let Some(response_format) = config.response_format {
+            form = form.text("response_format", response_format);
+        }
+        if let Some(granularities) = &config.timestamp_granularities {
+            for granularity in granularities {
+                form = form.text("timestamp_granularities[]", granularity);
+            }
+        }
+    }
+    if let Some(other) = &config.other {
+        let mut other: Vec<_> = other.iter().collect();
+        other.sort_by_key(|(key, _)| key.as_str());
+        for (key, value) in other {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            form = form.text(key, value);
+        }
+    }
+    Ok(form.file("file", &file.name, file.content_type(), &file.audio))
+}
+
+/// A part of the transcription with timestamps in seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    #[serde(default)]
+    pub id: u64,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub avg_logprob: Option<f64>,
+    pub compression_ratio: Option<f64>,
+    /// Probability that the segment contains no speech.
+    pub no_speech_prob: Option<f64>,
+}
+
+/// A word with timestamps in seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub word: String,
+    pub start: f64,
+    pub end: f64,
+    /// The speaker if the provider does diarization.
+    pub speaker: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Transcription {
+    pub text: String,
+    pub language: Option<String>,
+    /// Duration of the audio in seconds.
+    pub duration: Option<f64>,
+    pub segments: Vec<Segment>,
+    pub words: Vec<Word>,
+}
+
+pub struct TranscriptionResponse {
+    provider: Provider,
+    response_format: ResponseFormat,
+    resp: Bytes,
+}
+
+impl TranscriptionResponse {
+    pub fn bytes(&self) -> &Bytes {
+        &self.resp
+    }
+    pub fn raw_value(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        Ok(serde_json::from_slice::<Value>(&self.resp)?)
+    }
+    fn check_error(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if let Ok(resp) = serde_json::from_slice::<Value>(&self.resp) {
+            if resp.get("error").is_some() {
+                return Err(crate::extract_error(&resp).into());
+            }
+            if let Some(detail) = resp.get("detail") {
+                return Err(format!("{} returned an error: {detail}", self.provider).into());
+            }
+        }
+        Ok(())
+    }
+    /// The text of the response.
+    ///
+    /// This is the transcription for JSON responses and the body as-is for
+    /// the `text`, `srt`, and `vtt` response formats.
+    pub fn text(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        self.check_error()?;
+        match self.response_format {
+            ResponseFormat::Json | ResponseFormat::VerboseJson => Ok(self.structured()?.text),
+            _ => Ok(String::from_utf8(self.resp.to_vec())?),
+        }
+    }
+    pub fn structured(&self) -> Result<Transcription, Box<dyn Error + Send + Sync>> {
+        self.check_error()?;
+        if self.provider == Provider::ElevenLabs {
+            return elevenlabs_transcription(&self.raw_value()?);
+        }
+        match self.response_format {
+            ResponseFormat::Json | ResponseFormat::VerboseJson => {
+                openai_transcription(&self.raw_value()?)
+            }
+            ResponseFormat::Text => Ok(Transcription {
+                text: String::from_utf8(self.resp.to_vec())?.trim().to_string(),
+                ..Default::default()
+            }),
+            format => Err(format!("cannot parse {format} response; use text() instead").into()),
+        }
+    }
+    /// The transcription as subtitle cues.
+    ///
+    /// SRT and WebVTT responses are parsed as is. For the other formats, the
+    /// cues are built from the words or segments with `config`.
+    pub fn cues(&self, config: &SubtitleConfig) -> Result<Vec<Cue>, Box<dyn Error + Send + Sync>> {
+        match self.response_format {
+            ResponseFormat::Srt => subtitles::parse_srt(&self.text()?),
+            ResponseFormat::Vtt => subtitles::parse_vtt(&self.text()?),
+            _ => Ok(subtitles::cues_from_transcription(
+                &self.structured()?,
+                config,
+            )),
+        }
+    }
+}
+
+fn openai_transcription(resp: &Value) -> Result<Transcription, Box<dyn Error + Send + Sync>> {
+    let text = resp["text"]
+        .as_str()
+        .ok_or_else(|| format!("no text in response: {resp}"))?;
+    let segments = match resp.get("segments") {
+        Some(segments) if !segments.is_null() => serde_json::from_value(segments.clone())?,
+        _ => vec![],
+    };
+    let words = match resp.get("words") {
+        Some(words) if !words.is_null() => serde_json::from_value(words.clone())?,
+        _ => vec![],
+    };
+    Ok(Transcription {
+        text: text.trim().to_string(),
+        language: resp["language"].as_str().map(|l| l.to_string()),
+        duration: resp["duration"].as_f64(),
+        segments,
+        words,
+    })
+}
+
+fn elevenlabs_transcription(resp: &Value) -> Result<Transcription, Box<dyn Error + Send + Sync>> {
+    let text = resp["text"]
+        .as_str()
+        .ok_or_else(|| format!("no text in response: {resp}"))?;
+    let items = resp["words"].as_array().cloned().unwrap_or_default();
+    let words = items
+        .iter()
+        .filter(|item| item["type"].as_str().unwrap_or("word") == "word")
+        .map(|item| {
+            let word = item["text"]
+                .as_str()
+                .ok_or_else(|| format!("no text in word: {item}"))?;
+            Ok(Word {
+                word: word.to_string(),
+                start: item["start"].as_f64().unwrap_or_default(),
+                end: item["end"].as_f64().unwrap_or_default(),
+                speaker: item["speaker_id"].as_str().map(|s| s.to_string()),
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+    Ok(Transcription {
+        text: text.trim().to_string(),
+        language: resp["language_code"].as_str().map(|l| l.to_string()),
+        duration: words.last().map(|word| word.end),
+        segments: vec![],
+        words,
+    })
+}
+
+async fn upload(
+    provider: &Provider,
+    key: &Key,
+    config: &STTConfig,
+    model: &str,
+    file: &AudioFile,
+    task: Task,
+) -> Result<TranscriptionResponse, Box<dyn Error + Send + Sync>> {
+    let address = address(provider, key, task);
+    let mut headers = headers(provider, key)?;
+    let (content_type, body) = form(provider, config, model, file, task)?.finish();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_str(&content_type)?);
+    let client = reqwest::Client::new();
+    let req = client.post(address).headers(headers).body(body);
+    let resp = crate::http::send(req).await?;
+    let transcription_response = TranscriptionResponse {
+        provider: provider.clone(),
+        response_format: config.response_format.unwrap_or_default(),
+        resp: resp.bytes().await?,
+    };
+    Ok(transcription_response)
+}
+
+/// Transcribe the audio to text in the language of the audio.
+pub async fn transcribe(
+    provider: &Provider,
+    key: &Key,
+    config: &STTConfig,
+    model: &str,
+    file: &AudioFile,
+) -> Result<TranscriptionResponse, Box<dyn Error + Send + Sync>> {
+    upload(provider, key, config, model, file, Task::Transcribe).await
+}
+
+/// Translate the speech in the audio to English text.
+pub async fn translate(
+    provider: &Provider,
+    key: &Key,
+    config: &STTConfig,
+    model: &str,
+    file: &AudioFile,
+) -> Result<TranscriptionResponse, Box<dyn Error + Send + Sync>> {
+    upload(provider, key, config, model, file, Task::Translate).await
+}
+
+#[test]
+fn test_elevenlabs_transcription() {
+    let resp = serde_json::json!({
+        "language_code": "eng",
+        "text": "Hello world",
+        "words": [
+            {"text": "Hello", "start": 0.0, "end": 0.5, "type": "word", "speaker_id": "speaker_0"},
+            {"text": " ", "start": 0.5, "end": 0.6, "type": "spacing"},
+            {"text": "world", "start": 0.6, "end": 1.0, "type": "word"}
+        ]
+    });
+    let transcription = elevenlabs_transcription(&resp).unwrap();
+    assert_eq!(transcription.words.len(), 2);
+    assert_eq!(transcription.words[0].speaker.as_deref(), Some("speaker_0"));
+    assert_eq!(transcription.duration, Some(1.0));
+}
@@ -0,0 +1,364 @@
+//! Subtitles.
+//!
+//! Functionality related to SRT and WebVTT captions. Cues are built from the
+//! word timings of [crate::text_to_speech::Speech] or from the words and
+//! segments of a [Transcription], and can be rendered to and parsed from SRT
+//! and WebVTT:
+//!
+//! ```
+//! use transformrs::subtitles;
+//! use transformrs::subtitles::Cue;
+//!
+//! let cues = vec![Cue::new(0.0, 1.5, "Hello, world!")];
+//! let vtt = subtitles::to_vtt(&cues);
+//! assert_eq!(subtitles::parse_vtt(&vtt).unwrap(), cues);
+//! ```
+
+use crate::speech_to_text::Transcription;
+use crate::text_to_speech::Timing;
+use serde::Deserialize;
+use serde::Serialize;
+use std::error::Error;
+
+/// A caption that is shown from `start` to `end` in seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    /// The text with a newline between the lines.
+    pub text: String,
+}
+
+impl Cue {
+    pub fn new(start: f64, end: f64, text: &str) -> Cue {
+        Cue {
+            start,
+            end,
+            text: text.to_string(),
+        }
+    }
+}
+
+/// Configuration for building cues.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleConfig {
+    /// Maximum number of characters per line.
+    pub max_line_length: usize,
+    /// Maximum number of lines per cue.
+    pub max_lines: usize,
+    /// Minimum time in seconds that a cue is shown, unless the next cue
+    /// starts earlier.
+    pub min_duration: f64,
+}
+
+impl Default for SubtitleConfig {
+    fn default() -> Self {
+        Self {
+            max_line_length: 42,
+            max_lines: 2,
+            min_duration: 1.0,
+        }
+    }
+}
+
+/// Wrap the words into lines of at most `max_line_length` characters.
+///
+/// Words that are longer than the limit get a line of their own.
+fn wrap<'a>(words: impl IntoIterator<Item = &'a str>, max_line_length: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for word in words {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= max_line_length => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+fn ends_sentence(word: &str) -> bool {
+    let word = word.trim_end_matches(['"', '\'', ')', '”', '’']);
+    word.ends_with(['.', '!', '?', '…'])
+}
+
+/// Extend cues that are shorter than the minimum duration without
+/// overlapping the next cue.
+fn apply_min_duration(cues: &mut [Cue], min_duration: f64) {
+    for i in 0..cues.len() {
+        let next = cues.get(i + 1).map(|cue| cue.start);
+        let cue = &mut cues[i];
+        if cue.end - cue.start < min_duration {
+            let end = cue.start + min_duration;
+            cue.end = next.map_or(end, |next| end.min(next)).max(cue.end);
+        }
+    }
+}
+
+/// Build cues from word timings.
+///
+/// A new cue starts when the words do not fit in the lines of the current
+/// cue or after the end of a sentence.
+pub fn cues_from_words(words: &[Timing], config: &SubtitleConfig) -> Vec<Cue> {
+    let mut cues = vec![];
+    let mut current: Vec<&Timing> = vec![];
+    let finish = |current: &mut Vec<&Timing>, cues: &mut Vec<Cue>| {
+        let (Some(first), Some(last)) = (current.first(), current.last()) else {
+            return;
+        };
+        let lines = wrap(
+            current.iter().map(|word| word.text.trim()),
+            config.max_line_length,
+        );
+        let end = last.end.unwrap_or(last.start).max(first.start);
+        cues.push(Cue::new(first.start, end, &lines.join("\n")));
+        current.clear();
+    };
+    for word in words.iter().filter(|word| !word.text.trim().is_empty()) {
+        let candidate = current.iter().chain([&word]).map(|word| word.text.trim());
+        let lines = wrap(candidate, config.max_line_length).len();
+        if !current.is_empty() && lines > config.max_lines {
+            finish(&mut current, &mut cues);
+        }
+        current.push(word);
+        if ends_sentence(&word.text) {
+            finish(&mut current, &mut cues);
+        }
+    }
+    finish(&mut current, &mut cues);
+    apply_min_duration(&mut cues, config.min_duration);
+    cues
+}
+
+/// Build cues from a transcription.
+///
+/// The word timestamps are used if available and else the segments. Segments
+/// that do not fit in one cue are split with the time divided by the number of
+/// characters.
+pub fn cues_from_transcription(transcription: &Transcription, config: &SubtitleConfig) -> Vec<Cue> {
+    if !transcription.words.is_empty() {
+        let words: Vec<Timing> = transcription
+            .words
+            .iter()
+            .map(|word| Timing {
+                text: word.word.clone(),
+                start: word.start,
+                end: Some(word.end),
+            })
+            .collect();
+        return cues_from_words(&words, config);
+    }
+    let mut cues = vec![];
+    for segment in &transcription.segments {
+        let lines = wrap(segment.text.split_whitespace(), config.max_line_length);
+        let total: usize = lines.iter().map(|line| line.chars().count()).sum();
+        let per_char = (segment.end - segment.start) / total.max(1) as f64;
+        let mut start = segment.start;
+        for chunk in lines.chunks(config.max_lines.max(1)) {
+            let chars: usize = chunk.iter().map(|line| line.chars().count()).sum();
+            let end = (start + chars as f64 * per_char).min(segment.end);
+            cues.push(Cue::new(start, end, &chunk.join("\n")));
+            start = end;
+        }
+    }
+    apply_min_duration(&mut cues, config.min_duration);
+    cues
+}
+
+/// Format seconds as `HH:MM:SS` followed by `separator` and the milliseconds.
+fn timestamp(seconds: f64, separator: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    let (hours, rest) = (millis / 3_600_000, millis % 3_600_000);
+    let (minutes, rest) = (rest / 60_000, rest % 60_000);
+    let (seconds, millis) = (rest / 1000, rest % 1000);
+    format!("{hours:02}:{minutes:02}:{seconds:02}{separator}{millis:03}")
+}
+
+/// The cue text without blank lines, which would end the cue.
+fn cue_text(text: &str) -> String {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    lines.join("\n")
+}
+
+/// Render the cues as SRT.
+pub fn to_srt(cues: &[Cue]) -> String {
+    let mut srt = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        let start = timestamp(cue.start, ',');
+        let end = timestamp(cue.end, ',');
+        let text = cue_text(&cue.text);
+        srt.push_str(&format!("{}\n{start} --> {end}\n{text}\n\n", i + 1));
+    }
+    srt
+}
+
+/// Render the cues as WebVTT.
+pub fn to_vtt(cues: &[Cue]) -> String {
+    let mut vtt = "WEBVTT\n\n".to_string();
+    for cue in cues {
+        let start = timestamp(cue.start, '.');
+        let end = timestamp(cue.end, '.');
+        // `-->` would start a new cue.
+        let text = cue_text(&cue.text).replace("-->", "->");
+        vtt.push_str(&format!("{start} --> {end}\n{text}\n\n"));
+    }
+    vtt
+}
+
+/// Parse `HH:MM:SS,mmm`, `HH:MM:SS.mmm`, or `MM:SS.mmm` into seconds.
+fn parse_timestamp(timestamp: &str) -> Result<f64, Box<dyn Error + Send + Sync>> {
+    let invalid = || format!("invalid timestamp: {timestamp}");
+    let (time, millis) = timestamp
+        .trim()
+        .split_once([',', '.'])
+        .ok_or_else(invalid)?;
+    let parts = time
+        .split(':')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    let seconds = match parts[..] {
+        [hours, minutes, seconds] => hours * 3600 + minutes * 60 + seconds,
+        [minutes, seconds] => minutes * 60 + seconds,
+        _ => return Err(invalid().into()),
+    };
+    if millis.len() != 3 {
+        return Err(invalid().into());
+    }
+    let millis = millis.parse::<u64>().map_err(|_| invalid())?;
+    Ok(seconds as f64 + millis as f64 / 1000.0)
+}
+
+/// Parse a block of lines that contains a timing line into a cue.
+///
+/// Lines before the timing line, such as the SRT index or the WebVTT cue
+/// identifier, are ignored, as are WebVTT cue settings after the end time.
+fn parse_block(block: &str) -> Result<Option<Cue>, Box<dyn Error + Send + Sync>> {
+    let mut lines = block.lines();
+    let Some(timing) = lines.by_ref().find(|line| line.contains("-->")) else {
+        return Ok(None);
+    };
+    let (start, rest) = timing.split_once("-->").unwrap();
+    let end = rest.split_whitespace().next().unwrap_or_default();
+    let text: Vec<&str> = lines.collect();
+    Ok(Some(Cue {
+        start: parse_timestamp(start)?,
+        end: parse_timestamp(end)?,
+        text: text.join("\n"),
+    }))
+}
+
+/// Split the text at blank lines.
+fn blocks(text: &str) -> Vec<String> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    text.split("\n\n")
+        .map(|block| block.trim_matches('\n').to_string())
+        .filter(|block| !block.is_empty())
+        .collect()
+}
+
+/// Parse SRT into cues.
+pub fn parse_srt(srt: &str) -> Result<Vec<Cue>, Box<dyn Error + Send + Sync>> {
+    let mut cues = vec![];
+    for block in blocks(srt) {
+        match parse_block(&block)? {
+            Some(cue) => cues.push(cue),
+            None => return Err(format!("no timing line in SRT block: {block}").into()),
+        }
+    }
+    Ok(cues)
+}
+
+/// Parse WebVTT into cues.
+///
+/// Comments, styles, and regions are skipped.
+pub fn parse_vtt(vtt: &str) -> Result<Vec<Cue>, Box<dyn Error + Send + Sync>> {
+    let mut blocks = blocks(vtt).into_iter();
+    match blocks.next() {
+        Some(header) if header.starts_with("WEBVTT") => {}
+        _ => return Err("WebVTT must start with WEBVTT".into()),
+    }
+    let mut cues = vec![];
+    for block in blocks {
+        let skip = ["NOTE", "STYLE", "REGION"];
+        if skip.iter().any(|keyword| block.starts_with(keyword)) {
+            continue;
+        }
+        if let Some(cue) = parse_block(&block)? {
+            cues.push(cue);
+        }
+    }
+    Ok(cues)
+}
+
+#[test]
+fn test_cues_from_words() {
+    let words: Vec<Timing> = "The quick brown fox jumps. Over the lazy dog"
+        .split(' ')
+        .enumerate()
+        .map(|(i, word)| Timing {
+            text: word.to_string(),
+            start: i as f64 * 0.25,
+            end: Some(i as f64 * 0.25 + 0.25),
+        })
+        .collect();
+    let config = SubtitleConfig {
+        max_line_length: 10,
+        max_lines: 2,
+        min_duration: 1.5,
+    };
+    let cues = cues_from_words(&words, &config);
+    let texts: Vec<&str> = cues.iter().map(|cue| cue.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        vec!["The quick\nbrown fox", "jumps.", "Over the\nlazy dog"]
+    );
+    let times: Vec<(f64, f64)> = cues.iter().map(|cue| (cue.start, cue.end)).collect();
+    // Only the last cue is extended to the minimum duration since the others
+    // would overlap the next cue.
+    assert_eq!(times, vec![(0.0, 1.0), (1.0, 1.25), (1.25, 2.75)]);
+}
+
+#[test]
+fn test_srt_and_vtt() {
+    let cues = vec![
+        Cue::new(0.0, 1.5, "Hello,\nworld!"),
+        Cue::new(3661.5, 3662.0, "Bye"),
+    ];
+    let srt = to_srt(&cues);
+    let expected = "1\n00:00:00,000 --> 00:00:01,500\nHello,\nworld!\n\n\
+                    2\n01:01:01,500 --> 01:01:02,000\nBye\n\n";
+    assert_eq!(srt, expected);
+    assert_eq!(parse_srt(&srt).unwrap(), cues);
+    assert_eq!(parse_srt(&srt.replace('\n', "\r\n")).unwrap(), cues);
+    let vtt = to_vtt(&cues);
+    assert!(vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:01.500\n"));
+    assert_eq!(parse_vtt(&vtt).unwrap(), cues);
+
+    let vtt = "WEBVTT - Title\n\nNOTE a comment\n\nSTYLE\n::cue { color: red }\n\n\
+               intro\n00:01.000 --> 00:02.500 align:start\n<v Bob>Hi</v>\n";
+    let cues = parse_vtt(vtt).unwrap();
+    assert_eq!(cues, vec![Cue::new(1.0, 2.5, "<v Bob>Hi</v>")]);
+    assert!(parse_vtt("1\n00:00:00,000 --> 00:00:01,000\nHi").is_err());
+    assert!(parse_srt("1\n00:00:00 --> 00:00:01,000\nHi").is_err());
+}
+
+#[test]
+fn test_blank_lines_in_cue() {
+    let cues = vec![
+        Cue::new(0.0, 1.0, "First\n\n\nparagraph\n \n"),
+        Cue::new(1.0, 2.0, "2\n\n00:00:05,000 --> 00:00:06,000"),
+    ];
+    let expected = vec![
+        Cue::new(0.0, 1.0, "First\nparagraph"),
+        Cue::new(1.0, 2.0, "2\n00:00:05,000 --> 00:00:06,000"),
+    ];
+    assert_eq!(parse_srt(&to_srt(&cues)).unwrap(), expected);
+    let vtt = parse_vtt(&to_vtt(&cues)).unwrap();
+    assert_eq!(vtt[0], expected[0]);
+    assert_eq!(vtt.len(), 2);
+}
@@ -1,7 +1,14 @@
 //! Text-to-image.
 //!
 //! Functionality related to text-to-image.
+//!
+//! Hyperbolic and DeepInfra use their native endpoints. OpenAI, TogetherAI,
+//! Fireworks, Nebius, and OpenAI-compatible providers use the
+//! `/images/generations` endpoint. Replicate runs the model as a job, see
+//! [crate::jobs].
 
+use crate::jobs::Job;
+use crate::jobs::PollConfig;
 use crate::request_headers;
 use crate::Key;
 use crate::Provider;
@@ -10,69 +17,528 @@ use bytes::Bytes;
 use reqwest;
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::json;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::error::Error;
 
+/// How `/images/generations` returns the images.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageResponseFormat {
+    /// URLs which are downloaded by [ImageResponse::images].
+    Url,
+    B64Json,
+}
+
 /// Configuration for text-to-image.
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// Each provider maps the options to its own field names. Options that a
+/// provider does not support result in an error instead of being dropped.
+/// Options that are not listed here can be passed via `other`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TTIConfig {
     pub model: String,
+    /// Number of inference steps.
     pub steps: Option<u32>,
-    pub cfg_scale: Option<u32>,
+    /// Guidance scale, which is called `guidance_scale` by some providers.
+    pub cfg_scale: Option<f64>,
     pub height: Option<u32>,
     pub width: Option<u32>,
+    pub seed: Option<u64>,
+    /// What the image should not contain.
+    pub negative_prompt: Option<String>,
+    /// Number of images.
+    pub n: Option<u32>,
+    pub sampler: Option<String>,
+    /// Image format such as `png`, `jpeg`, or `webp`.
+    pub output_format: Option<String>,
+    /// Size such as `1024x1024`.
+    ///
+    /// For providers that take a width and height, this is an alternative to
+    /// `width` and `height`.
+    pub size: Option<String>,
+    /// Quality such as `hd` or `high`.
+    pub quality: Option<String>,
+    /// Style such as `vivid` or `natural`.
+    pub style: Option<String>,
+    pub response_format: Option<ImageResponseFormat>,
+    pub other: Option<HashMap<String, Value>>,
 }
 
 impl Default for TTIConfig {
+    /// The default model with the provider defaults for all other options.
     fn default() -> Self {
         Self {
             model: "FLUX.1-dev".to_string(),
-            steps: Some(10),
-            cfg_scale: Some(3),
-            height: Some(128),
-            width: Some(128),
+            steps: None,
+            cfg_scale: None,
+            height: None,
+            width: None,
+            seed: None,
+            negative_prompt: None,
+            n: None,
+            sampler: None,
+            output_format: None,
+            size: None,
+            quality: None,
+            style: None,
+            response_format: None,
+            other: None,
+        }
+    }
+}
+
+/// Whether the provider uses the OpenAI `/images/generations` endpoint.
+pub(crate) fn uses_images_generations(provider: &Provider) -> bool {
+    matches!(
+        provider,
+        Provider::OpenAI
+            | Provider::TogetherAI
+            | Provider::Fireworks
+            | Provider::Nebius
+            | Provider::OpenAICompatible(_)
+    )
+}
+
+fn address(
+    provider: &Provider,
+    key: &Key,
+    model: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let address = match provider {
+        Provider::Hyperbolic => format!("{}/v1/image/generation", crate::domain(provider, key)),
+        Provider::DeepInfra => format!("{}/v1/inference/{}", crate::domain(provider, key), model),
+        provider if uses_images_generations(provider) => {
+            format!(
+                "{}/images/generations",
+                crate::openai_base_url(provider, key)
+            )
+        }
+        _ => return Err(format!("Unsupported text-to-image provider: {provider}").into()),
+    };
+    Ok(address)
+}
+
+/// The width and height from `width` and `height` or else from `size`.
+fn dimensions(
+    config: &TTIConfig,
+) -> Result<(Option<u32>, Option<u32>), Box<dyn Error + Send + Sync>> {
+    match &config.size {
+        Some(size) if config.width.is_none() && config.height.is_none() => {
+            let (width, height) = size
+                .split_once('x')
+                .ok_or_else(|| format!("invalid size: {size}"))?;
+            Ok((Some(width.parse()?), Some(height.parse()?)))
         }
+        Some(_) => Err("set either size or width and height".into()),
+        None => Ok((config.width, config.height)),
     }
 }
 
-fn address(key: &Key, model: &str) -> String {
-    match key.provider {
-        Provider::Hyperbolic => format!("{}/v1/image/generation", key.provider.domain()),
-        Provider::DeepInfra => format!("{}/v1/inference/{}", key.provider.domain(), model),
-        _ => format!("{}/v1/image/generation", key.provider.domain()),
+/// Set `body[name]` if `value` is set.
+fn set<T: Into<Value>>(body: &mut Value, name: &str, value: Option<T>) {
+    if let Some(value) = value {
+        body[name] = value.into();
+    }
+}
+
+fn response_format(provider: &Provider, format: Option<ImageResponseFormat>) -> Option<&str> {
+    format.map(|format| match (provider, format) {
+        (Provider::TogetherAI, ImageResponseFormat::B64Json) => "base64",
+        (_, ImageResponseFormat::B64Json) => "b64_json",
+        (_, ImageResponseFormat::Url) => "url",
+    })
+}
+
+fn body(
+    provider: &Provider,
+    config: &TTIConfig,
+    prompt: &str,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let c = config;
+    let mut body = match provider {
+        Provider::Hyperbolic => {
+            crate::reject(
+                provider,
+                "text-to-image",
+                &[
+                    ("output_format", c.output_format.is_some()),
+                    ("quality", c.quality.is_some()),
+                    ("style", c.style.is_some()),
+                    ("response_format", c.response_format.is_some()),
+                ],
+            )?;
+            let (width, height) = dimensions(c)?;
+            let mut body = json!({"model_name": c.model, "prompt": prompt});
+            set(&mut body, "width", width);
+            set(&mut body, "height", height);
+            set(&mut body, "steps", c.steps);
+            set(&mut body, "cfg_scale", c.cfg_scale);
+            set(&mut body, "seed", c.seed);
+            set(&mut body, "negative_prompt", c.negative_prompt.clone());
+            set(&mut body, "num_images", c.n);
+            set(&mut body, "sampler", c.sampler.clone());
+            body
+        }
+        Provider::DeepInfra => {
+            crate::reject(
+                provider,
+                "text-to-image",
+                &[
+                    ("sampler", c.sampler.is_some()),
+                    ("output_format", c.output_format.is_some()),
+                    ("quality", c.quality.is_some()),
+                    ("style", c.style.is_some()),
+                    ("response_format", c.response_format.is_some()),
+                ],
+            )?;
+            let (width, height) = dimensions(c)?;
+            let mut body = json!({"prompt": prompt});
+            set(&mut body, "width", width);
+            set(&mut body, "height", height);
+            set(&mut body, "num_inference_steps", c.steps);
+            set(&mut body, "guidance_scale", c.cfg_scale);
+            set(&mut body, "seed", c.seed);
+            set(&mut body, "negative_prompt", c.negative_prompt.clone());
+            set(&mut body, "num_images", c.n);
+            body
+        }
+        Provider::OpenAI => {
+            crate::reject(
+                provider,
+                "text-to-image",
+                &[
+                    ("steps", c.steps.is_some()),
+                    ("cfg_scale", c.cfg_scale.is_some()),
+                    ("seed", c.seed.is_some()),
+                    ("negative_prompt", c.negative_prompt.is_some()),
+                    ("sampler", c.sampler.is_some()),
+                ],
+            )?;
+            let size = match (&c.size, c.width, c.height) {
+                (Some(size), None, None) => Some(size.clone()),
+                (None, Some(width), Some(height)) => Some(format!("{width}x{height}")),
+                (None, None, None) => None,
+                _ => return Err("set either size or both width and height".into()),
+            };
+            let mut body = json!({"model": c.model, "prompt": prompt});
+            set(&mut body, "n", c.n);
+            set(&mut body, "size", size);
+            set(&mut body, "quality", c.quality.clone());
+            set(&mut body, "style", c.style.clone());
+            set(&mut body, "output_format", c.output_format.clone());
+            set(
+                &mut body,
+                "response_format",
+                response_format(provider, c.response_format),
+            );
+            body
+        }
+        Provider::TogetherAI | Provider::Nebius | Provider::Fireworks => {
+            crate::reject(
+                provider,
+                "text-to-image",
+                &[
+                    ("sampler", c.sampler.is_some()),
+                    ("quality", c.quality.is_some()),
+                    ("style", c.style.is_some()),
+                ],
+            )?;
+            let (width, height) = dimensions(c)?;
+            let mut body = json!({"model": c.model, "prompt": prompt});
+            set(&mut body, "n", c.n);
+            set(&mut body, "width", width);
+            set(&mut body, "height", height);
+            let steps = if provider == &Provider::TogetherAI {
+                "steps"
+            } else {
+                "num_inference_steps"
+            };
+            set(&mut body, steps, c.steps);
+            set(&mut body, "guidance_scale", c.cfg_scale);
+            set(&mut body, "seed", c.seed);
+            set(&mut body, "negative_prompt", c.negative_prompt.clone());
+            let output_format = if provider == &Provider::Nebius {
+                "response_extension"
+            } else {
+                "output_format"
+            };
+            set(&mut body, output_format, c.output_format.clone());
+            set(
+                &mut body,
+                "response_format",
+                response_format(provider, c.response_format),
+            );
+            body
+        }
+        Provider::Replicate => {
+            crate::reject(
+                provider,
+                "text-to-image",
+                &[
+                    ("sampler", c.sampler.is_some()),
+                    ("quality", c.quality.is_some()),
+                    ("style", c.style.is_some()),
+                    ("response_format", c.response_format.is_some()),
+                ],
+            )?;
+            // The names that most Replicate image models use for their input.
+            let (width, height) = dimensions(c)?;
+            let mut body = json!({"prompt": prompt});
+            set(&mut body, "width", width);
+            set(&mut body, "height", height);
+            set(&mut body, "num_outputs", c.n);
+            set(&mut body, "num_inference_steps", c.steps);
+            set(&mut body, "guidance_scale", c.cfg_scale);
+            set(&mut body, "seed", c.seed);
+            set(&mut body, "negative_prompt", c.negative_prompt.clone());
+            set(&mut body, "output_format", c.output_format.clone());
+            body
+        }
+        Provider::OpenAICompatible(_) => {
+            // Unknown servers get every option with the OpenAI names.
+            let mut body = json!({"model": c.model, "prompt": prompt});
+            set(&mut body, "n", c.n);
+            set(&mut body, "size", c.size.clone());
+            set(&mut body, "width", c.width);
+            set(&mut body, "height", c.height);
+            set(&mut body, "steps", c.steps);
+            set(&mut body, "guidance_scale", c.cfg_scale);
+            set(&mut body, "seed", c.seed);
+            set(&mut body, "negative_prompt", c.negative_prompt.clone());
+            set(&mut body, "sampler", c.sampler.clone());
+            set(&mut body, "output_format", c.output_format.clone());
+            set(&mut body, "quality", c.quality.clone());
+            set(&mut body, "style", c.style.clone());
+            set(
+                &mut body,
+                "response_format",
+                response_format(provider, c.response_format),
+            );
+            body
+        }
+        _ => return Err(format!("Unsupported text-to-image provider: {provider}").into()),
+    };
+    if let Some(other) = &c.other {
+        for (key, value) in other {
+            body[key] = value.clone();
+        }
     }
+    Ok(body)
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Base64Image {
     pub index: u64,
     pub random_seed: Option<u64>,
+    /// The base64 encoded image, which is empty if the image is at [Base64Image::url].
     pub image: String,
+    /// The URL of the image for the `url` response format.
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// Image format detected from the magic bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Gif,
+}
+
+impl ImageFormat {
+    /// Detect the format from the first bytes of the image.
+    pub fn detect(bytes: &[u8]) -> Option<ImageFormat> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(ImageFormat::Webp)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(ImageFormat::Gif)
+        } else {
+            None
+        }
+    }
+    /// The file extension without a dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Gif => "gif",
+        }
+    }
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Gif => "image/gif",
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+fn u16_be(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 2)?;
+    Some(u16::from_be_bytes([b[0], b[1]]) as u32)
+}
+
+fn u16_le(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 2)?;
+    Some(u16::from_le_bytes([b[0], b[1]]) as u32)
+}
+
+fn u24_le(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], 0]))
+}
+
+fn png_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let b = bytes.get(16..24)?;
+    let width = u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+    let height = u32::from_be_bytes([b[4], b[5], b[6], b[7]]);
+    Some((width, height))
+}
+
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut i = 2;
+    loop {
+        // Markers may be preceded by any number of 0xff fill bytes.
+        while *bytes.get(i)? == 0xff && *bytes.get(i + 1)? == 0xff {
+            i += 1;
+        }
+        if *bytes.get(i)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(i + 1)?;
+        i += 2;
+        match marker {
+            0x01 | 0xd0..=0xd8 => continue,
+            0xd9 | 0xda => return None,
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                let height = u16_be(bytes, i + 3)?;
+                let width = u16_be(bytes, i + 5)?;
+                return Some((width, height));
+            }
+            _ => i += u16_be(bytes, i)? as usize,
+        }
+    }
+}
+
+fn webp_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        b"VP8 " => {
+            let width = u16_le(bytes, 26)? & 0x3fff;
+            let height = u16_le(bytes, 28)? & 0x3fff;
+            Some((width, height))
+        }
+        b"VP8L" => {
+            let b = bytes.get(21..25)?;
+            let bits = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+            Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+        }
+        b"VP8X" => Some((u24_le(bytes, 24)? + 1, u24_le(bytes, 27)? + 1)),
+        _ => None,
+    }
+}
+
+fn gif_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    Some((u16_le(bytes, 6)?, u16_le(bytes, 8)?))
 }
 
 pub struct Image {
+    /// The file extension such as `png` or `unknown`.
     pub filetype: String,
+    /// The format if it was detected from the magic bytes.
+    pub format: Option<ImageFormat>,
     pub image: Bytes,
 }
 
+impl Image {
+    /// Create an image and detect the format from the magic bytes.
+    pub fn new(image: Bytes) -> Self {
+        let format = ImageFormat::detect(&image);
+        let filetype = format.map_or("unknown", |format| format.extension());
+        Self {
+            filetype: filetype.to_string(),
+            format,
+            image,
+        }
+    }
+    /// The width and height in pixels read from the header.
+    pub fn dimensions(&self) -> Result<(u32, u32), Box<dyn Error + Send + Sync>> {
+        let format = self
+            .format
+            .ok_or("cannot read the dimensions of an image of unknown format")?;
+        let bytes = &self.image;
+        let dimensions = match format {
+            ImageFormat::Png => png_dimensions(bytes),
+            ImageFormat::Jpeg => jpeg_dimensions(bytes),
+            ImageFormat::Webp => webp_dimensions(bytes),
+            ImageFormat::Gif => gif_dimensions(bytes),
+        };
+        dimensions.ok_or_else(|| format!("invalid {format} header").into())
+    }
+}
+
 impl Base64Image {
+    /// Decode the image and detect the format.
+    ///
+    /// The format is detected from the magic bytes and, if that fails, from a
+    /// `data:image/...;base64,` prefix.
     pub fn base64_decode(&self) -> Result<Image, Box<dyn Error + Send + Sync>> {
         let re = regex::Regex::new(r"^data:image/(\w+);base64,").unwrap();
-        let filetype = match re
+        let prefix = re
             .captures(&self.image)
-            .map(|cap| cap.get(1).unwrap().as_str())
-        {
-            Some("png") => "png",
-            Some("jpg" | "jpeg") => "jpg",
-            _ => "unknown",
-        };
+            .and_then(|cap| cap.get(1))
+            .map(|filetype| filetype.as_str().to_string());
         let image = re.replace(&self.image, "").to_string();
-        let bytes = BASE64_STANDARD.decode(image.as_bytes()).expect("no decode");
-        Ok(Image {
-            filetype: filetype.to_string(),
-            image: Bytes::from(bytes),
-        })
+        if image.is_empty() {
+            if let Some(url) = &self.url {
+                return Err(format!("image is at {url}; use ImageResponse::images").into());
+            }
+        }
+        let bytes = BASE64_STANDARD
+            .decode(image.trim().as_bytes())
+            .map_err(|e| format!("invalid base64 image {}: {e}", self.index))?;
+        let mut image = Image::new(Bytes::from(bytes));
+        if image.format.is_none() {
+            let filetype = match prefix.as_deref() {
+                Some("png") => "png",
+                Some("jpg" | "jpeg") => "jpg",
+                Some("webp") => "webp",
+                Some("gif") => "gif",
+                _ => "unknown",
+            };
+            image.filetype = filetype.to_string();
+        }
+        Ok(image)
+    }
+}
+
+/// Download the image at `url`.
+async fn download(url: &str) -> Result<Image, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::Client::new();
+    let resp = crate::http::send(client.get(url)).await?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(format!("downloading image failed with status {status}").into());
     }
+    Ok(Image::new(resp.bytes().await?))
 }
 
 #[derive(Debug, Deserialize)]
@@ -81,8 +547,8 @@ pub struct Images {
 }
 
 pub struct ImageResponse {
-    provider: Provider,
-    resp: Bytes,
+    pub(crate) provider: Provider,
+    pub(crate) resp: Bytes,
 }
 
 impl ImageResponse {
@@ -95,16 +561,67 @@ impl ImageResponse {
     pub fn structured(&self) -> Result<Images, Box<dyn Error + Send + Sync>> {
         let resp = self.raw_value()?;
         tracing::debug!("Response: {resp}");
-        let resp: Images = if self.provider == Provider::DeepInfra {
+        let resp: Images = if self.provider == Provider::Replicate {
+            let job: Job = serde_json::from_value(resp)?;
+            job.check()?;
+            let images = job
+                .outputs()
+                .into_iter()
+                .enumerate()
+                .map(|(index, url)| Base64Image {
+                    index: index as u64,
+                    random_seed: None,
+                    image: String::new(),
+                    url: Some(url),
+                })
+                .collect();
+            Images { images }
+        } else if self.provider == Provider::DeepInfra {
             if resp.get("detail").is_some() {
                 return Err(format!("DeepInfra returned an error: {}", resp["detail"]).into());
             }
-            let image = resp["images"][0].clone();
-            let images: Vec<Base64Image> = vec![Base64Image {
-                index: 0,
-                random_seed: None,
-                image: image.as_str().unwrap().to_string(),
-            }];
+            let images = resp["images"]
+                .as_array()
+                .ok_or_else(|| format!("no images in response: {resp}"))?
+                .iter()
+                .enumerate()
+                .map(|(index, image)| {
+                    let image = image
+                        .as_str()
+                        .ok_or_else(|| format!("invalid image in response: {image}"))?;
+                    Ok(Base64Image {
+                        index: index as u64,
+                        random_seed: None,
+                        image: image.to_string(),
+                        url: None,
+                    })
+                })
+                .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+            Images { images }
+        } else if uses_images_generations(&self.provider) {
+            if resp.get("error").is_some() {
+                return Err(crate::extract_error(&resp).into());
+            }
+            let data = resp["data"]
+                .as_array()
+                .ok_or_else(|| format!("no data in response: {resp}"))?;
+            let images = data
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    let image = item["b64_json"].as_str().unwrap_or_default().to_string();
+                    let url = item["url"].as_str().map(|url| url.to_string());
+                    if image.is_empty() && url.is_none() {
+                        return Err(format!("no b64_json or url in image: {item}"));
+                    }
+                    Ok(Base64Image {
+                        index: item["index"].as_u64().unwrap_or(index as u64),
+                        random_seed: item["seed"].as_u64(),
+                        image,
+                        url,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
             Images { images }
         } else {
             match serde_json::from_value(resp.clone()) {
@@ -116,6 +633,17 @@ impl ImageResponse {
         };
         Ok(resp)
     }
+    /// The decoded images, which are downloaded first if they are URLs.
+    pub async fn images(&self) -> Result<Vec<Image>, Box<dyn Error + Send + Sync>> {
+        let mut images = vec![];
+        for image in self.structured()?.images {
+            match &image.url {
+                Some(url) if image.image.is_empty() => images.push(download(url).await?),
+                _ => images.push(image.base64_decode()?),
+            }
+        }
+        Ok(images)
+    }
 }
 
 pub async fn text_to_image(
@@ -123,34 +651,136 @@ pub async fn text_to_image(
     config: TTIConfig,
     prompt: &str,
 ) -> Result<ImageResponse, Box<dyn Error + Send + Sync>> {
-    let address = address(key, &config.model);
-    let mut body = serde_json::json!({
-        "model_name": config.model,
-        "prompt": prompt,
-    });
-    if let Some(steps) = config.steps {
-        body["steps"] = serde_json::Value::from(steps);
-    }
-    if let Some(cfg_scale) = config.cfg_scale {
-        body["cfg_scale"] = serde_json::Value::from(cfg_scale);
-    }
-    if let Some(height) = config.height {
-        body["height"] = serde_json::Value::from(height);
-    }
-    if let Some(width) = config.width {
-        body["width"] = serde_json::Value::from(width);
+    if key.provider == Provider::Replicate {
+        let input = body(&key.provider, &config, prompt)?;
+        let job = crate::jobs::run(key, &config.model, &input, &PollConfig::default()).await?;
+        let image_response = ImageResponse {
+            provider: key.provider.clone(),
+            resp: Bytes::from(serde_json::to_vec(&job)?),
+        };
+        return Ok(image_response);
     }
-    tracing::debug!("Requesting image: {body}");
+    let address = address(&key.provider, key, &config.model)?;
+    let body = body(&key.provider, &config, prompt)?;
     let client = reqwest::Client::new();
-    let resp = client
+    let req = client
         .post(address)
         .headers(request_headers(key)?)
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    let resp = crate::http::send(req).await?;
     let image_response = ImageResponse {
         provider: key.provider.clone(),
         resp: resp.bytes().await?,
     };
     Ok(image_response)
 }
+
+#[test]
+fn test_body() {
+    let config = TTIConfig {
+        cfg_scale: Some(3.5),
+        n: Some(2),
+        size: Some("512x768".to_string()),
+        negative_prompt: Some("blur".to_string()),
+        ..Default::default()
+    };
+    let hyperbolic = body(&Provider::Hyperbolic, &config, "A cat").unwrap();
+    assert_eq!(hyperbolic["model_name"], "FLUX.1-dev");
+    assert_eq!(hyperbolic["cfg_scale"], 3.5);
+    assert_eq!(hyperbolic["num_images"], 2);
+    assert_eq!(hyperbolic["width"], 512);
+    assert_eq!(hyperbolic["height"], 768);
+    let deepinfra = body(&Provider::DeepInfra, &config, "A cat").unwrap();
+    assert_eq!(deepinfra["guidance_scale"], 3.5);
+    assert_eq!(deepinfra["negative_prompt"], "blur");
+    assert!(deepinfra.get("model_name").is_none());
+    let err = body(&Provider::OpenAI, &config, "A cat").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "OpenAI does not support the text-to-image options: cfg_scale, negative_prompt"
+    );
+
+    let mut other = HashMap::new();
+    other.insert("background".to_string(), json!("transparent"));
+    let config = TTIConfig {
+        model: "gpt-image-1".to_string(),
+        width: Some(1024),
+        height: Some(1536),
+        output_format: Some("webp".to_string()),
+        other: Some(other),
+        ..Default::default()
+    };
+    let openai = body(&Provider::OpenAI, &config, "A cat").unwrap();
+    assert_eq!(openai["size"], "1024x1536");
+    assert_eq!(openai["output_format"], "webp");
+    assert_eq!(openai["background"], "transparent");
+    assert!(body(&Provider::Hyperbolic, &config, "A cat").is_err());
+}
+
+#[test]
+fn test_image_format() {
+    let png = Image::new(Bytes::from_static(crate::mock_server::PNG));
+    assert_eq!(png.format, Some(ImageFormat::Png));
+    assert_eq!(png.filetype, "png");
+    assert_eq!(png.dimensions().unwrap(), (1, 1));
+
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend_from_slice(&[0x20, 0x03, 0x58, 0x02]);
+    let gif = Image::new(Bytes::from(gif));
+    assert_eq!(gif.format, Some(ImageFormat::Gif));
+    assert_eq!(gif.dimensions().unwrap(), (800, 600));
+
+    // SOI, an APP0 segment, and a SOF0 segment.
+    let jpeg = vec![
+        0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xc0, 0x00, 0x11, 0x08, 0x02, 0x58,
+        0x03, 0x20,
+    ];
+    let jpeg = Image::new(Bytes::from(jpeg));
+    assert_eq!(jpeg.filetype, "jpg");
+    assert_eq!(jpeg.dimensions().unwrap(), (800, 600));
+
+    let webp = |chunk: &[u8], data: &[u8]| {
+        let mut bytes = b"RIFF\0\0\0\0WEBP".to_vec();
+        bytes.extend_from_slice(chunk);
+        bytes.extend_from_slice(data);
+        Image::new(Bytes::from(bytes))
+    };
+    let mut vp8x = vec![0; 8];
+    vp8x.extend_from_slice(&[0x1f, 0x03, 0x00, 0x57, 0x02, 0x00]);
+    let vp8x = webp(b"VP8X", &vp8x);
+    assert_eq!(vp8x.format, Some(ImageFormat::Webp));
+    assert_eq!(vp8x.dimensions().unwrap(), (800, 600));
+    // Width 800 and height 600 minus one packed in 14 bits each.
+    let bits: u32 = 799 | (599 << 14);
+    let mut vp8l = vec![0; 5];
+    vp8l.extend_from_slice(&bits.to_le_bytes());
+    assert_eq!(webp(b"VP8L", &vp8l).dimensions().unwrap(), (800, 600));
+    let mut vp8 = vec![0; 10];
+    vp8.extend_from_slice(&[0x20, 0x03, 0x58, 0x02]);
+    assert_eq!(webp(b"VP8 ", &vp8).dimensions().unwrap(), (800, 600));
+
+    let unknown = Image::new(Bytes::from_static(b"not an image"));
+    assert_eq!(unknown.format, None);
+    assert!(unknown.dimensions().is_err());
+}
+
+#[test]
+fn test_base64_decode() {
+    let encoded = Base64Image {
+        index: 0,
+        random_seed: None,
+        image: BASE64_STANDARD.encode(crate::mock_server::PNG),
+        url: None,
+    };
+    assert_eq!(
+        encoded.base64_decode().unwrap().format,
+        Some(ImageFormat::Png)
+    );
+    let invalid = Base64Image {
+        index: 0,
+        random_seed: None,
+        image: "data:image/png;base64,!!!".to_string(),
+        url: None,
+    };
+    assert!(invalid.base64_decode().is_err());
+}
@@ -1,30 +1,124 @@
 //! Text-to-speech.
 //!
 //! Functionality related to text-to-speech.
+//!
+//! Use [tts] to get the complete audio at once or [tts_stream] to receive the
+//! audio in chunks while it is being synthesized. For texts that are longer
+//! than what the provider accepts in one request, use [tts_long]. The voices
+//! that can be passed as [TTSConfig::voice] are listed by [voices].
 
+use crate::audio::AudioFormat;
 use crate::request_headers;
 use crate::Key;
 use crate::Provider;
+use async_stream::stream;
 use base64::prelude::*;
 use bytes::Bytes;
+use futures::Stream;
+use futures::StreamExt;
 use reqwest;
 use reqwest::header::HeaderMap;
 use reqwest::header::HeaderValue;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::header::USER_AGENT;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::json;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::error::Error;
+use std::pin::Pin;
+
+/// Voice settings that override the stored settings of an ElevenLabs voice.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VoiceSettings {
+    /// From 0 to 1. Lower values give a more expressive voice.
+    pub stability: Option<f64>,
+    /// From 0 to 1. How closely to follow the original voice.
+    pub similarity_boost: Option<f64>,
+    /// From 0 to 1. How much to exaggerate the style of the voice.
+    pub style: Option<f64>,
+    /// From 0.7 to 1.2.
+    pub speed: Option<f64>,
+    pub use_speaker_boost: Option<bool>,
+}
+
+/// A pronunciation dictionary in ElevenLabs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PronunciationDictionaryLocator {
+    pub pronunciation_dictionary_id: String,
+    /// The latest version is used if not set.
+    pub version_id: Option<String>,
+}
 
 /// Text-to-speech config
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct TTSConfig {
-    pub output_format: Option<String>,
+    /// The format of the audio.
+    ///
+    /// Defaults to WAV for Google, Groq, and TogetherAI, and to MP3 for the
+    /// other providers.
+    pub output_format: Option<AudioFormat>,
     pub voice: Option<String>,
     pub speed: Option<f64>,
     pub language_code: Option<String>,
     pub seed: Option<u64>,
+    /// Whether the text is SSML instead of plain text.
+    ///
+    /// Supported by Google and Azure. For Azure, the text must be a complete
+    /// `<speak>` document.
+    #[serde(default)]
+    pub ssml: bool,
+    /// Pitch in semitones from -20 to 20.
+    ///
+    /// Only supported by Google.
+    pub pitch: Option<f64>,
+    /// Volume gain in dB from -96 to 16.
+    ///
+    /// Only supported by Google.
+    pub volume_gain_db: Option<f64>,
+    /// Sample rate in Hz.
+    ///
+    /// Only supported by Google. For PCM, the sample rate of the format is
+    /// used.
+    pub sample_rate: Option<u32>,
+    /// Effects profiles such as `headphone-class-device` that optimize the
+    /// audio for the device that plays it.
+    ///
+    /// Only supported by Google.
+    pub effects_profile_id: Option<Vec<String>>,
+    /// Return the time of each SSML `<mark name="..."/>` in the text.
+    ///
+    /// Only supported by Google.
+    #[serde(default)]
+    pub enable_time_pointing: bool,
+    /// Return when each word is spoken, and for ElevenLabs also each
+    /// character.
+    ///
+    /// Supported by Google and ElevenLabs. For Google, the text must be plain
+    /// text since a `<mark>` is added before each word.
+    #[serde(default)]
+    pub timestamps: bool,
+    /// Only supported by ElevenLabs. [TTSConfig::speed] is used as the speed
+    /// if set.
+    pub voice_settings: Option<VoiceSettings>,
+    /// The text before this text, which improves the flow of speech that is
+    /// synthesized in parts.
+    ///
+    /// Only supported by ElevenLabs.
+    pub previous_text: Option<String>,
+    /// The text after this text.
+    ///
+    /// Only supported by ElevenLabs.
+    pub next_text: Option<String>,
+    /// At most three dictionaries that are applied in order.
+    ///
+    /// Only supported by ElevenLabs.
+    pub pronunciation_dictionary_locators: Option<Vec<PronunciationDictionaryLocator>>,
+    /// The region of the Speech resource, such as `westeurope`.
+    ///
+    /// Required for Azure.
+    pub region: Option<String>,
     pub other: Option<HashMap<String, Value>>,
 }
 
@@ -54,6 +148,18 @@ impl PartialEq for TTSConfig {
             && self.speed == other.speed
             && self.language_code == other.language_code
             && self.seed == other.seed
+            && self.ssml == other.ssml
+            && self.pitch == other.pitch
+            && self.volume_gain_db == other.volume_gain_db
+            && self.sample_rate == other.sample_rate
+            && self.effects_profile_id == other.effects_profile_id
+            && self.enable_time_pointing == other.enable_time_pointing
+            && self.timestamps == other.timestamps
+            && self.voice_settings == other.voice_settings
+            && self.previous_text == other.previous_text
+            && self.next_text == other.next_text
+            && self.pronunciation_dictionary_locators == other.pronunciation_dictionary_locators
+            && self.region == other.region
             && compare_hashmap(&self.other, &other.other)
     }
 }
@@ -79,76 +185,369 @@ fn is_openai_compatible(provider: &Provider) -> bool {
     matches!(provider, Provider::OpenAICompatible(_))
 }
 
-fn address(provider: &Provider, key: &Key, model: Option<&str>, config: &TTSConfig) -> String {
-    if provider == &Provider::ElevenLabs {
+/// Whether the provider has an OpenAI-style `/audio/speech` endpoint.
+fn is_openai_style(provider: &Provider) -> bool {
+    matches!(
+        provider,
+        Provider::OpenAI | Provider::OpenAICompatible(_) | Provider::Groq | Provider::TogetherAI
+    )
+}
+
+/// The name of the format in the request to the provider.
+fn format_param(
+    provider: &Provider,
+    format: AudioFormat,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let unsupported = || format!("{provider} does not support {format} output");
+    let param = match (provider, format) {
+        (Provider::OpenAI, AudioFormat::Pcm { sample_rate }) if sample_rate != 24000 => {
+            return Err("OpenAI only supports pcm output at 24000 Hz".into());
+        }
+        (Provider::OpenAI | Provider::OpenAICompatible(_) | Provider::DeepInfra, format) => {
+            format.extension().to_string()
+        }
+        (Provider::ElevenLabs, AudioFormat::Mp3) => "mp3_44100_128".to_string(),
+        (Provider::ElevenLabs, AudioFormat::Opus) => "opus_48000_128".to_string(),
+        (Provider::ElevenLabs, AudioFormat::Pcm { sample_rate }) => {
+            if ![8000, 16000, 22050, 24000, 44100, 48000].contains(&sample_rate) {
+                return Err(
+                    format!("ElevenLabs does not support pcm output at {sample_rate} Hz").into(),
+                );
+            }
+            format!("pcm_{sample_rate}")
+        }
+        (Provider::Google, AudioFormat::Mp3) => "MP3".to_string(),
+        (Provider::Google, AudioFormat::Opus) => "OGG_OPUS".to_string(),
+        (Provider::Google, AudioFormat::Wav) => "LINEAR16".to_string(),
+        (Provider::Google, AudioFormat::Pcm { .. }) => "PCM".to_string(),
+        (Provider::Hyperbolic, AudioFormat::Mp3) => "mp3".to_string(),
+        (Provider::Groq, AudioFormat::Mp3 | AudioFormat::Wav | AudioFormat::Flac) => {
+            format.extension().to_string()
+        }
+        (Provider::TogetherAI, AudioFormat::Mp3 | AudioFormat::Wav) => {
+            format.extension().to_string()
+        }
+        // Sent in the `X-Microsoft-OutputFormat` header.
+        (Provider::Azure, AudioFormat::Mp3) => "audio-24khz-48kbitrate-mono-mp3".to_string(),
+        (Provider::Azure, AudioFormat::Opus) => "ogg-24khz-16bit-mono-opus".to_string(),
+        (Provider::Azure, AudioFormat::Wav) => "riff-24khz-16bit-mono-pcm".to_string(),
+        (Provider::Azure, AudioFormat::Pcm { sample_rate }) => {
+            let rate = match sample_rate {
+                8000 | 16000 | 24000 | 48000 => format!("{}khz", sample_rate / 1000),
+                22050 | 44100 => format!("{sample_rate}hz"),
+                _ => {
+                    return Err(
+                        format!("Azure does not support pcm output at {sample_rate} Hz").into(),
+                    )
+                }
+            };
+            format!("raw-{rate}-16bit-mono-pcm")
+        }
+        _ => return Err(unsupported().into()),
+    };
+    Ok(param)
+}
+
+#[test]
+fn test_format_param() {
+    let pcm = |sample_rate| AudioFormat::Pcm { sample_rate };
+    let param = |provider: &Provider, format| format_param(provider, format);
+    assert_eq!(param(&Provider::OpenAI, AudioFormat::Flac).unwrap(), "flac");
+    assert_eq!(param(&Provider::OpenAI, pcm(24000)).unwrap(), "pcm");
+    assert!(param(&Provider::OpenAI, pcm(16000)).is_err());
+    assert_eq!(
+        param(&Provider::ElevenLabs, pcm(16000)).unwrap(),
+        "pcm_16000"
+    );
+    assert!(param(&Provider::ElevenLabs, pcm(12345)).is_err());
+    assert!(param(&Provider::ElevenLabs, AudioFormat::Wav).is_err());
+    assert_eq!(
+        param(&Provider::Google, AudioFormat::Wav).unwrap(),
+        "LINEAR16"
+    );
+    assert_eq!(
+        param(&Provider::Google, AudioFormat::Aac)
+            .unwrap_err()
+            .to_string(),
+        "Google does not support aac output"
+    );
+    assert!(param(&Provider::Hyperbolic, AudioFormat::Opus).is_err());
+    assert_eq!(param(&Provider::Groq, AudioFormat::Flac).unwrap(), "flac");
+    assert!(param(&Provider::TogetherAI, AudioFormat::Opus).is_err());
+    assert_eq!(
+        param(&Provider::Azure, pcm(22050)).unwrap(),
+        "raw-22050hz-16bit-mono-pcm"
+    );
+    assert_eq!(
+        param(&Provider::Azure, pcm(16000)).unwrap(),
+        "raw-16khz-16bit-mono-pcm"
+    );
+}
+
+/// The format that the provider returns when none is requested.
+fn default_format(provider: &Provider) -> AudioFormat {
+    match provider {
+        Provider::Google | Provider::Groq | Provider::TogetherAI => AudioFormat::Wav,
+        _ => AudioFormat::Mp3,
+    }
+}
+
+/// Google text-to-speech uses another domain than the other Google APIs.
+fn google_tts_domain(key: &Key) -> String {
+    match &key.base_url {
+        Some(base_url) => base_url.clone(),
+        None => "https://texttospeech.googleapis.com".to_string(),
+    }
+}
+
+fn address(
+    provider: &Provider,
+    key: &Key,
+    model: Option<&str>,
+    config: &TTSConfig,
+    stream: bool,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let address = if provider == &Provider::ElevenLabs {
         let voice = config
             .voice
             .as_ref()
-            .expect("voice is required for ElevenLabs");
-        if let Some(output_format) = &config.output_format {
+            .ok_or("voice is required for ElevenLabs")?;
+        let mut path = format!("/v1/text-to-speech/{voice}");
+        if stream {
+            path.push_str("/stream");
+        }
+        if config.timestamps {
+            path.push_str("/with-timestamps");
+        }
+        if let Some(output_format) = config.output_format {
+            let output_format = format_param(provider, output_format)?;
             format!(
-                "{}/v1/text-to-speech/{voice}?{output_format}",
-                provider.domain()
+                "{}{path}?output_format={output_format}",
+                crate::domain(provider, key)
             )
         } else {
-            format!("{}/v1/text-to-speech/{voice}", provider.domain())
+            format!("{}{path}", crate::domain(provider, key))
         }
     } else if provider == &Provider::DeepInfra {
         let model = model.unwrap_or("hexgrad/Kokoro-82M");
-        format!("{}/v1/inference/{}", provider.domain(), model)
+        format!("{}/v1/inference/{}", crate::domain(provider, key), model)
     } else if provider == &Provider::Hyperbolic {
-        format!("{}/v1/audio/generation", provider.domain())
+        format!("{}/v1/audio/generation", crate::domain(provider, key))
     } else if provider == &Provider::OpenAI {
-        format!("{}/v1/audio/speech", provider.domain())
-    } else if let Provider::OpenAICompatible(domain) = &provider {
-        format!("{domain}/v1/audio/speech")
+        format!("{}/v1/audio/speech", crate::domain(provider, key))
+    } else if provider == &Provider::Groq || provider == &Provider::TogetherAI {
+        format!("{}/audio/speech", crate::openai_base_url(provider, key))
+    } else if is_openai_compatible(provider) {
+        format!("{}/v1/audio/speech", crate::domain(provider, key))
     } else if provider == &Provider::Google {
-        let domain = "https://texttospeech.googleapis.com";
         let path = "/v1beta1/text:synthesize";
-        format!("{domain}{path}?key={}", key.key)
+        format!("{}{path}", google_tts_domain(key))
+    } else if provider == &Provider::Azure {
+        let domain = match &key.base_url {
+            Some(base_url) => base_url.clone(),
+            None => {
+                let region = config
+                    .region
+                    .as_ref()
+                    .ok_or("region is required for Azure")?;
+                format!("https://{region}.tts.speech.microsoft.com")
+            }
+        };
+        format!("{domain}/cognitiveservices/v1")
     } else {
-        panic!("Unsupported TTS provider: {}", provider);
+        return Err(format!("Unsupported TTS provider: {provider}").into());
+    };
+    Ok(address)
+}
+
+/// When a part of the text is spoken.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Timing {
+    pub text: String,
+    /// Start in seconds from the beginning of the audio.
+    pub start: f64,
+    /// End in seconds from the beginning of the audio, if known.
+    pub end: Option<f64>,
+}
+
+impl Timing {
+    pub(crate) fn shift(mut self, offset: f64) -> Timing {
+        self.start += offset;
+        self.end = self.end.map(|end| end + offset);
+        self
+    }
+}
+
+/// Group character timings into word timings.
+fn words_from_characters(characters: &[Timing]) -> Vec<Timing> {
+    let mut words = vec![];
+    let mut current: Option<Timing> = None;
+    for character in characters {
+        if character.text.trim().is_empty() {
+            words.extend(current.take());
+            continue;
+        }
+        match &mut current {
+            Some(word) => {
+                word.text.push_str(&character.text);
+                word.end = character.end;
+            }
+            None => current = Some(character.clone()),
+        }
+    }
+    words.extend(current);
+    words
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// SSML with a mark before each word that is named after the index of the word.
+fn google_word_ssml(text: &str) -> String {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .enumerate()
+        .map(|(i, word)| format!("<mark name=\"{i}\"/>{}", escape_xml(word)))
+        .collect();
+    format!("<speak>{}</speak>", words.join(" "))
+}
+
+/// Map the marks from [google_word_ssml] back to the words.
+///
+/// Each word ends when the next one starts and the last one ends at the end
+/// of the audio.
+fn google_words(words: &[String], marks: &[Timing], duration: Option<f64>) -> Vec<Timing> {
+    let mut timings: Vec<Timing> = marks
+        .iter()
+        .filter_map(|mark| {
+            let word = words.get(mark.text.parse::<usize>().ok()?)?;
+            Some(Timing {
+                text: word.clone(),
+                start: mark.start,
+                end: None,
+            })
+        })
+        .collect();
+    timings.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let starts: Vec<f64> = timings.iter().map(|timing| timing.start).collect();
+    for (i, timing) in timings.iter_mut().enumerate() {
+        timing.end = starts.get(i + 1).copied().or(duration);
     }
+    timings
+}
+
+#[test]
+fn test_timings() {
+    let timing = |text: &str, start: f64, end: Option<f64>| Timing {
+        text: text.to_string(),
+        start,
+        end,
+    };
+    let characters: Vec<Timing> = "Hi, you"
+        .chars()
+        .enumerate()
+        .map(|(i, c)| timing(&c.to_string(), i as f64, Some(i as f64 + 1.0)))
+        .collect();
+    let words = words_from_characters(&characters);
+    assert_eq!(
+        words,
+        vec![timing("Hi,", 0.0, Some(3.0)), timing("you", 4.0, Some(7.0))]
+    );
+
+    let ssml = google_word_ssml("Tom & Jerry");
+    let expected =
+        r#"<speak><mark name="0"/>Tom <mark name="1"/>&amp; <mark name="2"/>Jerry</speak>"#;
+    assert_eq!(ssml, expected);
+    let words: Vec<String> = vec!["Tom".into(), "&".into(), "Jerry".into()];
+    let marks = vec![
+        timing("1", 0.5, None),
+        timing("0", 0.0, None),
+        timing("2", 0.7, None),
+    ];
+    assert_eq!(
+        google_words(&words, &marks, Some(1.0)),
+        vec![
+            timing("Tom", 0.0, Some(0.5)),
+            timing("&", 0.5, Some(0.7)),
+            timing("Jerry", 0.7, Some(1.0)),
+        ]
+    );
 }
 
 #[derive(Debug)]
 pub struct Speech {
     pub request_id: Option<String>,
+    /// The file extension of the audio.
     pub file_format: String,
+    /// The format of the audio, checked against its first bytes.
+    pub format: AudioFormat,
     pub audio: Bytes,
+    /// When each word is spoken if [TTSConfig::timestamps] is set.
+    pub words: Option<Vec<Timing>>,
+    /// When each character is spoken if [TTSConfig::timestamps] is set.
+    ///
+    /// Only returned by ElevenLabs.
+    pub characters: Option<Vec<Timing>>,
+    /// When each SSML mark is reached if [TTSConfig::enable_time_pointing] is
+    /// set. The text is the name of the mark.
+    pub marks: Option<Vec<Timing>>,
 }
 
 impl Speech {
     /// Convert the base64 encoded audio to bytes.
     ///
+    /// A data URL prefix such as `data:audio/mp3;base64,` is removed first.
     /// These bytes can then, for example, be written to a file.
-    pub fn decode_speech(
-        audio: &str,
-        provider: &Provider,
-        output_format: Option<&str>,
-    ) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
-        let stripped = if provider == &Provider::DeepInfra {
-            let output_format = output_format.expect("no output format");
-            tracing::debug!("Decoding DeepInfra speech with output format: {output_format}");
-            let deepinfra_prefix = match output_format {
-                "mp3" => "data:audio/mp3;base64,",
-                "opus" => "data:audio/ogg; codec=\"opus\";base64,",
-                _ => panic!("Unsupported output format: {}", output_format),
-            };
-            match audio.strip_prefix(deepinfra_prefix) {
-                Some(stripped) => stripped,
-                None => panic!("prefix '{deepinfra_prefix}' not found"),
-            }
-        } else {
-            audio
+    pub fn decode_speech(audio: &str) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
+        let stripped = match audio.strip_prefix("data:") {
+            Some(url) => match url.split_once("base64,") {
+                Some((_, data)) => data,
+                None => return Err("data URL is not base64 encoded".into()),
+            },
+            None => audio,
         };
-        let bytes = BASE64_STANDARD.decode(stripped).expect("no decode");
+        let bytes = BASE64_STANDARD.decode(stripped)?;
         Ok(Bytes::from(bytes))
     }
+    fn new(request_id: Option<String>, expected: AudioFormat, audio: Bytes) -> Speech {
+        let format = match (expected, AudioFormat::detect(&audio)) {
+            (AudioFormat::Pcm { .. }, Some(AudioFormat::Wav)) => AudioFormat::Wav,
+            // Raw PCM has no header, so its bytes can look like an MP3 frame.
+            (AudioFormat::Pcm { .. }, _) | (_, None) => expected,
+            (_, Some(detected)) => {
+                if detected != expected {
+                    tracing::warn!("Requested {expected} audio but received {detected}");
+                }
+                detected
+            }
+        };
+        Speech {
+            request_id,
+            file_format: format.extension().to_string(),
+            format,
+            audio,
+            words: None,
+            characters: None,
+            marks: None,
+        }
+    }
+    /// The duration of the audio in seconds, if it can be determined from the
+    /// format.
+    pub fn duration(&self) -> Option<f64> {
+        crate::audio::duration(self.format, &self.audio)
+    }
 }
 
 pub struct SpeechResponse {
     provider: Provider,
+    output_format: Option<AudioFormat>,
+    /// The words that were marked to get word timings from Google.
+    words: Option<Vec<String>>,
     resp: Bytes,
 }
 
@@ -159,112 +558,368 @@ impl SpeechResponse {
     pub fn raw_value(&self) -> Result<Value, Box<dyn Error + Send + Sync>> {
         Ok(serde_json::from_slice::<Value>(&self.resp)?)
     }
+    /// The requested format or else the default of the provider.
+    fn expected_format(&self) -> AudioFormat {
+        self.output_format
+            .unwrap_or_else(|| default_format(&self.provider))
+    }
     pub fn structured(&self) -> Result<Speech, Box<dyn Error + Send + Sync>> {
+        let expected = self.expected_format();
         if self.provider == Provider::ElevenLabs {
-            Ok(Speech {
-                request_id: None,
-                file_format: "mp3".to_string(),
-                audio: self.resp.clone(),
-            })
+            let Ok(resp) = serde_json::from_slice::<Value>(&self.resp) else {
+                return Ok(Speech::new(None, expected, self.resp.clone()));
+            };
+            tracing::debug!("Response: {resp}");
+            if let Some(detail) = resp.get("detail") {
+                let message = detail["message"].as_str().map(|m| m.to_string());
+                let message = message.unwrap_or_else(|| detail.to_string());
+                return Err(format!("ElevenLabs returned an error: {message}").into());
+            }
+            // The response of `/with-timestamps`.
+            let audio = resp["audio_base64"]
+                .as_str()
+                .ok_or("no audio_base64 in response")?;
+            let mut speech = Speech::new(None, expected, Speech::decode_speech(audio)?);
+            let alignment = &resp["alignment"];
+            let characters = alignment["characters"].as_array();
+            let starts = alignment["character_start_times_seconds"].as_array();
+            let ends = alignment["character_end_times_seconds"].as_array();
+            if let (Some(characters), Some(starts), Some(ends)) = (characters, starts, ends) {
+                let characters: Vec<Timing> = characters
+                    .iter()
+                    .zip(starts)
+                    .zip(ends)
+                    .map(|((text, start), end)| Timing {
+                        text: text.as_str().unwrap_or_default().to_string(),
+                        start: start.as_f64().unwrap_or_default(),
+                        end: end.as_f64(),
+                    })
+                    .collect();
+                speech.words = Some(words_from_characters(&characters));
+                speech.characters = Some(characters);
+            }
+            Ok(speech)
         } else if self.provider == Provider::DeepInfra {
             let resp = self.raw_value()?;
             tracing::debug!("Response: {resp}");
             if resp.get("detail").is_some() {
                 return Err(format!("DeepInfra returned an error: {}", resp["detail"]).into());
             }
-            let audio = resp["audio"].as_str().expect("no audio in resp");
-            let output_format = resp["output_format"].as_str().unwrap().to_string();
-            let out = Speech {
-                request_id: Some(resp["request_id"].as_str().unwrap().to_string()),
-                file_format: output_format.to_string(),
-                audio: Speech::decode_speech(audio, &self.provider, Some(&output_format))?,
+            let audio = resp["audio"].as_str().ok_or("no audio in response")?;
+            let expected = match resp["output_format"].as_str() {
+                Some("mp3") => AudioFormat::Mp3,
+                Some("opus") => AudioFormat::Opus,
+                Some("flac") => AudioFormat::Flac,
+                Some("wav") => AudioFormat::Wav,
+                Some("aac") => AudioFormat::Aac,
+                // Kokoro returns raw PCM at 24 kHz.
+                Some("pcm") if !matches!(expected, AudioFormat::Pcm { .. }) => {
+                    AudioFormat::Pcm { sample_rate: 24000 }
+                }
+                _ => expected,
             };
-            Ok(out)
+            let request_id = resp["request_id"].as_str().map(|id| id.to_string());
+            let audio = Speech::decode_speech(audio)?;
+            Ok(Speech::new(request_id, expected, audio))
         } else if self.provider == Provider::Hyperbolic {
             let resp = self.raw_value()?;
             tracing::debug!("Response: {resp}");
-            let audio = &resp["audio"].as_str().unwrap();
-            let out = Speech {
-                request_id: None,
-                file_format: "mp3".to_string(),
-                audio: Speech::decode_speech(audio, &self.provider, None)?,
-            };
-            Ok(out)
-        } else if self.provider == Provider::OpenAI || is_openai_compatible(&self.provider) {
+            let audio = resp["audio"].as_str().ok_or("no audio in response")?;
+            let audio = Speech::decode_speech(audio)?;
+            Ok(Speech::new(None, expected, audio))
+        } else if is_openai_style(&self.provider) || self.provider == Provider::Azure {
             let audio = self.resp.clone();
             if let Ok(resp) = serde_json::from_slice::<Value>(&self.resp) {
                 tracing::debug!("Response: {resp}");
                 if resp.get("error").is_some() {
-                    return Err(resp["error"].to_string().into());
+                    return Err(crate::extract_error(&resp).into());
                 }
             }
-            let out = Speech {
-                request_id: None,
-                file_format: "mp3".to_string(),
-                audio,
-            };
-            Ok(out)
+            Ok(Speech::new(None, expected, audio))
         } else if self.provider == Provider::Google {
             let resp = self.raw_value()?;
             tracing::debug!("Response: {resp}");
             if resp.get("error").is_some() {
-                return Err(resp["error"].to_string().into());
+                return Err(crate::extract_error(&resp).into());
             }
-            let audio = &resp["audioContent"].as_str().expect("audioContent");
-            let _timepoints = &resp["timepoints"].as_array().unwrap();
-            let out = Speech {
-                request_id: None,
-                file_format: "mp3".to_string(),
-                audio: Speech::decode_speech(audio, &self.provider, None)?,
-            };
-            Ok(out)
+            let audio = resp["audioContent"]
+                .as_str()
+                .ok_or("no audioContent in response")?;
+            let audio = Speech::decode_speech(audio)?;
+            let mut speech = Speech::new(None, expected, audio);
+            let marks = resp["timepoints"].as_array().map(|timepoints| {
+                timepoints
+                    .iter()
+                    .map(|timepoint| Timing {
+                        text: timepoint["markName"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        start: timepoint["timeSeconds"].as_f64().unwrap_or_default(),
+                        end: None,
+                    })
+                    .collect::<Vec<_>>()
+            });
+            match (&self.words, marks) {
+                (Some(words), marks) => {
+                    let marks = marks.unwrap_or_default();
+                    speech.words = Some(google_words(words, &marks, speech.duration()));
+                }
+                (None, marks) => speech.marks = marks,
+            }
+            Ok(speech)
         } else {
-            panic!("Unsupported TTS provider: {}", self.provider);
+            Err(format!("Unsupported TTS provider: {}", self.provider).into())
         }
     }
 }
 
+#[test]
+fn test_structured_error() {
+    let response = |provider: Provider, body: Value| SpeechResponse {
+        provider,
+        output_format: None,
+        words: None,
+        resp: Bytes::from(body.to_string()),
+    };
+    let openai = json!({"error": {"message": "Invalid voice", "code": "invalid_value"}});
+    let err = response(Provider::OpenAI, openai).structured().unwrap_err();
+    assert_eq!(err.to_string(), "Invalid voice");
+    let google =
+        json!({"error": {"code": 400, "message": "Bad text", "status": "INVALID_ARGUMENT"}});
+    let err = response(Provider::Google, google).structured().unwrap_err();
+    assert_eq!(err.to_string(), "Bad text");
+}
+
 fn tts_headers(provider: &Provider, key: &Key) -> Result<HeaderMap, Box<dyn Error + Send + Sync>> {
     let headers = if provider == &Provider::Google {
-        let mut headers = request_headers(key)?;
-        headers.remove("Authorization");
-        headers
+        crate::key_header(key, "x-goog-api-key")?
     } else if provider == &Provider::ElevenLabs {
-        let mut headers = request_headers(key)?;
-        headers.insert("xi-api-key", HeaderValue::from_str(&key.key)?);
-        headers.remove("Authorization");
-        headers
+        crate::key_header(key, "xi-api-key")?
     } else {
         request_headers(key)?
     };
     Ok(headers)
 }
 
-fn tts_body(config: &TTSConfig, provider: &Provider, model: Option<&str>, text: &str) -> Value {
-    if provider == &Provider::ElevenLabs {
-        let mut body = json!({});
-        body["text"] = Value::String(text.to_string());
-        if let Some(model) = &model {
-            body["model_id"] = Value::String(model.to_string());
+/// The `audioConfig` for Google.
+fn google_audio_config(config: &TTSConfig) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let output_format = config.output_format.unwrap_or(AudioFormat::Wav);
+    let mut audio_config = json!({
+        "audioEncoding": format_param(&Provider::Google, output_format)?,
+    });
+    let sample_rate = match (output_format, config.sample_rate) {
+        (AudioFormat::Pcm { sample_rate }, Some(other)) if sample_rate != other => {
+            return Err(format!(
+                "sample rate {other} differs from the pcm sample rate {sample_rate}"
+            )
+            .into());
+        }
+        (AudioFormat::Pcm { sample_rate }, _) => Some(sample_rate),
+        (_, sample_rate) => sample_rate,
+    };
+    if let Some(sample_rate) = sample_rate {
+        audio_config["sampleRateHertz"] = Value::from(sample_rate);
+    }
+    if let Some(speed) = config.speed {
+        audio_config["speakingRate"] = Value::from(speed);
+    }
+    if let Some(pitch) = config.pitch {
+        audio_config["pitch"] = Value::from(pitch);
+    }
+    if let Some(volume_gain_db) = config.volume_gain_db {
+        audio_config["volumeGainDb"] = Value::from(volume_gain_db);
+    }
+    if let Some(effects_profile_id) = &config.effects_profile_id {
+        audio_config["effectsProfileId"] = json!(effects_profile_id);
+    }
+    Ok(audio_config)
+}
+
+fn elevenlabs_body(
+    config: &TTSConfig,
+    model: Option<&str>,
+    text: &str,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let mut body = json!({});
+    body["text"] = Value::String(text.to_string());
+    if let Some(model) = &model {
+        body["model_id"] = Value::String(model.to_string());
+    }
+    if let Some(language_code) = &config.language_code {
+        body["language_code"] = Value::String(language_code.clone());
+    }
+    if let Some(seed) = config.seed {
+        body["seed"] = Value::from(seed);
+    }
+    let mut settings = config.voice_settings.clone().unwrap_or_default();
+    if let Some(speed) = config.speed {
+        if settings.speed.is_some_and(|other| other != speed) {
+            return Err("speed and voice_settings.speed are both set".into());
+        }
+        settings.speed = Some(speed);
+    }
+    if settings != VoiceSettings::default() {
+        let mut voice_settings = json!({});
+        let numbers = [
+            ("stability", settings.stability),
+            ("similarity_boost", settings.similarity_boost),
+            ("style", settings.style),
+            ("speed", settings.speed),
+        ];
+        for (name, value) in numbers {
+            if let Some(value) = value {
+                voice_settings[name] = Value::from(value);
+            }
         }
-        if let Some(language_code) = &config.language_code {
-            body["language_code"] = Value::String(language_code.clone());
+        if let Some(use_speaker_boost) = settings.use_speaker_boost {
+            voice_settings["use_speaker_boost"] = Value::Bool(use_speaker_boost);
         }
-        if let Some(_speed) = &config.speed {
-            panic!("Set speed for ElevenLabs via stored settings for voice.");
+        body["voice_settings"] = voice_settings;
+    }
+    if let Some(previous_text) = &config.previous_text {
+        body["previous_text"] = Value::String(previous_text.clone());
+    }
+    if let Some(next_text) = &config.next_text {
+        body["next_text"] = Value::String(next_text.clone());
+    }
+    if let Some(locators) = &config.pronunciation_dictionary_locators {
+        if locators.len() > 3 {
+            return Err("ElevenLabs supports at most 3 pronunciation dictionaries".into());
         }
-        if let Some(seed) = &config.seed {
-            body["seed"] = Value::String(seed.to_string());
+        let locators: Vec<Value> = locators
+            .iter()
+            .map(|locator| {
+                let mut value = json!({
+                    "pronunciation_dictionary_id": locator.pronunciation_dictionary_id,
+                });
+                if let Some(version_id) = &locator.version_id {
+                    value["version_id"] = Value::String(version_id.clone());
+                }
+                value
+            })
+            .collect();
+        body["pronunciation_dictionary_locators"] = Value::Array(locators);
+    }
+    if let Some(other) = &config.other {
+        for (key, value) in other {
+            body[key] = value.clone();
         }
-        return body;
     }
+    Ok(body)
+}
+
+#[test]
+fn test_elevenlabs_body() {
+    let config = TTSConfig {
+        speed: Some(1.1),
+        seed: Some(42),
+        voice_settings: Some(VoiceSettings {
+            stability: Some(0.5),
+            use_speaker_boost: Some(true),
+            ..Default::default()
+        }),
+        previous_text: Some("Before.".to_string()),
+        pronunciation_dictionary_locators: Some(vec![PronunciationDictionaryLocator {
+            pronunciation_dictionary_id: "dict".to_string(),
+            version_id: None,
+        }]),
+        ..Default::default()
+    };
+    let body = tts_body(
+        &config,
+        &Provider::ElevenLabs,
+        Some("eleven_multilingual_v2"),
+        "Hi",
+    )
+    .unwrap();
+    let expected = json!({
+        "text": "Hi",
+        "model_id": "eleven_multilingual_v2",
+        "seed": 42,
+        "voice_settings": {"stability": 0.5, "speed": 1.1, "use_speaker_boost": true},
+        "previous_text": "Before.",
+        "pronunciation_dictionary_locators": [{"pronunciation_dictionary_id": "dict"}],
+    });
+    assert_eq!(body, expected);
+    let body = tts_body(&TTSConfig::default(), &Provider::ElevenLabs, None, "Hi").unwrap();
+    assert_eq!(body, json!({"text": "Hi"}));
+
+    let mut conflicting = config.clone();
+    conflicting.voice_settings.as_mut().unwrap().speed = Some(0.9);
+    assert!(tts_body(&conflicting, &Provider::ElevenLabs, None, "Hi").is_err());
+    let err = tts_body(&config, &Provider::OpenAI, None, "Hi").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "OpenAI does not support the text-to-speech options: voice_settings, previous_text, \
+         pronunciation_dictionary_locators"
+    );
+}
+
+fn tts_body(
+    config: &TTSConfig,
+    provider: &Provider,
+    model: Option<&str>,
+    text: &str,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    if provider != &Provider::Google {
+        let timestamps = config.timestamps && provider != &Provider::ElevenLabs;
+        crate::reject(
+            provider,
+            "text-to-speech",
+            &[
+                ("ssml", config.ssml),
+                ("pitch", config.pitch.is_some()),
+                ("volume_gain_db", config.volume_gain_db.is_some()),
+                ("sample_rate", config.sample_rate.is_some()),
+                ("effects_profile_id", config.effects_profile_id.is_some()),
+                ("enable_time_pointing", config.enable_time_pointing),
+                ("timestamps", timestamps),
+            ],
+        )?;
+    }
+    if provider != &Provider::ElevenLabs {
+        crate::reject(
+            provider,
+            "text-to-speech",
+            &[
+                ("voice_settings", config.voice_settings.is_some()),
+                ("previous_text", config.previous_text.is_some()),
+                ("next_text", config.next_text.is_some()),
+                (
+                    "pronunciation_dictionary_locators",
+                    config.pronunciation_dictionary_locators.is_some(),
+                ),
+            ],
+        )?;
+    }
+    crate::reject(
+        provider,
+        "text-to-speech",
+        &[("region", config.region.is_some())],
+    )?;
+    if provider == &Provider::ElevenLabs {
+        return elevenlabs_body(config, model, text);
+    }
+    let model = match provider {
+        Provider::Groq => Some(model.unwrap_or("playai-tts")),
+        Provider::TogetherAI => Some(model.unwrap_or("cartesia/sonic")),
+        _ => model,
+    };
     let mut body = json!({});
-    if provider == &Provider::OpenAI || is_openai_compatible(provider) {
+    if is_openai_style(provider) {
         body["input"] = Value::String(text.to_string());
     } else if provider == &Provider::Google {
-        body["input"] = json!({
-            "text": text.to_string()
-        });
+        body["input"] = if config.timestamps {
+            if config.ssml {
+                return Err("Google word timestamps require plain text instead of SSML".into());
+            }
+            json!({ "ssml": google_word_ssml(text) })
+        } else if config.ssml {
+            json!({ "ssml": text })
+        } else {
+            json!({ "text": text })
+        };
     } else {
         body["text"] = Value::String(text.to_string());
     }
@@ -272,7 +927,7 @@ fn tts_body(config: &TTSConfig, provider: &Provider, model: Option<&str>, text:
         body["model"] = Value::String(model.to_string());
     }
     if let Some(voice) = &config.voice {
-        if provider == &Provider::OpenAI || is_openai_compatible(provider) {
+        if is_openai_style(provider) {
             body["voice"] = Value::String(voice.clone());
         } else if provider == &Provider::Google {
             body["voice"] = json!({
@@ -281,29 +936,184 @@ fn tts_body(config: &TTSConfig, provider: &Provider, model: Option<&str>, text:
             if let Some(language_code) = &config.language_code {
                 body["voice"]["languageCode"] = Value::String(language_code.clone());
             }
-            body["audioConfig"] = json!({
-                "audioEncoding": "LINEAR16",
-                "pitch": 0,
-                "speakingRate": 1
-            });
         } else if provider == &Provider::DeepInfra {
             body["preset_voice"] = Value::String(voice.clone());
+        } else if provider == &Provider::Hyperbolic {
+            // The speaker, such as `EN-US`, starts with the language.
+            let language = voice.split('-').next().unwrap_or(voice);
+            body["language"] = Value::String(language.to_string());
+            body["speaker"] = Value::String(voice.clone());
         } else {
-            panic!("Unsupported TTS provider: {}", provider);
+            return Err(format!("Unsupported TTS provider: {provider}").into());
         }
     }
-    if let Some(speed) = config.speed {
-        body["speed"] = Value::from(speed);
-    }
-    if let Some(output_format) = &config.output_format {
-        body["output_format"] = Value::String(output_format.clone());
+    if provider == &Provider::Google {
+        body["audioConfig"] = google_audio_config(config)?;
+        if config.enable_time_pointing || config.timestamps {
+            body["enableTimePointing"] = json!(["SSML_MARK"]);
+        }
+    } else {
+        if let Some(speed) = config.speed {
+            body["speed"] = Value::from(speed);
+        }
+        let output_format = match provider {
+            // Their default is WAV, but request it anyway so that the format of
+            // the response does not depend on a changing default.
+            Provider::Groq | Provider::TogetherAI => {
+                Some(config.output_format.unwrap_or(default_format(provider)))
+            }
+            _ => config.output_format,
+        };
+        if let Some(output_format) = output_format {
+            let param = format_param(provider, output_format)?;
+            if is_openai_style(provider) {
+                body["response_format"] = Value::String(param);
+            } else if provider == &Provider::DeepInfra {
+                body["output_format"] = Value::String(param);
+            }
+        }
     }
     if let Some(other) = &config.other {
         for (key, value) in other {
             body[key] = value.clone();
         }
     }
-    body
+    Ok(body)
+}
+
+#[test]
+fn test_google_body() {
+    let config = TTSConfig {
+        voice: Some("en-US-Studio-Q".to_string()),
+        language_code: Some("en-US".to_string()),
+        speed: Some(1.25),
+        pitch: Some(-2.0),
+        volume_gain_db: Some(3.0),
+        effects_profile_id: Some(vec!["headphone-class-device".to_string()]),
+        ssml: true,
+        enable_time_pointing: true,
+        output_format: Some(AudioFormat::Pcm { sample_rate: 16000 }),
+        ..Default::default()
+    };
+    let text = r#"<speak>Hi <break time="1s"/> <mark name="a"/>there</speak>"#;
+    let body = tts_body(&config, &Provider::Google, None, text).unwrap();
+    let expected = json!({
+        "input": {"ssml": text},
+        "voice": {"name": "en-US-Studio-Q", "languageCode": "en-US"},
+        "audioConfig": {
+            "audioEncoding": "PCM",
+            "sampleRateHertz": 16000,
+            "speakingRate": 1.25,
+            "pitch": -2.0,
+            "volumeGainDb": 3.0,
+            "effectsProfileId": ["headphone-class-device"],
+        },
+        "enableTimePointing": ["SSML_MARK"],
+    });
+    assert_eq!(body, expected);
+
+    let config = TTSConfig {
+        sample_rate: Some(8000),
+        ..config
+    };
+    assert!(tts_body(&config, &Provider::Google, None, text).is_err());
+    let err = tts_body(&config, &Provider::OpenAI, None, text).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "OpenAI does not support the text-to-speech options: ssml, pitch, volume_gain_db, \
+         sample_rate, effects_profile_id, enable_time_pointing"
+    );
+}
+
+/// The SSML document for Azure.
+fn azure_ssml(config: &TTSConfig, text: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    crate::reject(
+        &Provider::Azure,
+        "text-to-speech",
+        &[
+            ("pitch", config.pitch.is_some()),
+            ("volume_gain_db", config.volume_gain_db.is_some()),
+            ("sample_rate", config.sample_rate.is_some()),
+            ("effects_profile_id", config.effects_profile_id.is_some()),
+            ("enable_time_pointing", config.enable_time_pointing),
+            ("timestamps", config.timestamps),
+            ("voice_settings", config.voice_settings.is_some()),
+            ("previous_text", config.previous_text.is_some()),
+            ("next_text", config.next_text.is_some()),
+            (
+                "pronunciation_dictionary_locators",
+                config.pronunciation_dictionary_locators.is_some(),
+            ),
+            ("other", config.other.is_some()),
+        ],
+    )?;
+    if config.ssml {
+        if config.speed.is_some() {
+            return Err("speed cannot be combined with SSML for Azure".into());
+        }
+        return Ok(text.to_string());
+    }
+    let voice = config.voice.as_ref().ok_or("voice is required for Azure")?;
+    // Voices such as `en-US-AvaMultilingualNeural` start with the language.
+    let language_code = match &config.language_code {
+        Some(language_code) => language_code.clone(),
+        None => voice.split('-').take(2).collect::<Vec<_>>().join("-"),
+    };
+    let mut content = escape_xml(text);
+    if let Some(speed) = config.speed {
+        content = format!("<prosody rate=\"{speed}\">{content}</prosody>");
+    }
+    Ok(format!(
+        "<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\" \
+         xml:lang=\"{}\"><voice name=\"{}\">{content}</voice></speak>",
+        escape_xml(&language_code),
+        escape_xml(voice)
+    ))
+}
+
+#[test]
+fn test_azure_ssml() {
+    let config = TTSConfig {
+        voice: Some("en-US-AvaMultilingualNeural".to_string()),
+        speed: Some(1.2),
+        ..Default::default()
+    };
+    assert_eq!(
+        azure_ssml(&config, "Tom & Jerry").unwrap(),
+        "<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\" \
+         xml:lang=\"en-US\"><voice name=\"en-US-AvaMultilingualNeural\">\
+         <prosody rate=\"1.2\">Tom &amp; Jerry</prosody></voice></speak>"
+    );
+    let ssml = TTSConfig {
+        ssml: true,
+        ..Default::default()
+    };
+    assert_eq!(azure_ssml(&ssml, "<speak/>").unwrap(), "<speak/>");
+    let err = azure_ssml(&TTSConfig::default(), "Hi").unwrap_err();
+    assert_eq!(err.to_string(), "voice is required for Azure");
+    let injected = TTSConfig {
+        voice: Some("en-US-AvaMultilingualNeural".to_string()),
+        language_code: Some("en\"><x/>".to_string()),
+        ..Default::default()
+    };
+    let ssml = azure_ssml(&injected, "Hi").unwrap();
+    assert!(ssml.contains("xml:lang=\"en&quot;&gt;&lt;x/&gt;\""));
+}
+
+fn azure_headers(key: &Key, config: &TTSConfig) -> Result<HeaderMap, Box<dyn Error + Send + Sync>> {
+    let mut headers = crate::key_header(key, "Ocp-Apim-Subscription-Key")?;
+    let output_format = config.output_format.unwrap_or(AudioFormat::Mp3);
+    let output_format = format_param(&Provider::Azure, output_format)?;
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/ssml+xml"),
+    );
+    headers.insert(
+        "X-Microsoft-OutputFormat",
+        HeaderValue::from_str(&output_format)?,
+    );
+    headers.insert(USER_AGENT, HeaderValue::from_static("transformrs"));
+    Ok(headers)
 }
 
 pub async fn tts(
@@ -313,20 +1123,659 @@ pub async fn tts(
     model: Option<&str>,
     text: &str,
 ) -> Result<SpeechResponse, Box<dyn Error + Send + Sync>> {
-    let address = address(provider, key, model, config);
-    let headers = tts_headers(provider, key)?;
-    let body = tts_body(config, provider, model, text);
-    tracing::debug!("Requesting {address} for text-to-speech with {body}");
+    let address = address(provider, key, model, config, false)?;
     let client = reqwest::Client::new();
-    let resp = client
-        .post(address)
-        .headers(headers)
-        .json(&body)
-        .send()
-        .await?;
+    let req = if provider == &Provider::Azure {
+        let headers = azure_headers(key, config)?;
+        client
+            .post(address)
+            .headers(headers)
+            .body(azure_ssml(config, text)?)
+    } else {
+        let headers = tts_headers(provider, key)?;
+        let body = tts_body(config, provider, model, text)?;
+        client.post(address).headers(headers).json(&body)
+    };
+    let resp = crate::http::send(req).await?;
+    // Azure reports errors with the status and an often empty body.
+    if provider == &Provider::Azure && !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.bytes().await?;
+        let message = String::from_utf8_lossy(&body);
+        return Err(format!("TTS request failed with status {status}: {message}").into());
+    }
+    let words = if provider == &Provider::Google && config.timestamps {
+        Some(
+            text.split_whitespace()
+                .map(|word| word.to_string())
+                .collect(),
+        )
+    } else {
+        None
+    };
     let speech_response = SpeechResponse {
         provider: provider.clone(),
+        output_format: config.output_format,
+        words,
         resp: resp.bytes().await?,
     };
     Ok(speech_response)
 }
+
+/// A stream of audio chunks.
+pub type SpeechStream =
+    Pin<Box<dyn Stream<Item = Result<Bytes, Box<dyn Error + Send + Sync>>> + Send>>;
+
+fn error_message(body: &[u8]) -> String {
+    match serde_json::from_slice::<Value>(body) {
+        // ElevenLabs reports errors as `{"detail": {"message": ...}}`.
+        Ok(value) => match value["detail"]["message"].as_str() {
+            Some(message) => message.to_string(),
+            None => crate::extract_error(&value),
+        },
+        Err(_) => String::from_utf8_lossy(body).to_string(),
+    }
+}
+
+fn stream_error(status: reqwest::StatusCode, body: &[u8]) -> String {
+    let message = error_message(body);
+    format!("TTS request failed with status {status}: {message}")
+}
+
+/// Text-to-speech where the audio is returned in chunks as soon as the
+/// provider has synthesized them.
+///
+/// This allows playback to start before the synthesis of the whole text has
+/// finished. Supported by OpenAI, OpenAI-compatible providers, and
+/// ElevenLabs. The chunks are not aligned to audio frames, so concatenate them
+/// or feed them to a decoder that accepts partial input.
+pub async fn tts_stream(
+    provider: &Provider,
+    key: &Key,
+    config: &TTSConfig,
+    model: Option<&str>,
+    text: &str,
+) -> Result<SpeechStream, Box<dyn Error + Send + Sync>> {
+    match provider {
+        Provider::ElevenLabs | Provider::OpenAI | Provider::OpenAICompatible(_) => {}
+        _ => return Err(format!("Unsupported TTS streaming provider: {provider}").into()),
+    }
+    if config.timestamps {
+        return Err("timestamps are not supported when streaming".into());
+    }
+    let address = address(provider, key, model, config, true)?;
+    let headers = tts_headers(provider, key)?;
+    let body = tts_body(config, provider, model, text)?;
+    let client = reqwest::Client::new();
+    let req = client.post(address).headers(headers).json(&body);
+    let resp = crate::http::send(req).await?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.bytes().await?;
+        return Err(stream_error(status, &body).into());
+    }
+    let stream = stream! {
+        let mut byte_stream = resp.bytes_stream();
+        while let Some(chunk) = byte_stream.next().await {
+            yield chunk.map_err(|e| e.into());
+        }
+    };
+    Ok(Box::pin(stream))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoiceGender {
+    Female,
+    Male,
+    Neutral,
+}
+
+impl VoiceGender {
+    fn parse(gender: &str) -> Option<VoiceGender> {
+        match gender.to_lowercase().as_str() {
+            "female" => Some(VoiceGender::Female),
+            "male" => Some(VoiceGender::Male),
+            "neutral" | "non-binary" => Some(VoiceGender::Neutral),
+            _ => None,
+        }
+    }
+}
+
+/// A voice that can be used as [TTSConfig::voice].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Voice {
+    pub id: String,
+    pub name: String,
+    /// Language codes such as `en-US` or `en`.
+    ///
+    /// Empty when the voice is not tied to a language.
+    pub languages: Vec<String>,
+    pub gender: Option<VoiceGender>,
+    /// URL of an audio sample of the voice.
+    pub preview_url: Option<String>,
+}
+
+impl Voice {
+    /// Whether the voice speaks `language_code`.
+    ///
+    /// A language matches its regional variants, so `en` matches `en-US` and
+    /// the other way around.
+    pub fn speaks(&self, language_code: &str) -> bool {
+        let matches = |language: &str| {
+            let (a, b) = (language.to_lowercase(), language_code.to_lowercase());
+            a == b || a.starts_with(&format!("{b}-")) || b.starts_with(&format!("{a}-"))
+        };
+        self.languages.is_empty() || self.languages.iter().any(|language| matches(language))
+    }
+}
+
+fn static_voice(id: &str, languages: &[&str], gender: Option<VoiceGender>) -> Voice {
+    let mut name = id.to_string();
+    if let Some(first) = name.get_mut(0..1) {
+        first.make_ascii_uppercase();
+    }
+    Voice {
+        id: id.to_string(),
+        name,
+        languages: languages
+            .iter()
+            .map(|language| language.to_string())
+            .collect(),
+        gender,
+        preview_url: None,
+    }
+}
+
+/// The OpenAI voices, which speak all supported languages.
+fn openai_voices() -> Vec<Voice> {
+    let ids = [
+        "alloy", "ash", "ballad", "coral", "echo", "fable", "nova", "onyx", "sage", "shimmer",
+        "verse",
+    ];
+    ids.iter().map(|id| static_voice(id, &[], None)).collect()
+}
+
+/// The Kokoro voices on DeepInfra.
+///
+/// The first letter of the id is the language and the second the gender, so
+/// `bf_emma` is a British English female voice.
+fn kokoro_voices() -> Vec<Voice> {
+    let ids = [
+        "af_alloy",
+        "af_aoede",
+        "af_bella",
+        "af_heart",
+        "af_jessica",
+        "af_kore",
+        "af_nicole",
+        "af_nova",
+        "af_river",
+        "af_sarah",
+        "af_sky",
+        "am_adam",
+        "am_echo",
+        "am_eric",
+        "am_fenrir",
+        "am_liam",
+        "am_michael",
+        "am_onyx",
+        "am_puck",
+        "am_santa",
+        "bf_alice",
+        "bf_emma",
+        "bf_isabella",
+        "bf_lily",
+        "bm_daniel",
+        "bm_fable",
+        "bm_george",
+        "bm_lewis",
+        "jf_alpha",
+        "jf_gongitsune",
+        "jf_nezumi",
+        "jf_tebukuro",
+        "jm_kumo",
+        "zf_xiaobei",
+        "zf_xiaoni",
+        "zf_xiaoxiao",
+        "zf_xiaoyi",
+        "zm_yunjian",
+        "zm_yunxi",
+        "zm_yunxia",
+        "zm_yunyang",
+        "ef_dora",
+        "em_alex",
+        "em_santa",
+        "ff_siwis",
+        "hf_alpha",
+        "hf_beta",
+        "hm_omega",
+        "hm_psi",
+        "if_sara",
+        "im_nicola",
+        "pf_dora",
+        "pm_alex",
+        "pm_santa",
+    ];
+    ids.iter()
+        .map(|id| {
+            let language = match &id[0..1] {
+                "a" => "en-US",
+                "b" => "en-GB",
+                "j" => "ja",
+                "z" => "zh",
+                "e" => "es",
+                "f" => "fr",
+                "h" => "hi",
+                "i" => "it",
+                _ => "pt-BR",
+            };
+            let gender = match &id[1..2] {
+                "f" => VoiceGender::Female,
+                _ => VoiceGender::Male,
+            };
+            let mut voice = static_voice(id, &[language], Some(gender));
+            voice.name = static_voice(&id[3..], &[], None).name;
+            voice
+        })
+        .collect()
+}
+
+/// The MeloTTS speakers on Hyperbolic.
+fn hyperbolic_voices() -> Vec<Voice> {
+    let speakers = [
+        ("EN-US", "en-US"),
+        ("EN-BR", "en-GB"),
+        ("EN-INDIA", "en-IN"),
+        ("EN-AU", "en-AU"),
+        ("EN-Default", "en"),
+        ("ES", "es"),
+        ("FR", "fr"),
+        ("ZH", "zh"),
+        ("JP", "ja"),
+        ("KR", "ko"),
+    ];
+    speakers
+        .iter()
+        .map(|(id, language)| static_voice(id, &[language], None))
+        .collect()
+}
+
+fn elevenlabs_voices(value: &Value) -> Vec<Voice> {
+    let voices = value["voices"].as_array().cloned().unwrap_or_default();
+    voices
+        .iter()
+        .map(|voice| {
+            let labels = &voice["labels"];
+            let mut languages: Vec<String> = vec![];
+            let verified = voice["verified_languages"].as_array().cloned();
+            for language in verified.unwrap_or_default() {
+                let code = language["locale"]
+                    .as_str()
+                    .or(language["language"].as_str());
+                if let Some(code) = code {
+                    if !languages.iter().any(|language| language == code) {
+                        languages.push(code.to_string());
+                    }
+                }
+            }
+            if languages.is_empty() {
+                if let Some(language) = labels["language"].as_str() {
+                    languages.push(language.to_string());
+                }
+            }
+            Voice {
+                id: voice["voice_id"].as_str().unwrap_or_default().to_string(),
+                name: voice["name"].as_str().unwrap_or_default().to_string(),
+                languages,
+                gender: labels["gender"].as_str().and_then(VoiceGender::parse),
+                preview_url: voice["preview_url"].as_str().map(|url| url.to_string()),
+            }
+        })
+        .collect()
+}
+
+fn google_voices(value: &Value) -> Vec<Voice> {
+    let voices = value["voices"].as_array().cloned().unwrap_or_default();
+    voices
+        .iter()
+        .map(|voice| {
+            let name = voice["name"].as_str().unwrap_or_default().to_string();
+            Voice {
+                id: name.clone(),
+                name,
+                languages: voice["languageCodes"]
+                    .as_array()
+                    .map(|codes| {
+                        codes
+                            .iter()
+                            .filter_map(|code| code.as_str().map(|code| code.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                gender: voice["ssmlGender"].as_str().and_then(VoiceGender::parse),
+                preview_url: None,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_voices() {
+    let value = json!({"voices": [{
+        "voice_id": "nPczCjzI2devNBz1zQrb",
+        "name": "Brian",
+        "labels": {"gender": "male", "accent": "american"},
+        "preview_url": "https://example.com/brian.mp3",
+        "verified_languages": [
+            {"language": "en", "locale": "en-US"},
+            {"language": "nl", "locale": null},
+        ],
+    }]});
+    let voices = elevenlabs_voices(&value);
+    assert_eq!(voices[0].languages, vec!["en-US", "nl"]);
+    assert_eq!(voices[0].gender, Some(VoiceGender::Male));
+    assert!(voices[0].speaks("en"));
+    assert!(voices[0].speaks("nl-BE"));
+    assert!(!voices[0].speaks("de"));
+
+    let value = json!({"voices": [{
+        "languageCodes": ["en-US"],
+        "name": "en-US-Studio-Q",
+        "ssmlGender": "MALE",
+        "naturalSampleRateHertz": 24000,
+    }]});
+    let voices = google_voices(&value);
+    assert_eq!(voices[0].id, "en-US-Studio-Q");
+    assert_eq!(voices[0].gender, Some(VoiceGender::Male));
+
+    let voices = kokoro_voices();
+    let emma = voices.iter().find(|voice| voice.id == "bf_emma").unwrap();
+    assert_eq!(emma.name, "Emma");
+    assert_eq!(emma.languages, vec!["en-GB"]);
+    assert_eq!(emma.gender, Some(VoiceGender::Female));
+    assert!(openai_voices()[0].speaks("ja"));
+}
+
+/// List the voices of `provider`.
+///
+/// ElevenLabs and Google are queried for the voices that are available to
+/// `key`. For OpenAI, the Kokoro model on DeepInfra, and Hyperbolic, the list
+/// is built in. With `language_code`, only voices that speak the language are
+/// returned.
+pub async fn voices(
+    provider: &Provider,
+    key: &Key,
+    language_code: Option<&str>,
+) -> Result<Vec<Voice>, Box<dyn Error + Send + Sync>> {
+    let voices = match provider {
+        Provider::OpenAI => openai_voices(),
+        Provider::DeepInfra => kokoro_voices(),
+        Provider::Hyperbolic => hyperbolic_voices(),
+        Provider::ElevenLabs | Provider::Google => {
+            let domain = if provider == &Provider::Google {
+                google_tts_domain(key)
+            } else {
+                crate::domain(provider, key)
+            };
+            let client = reqwest::Client::new();
+            let mut req = client
+                .get(format!("{domain}/v1/voices"))
+                .headers(tts_headers(provider, key)?);
+            if let (Provider::Google, Some(language_code)) = (provider, language_code) {
+                req = req.query(&[("languageCode", language_code)]);
+            }
+            let resp = crate::http::send(req).await?;
+            let status = resp.status();
+            let body = resp.bytes().await?;
+            if !status.is_success() {
+                let message = error_message(&body);
+                return Err(
+                    format!("voices request failed with status {status}: {message}").into(),
+                );
+            }
+            let value: Value = serde_json::from_slice(&body)?;
+            if provider == &Provider::Google {
+                google_voices(&value)
+            } else {
+                elevenlabs_voices(&value)
+            }
+        }
+        provider => return Err(format!("Unsupported voices provider: {provider}").into()),
+    };
+    Ok(match language_code {
+        Some(language_code) => voices
+            .into_iter()
+            .filter(|voice| voice.speaks(language_code))
+            .collect(),
+        None => voices,
+    })
+}
+
+/// The maximum length of the text in one text-to-speech request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextLimit {
+    /// The maximum number of characters.
+    Chars(usize),
+    /// The maximum number of UTF-8 bytes.
+    Bytes(usize),
+}
+
+impl TextLimit {
+    /// The limit of the provider.
+    pub fn for_provider(provider: &Provider) -> TextLimit {
+        match provider {
+            Provider::Google => TextLimit::Bytes(5000),
+            Provider::ElevenLabs => TextLimit::Chars(5000),
+            // OpenAI's limit; used for the others since they do not document one.
+            _ => TextLimit::Chars(4096),
+        }
+    }
+    fn fits(&self, text: &str) -> bool {
+        match self {
+            TextLimit::Chars(max) => text.chars().count() <= *max,
+            TextLimit::Bytes(max) => text.len() <= *max,
+        }
+    }
+}
+
+/// Split the text into sentences, keeping the punctuation.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = vec![];
+    let mut start = 0;
+    let mut prev: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() && matches!(prev, Some('.' | '!' | '?' | '…')) {
+            sentences.push(text[start..i].trim());
+            start = i;
+        }
+        prev = Some(c);
+    }
+    sentences.push(text[start..].trim());
+    sentences.retain(|sentence| !sentence.is_empty());
+    sentences
+}
+
+fn split_level(text: &str, level: usize, limit: TextLimit, chunks: &mut Vec<String>) {
+    let (units, separator): (Vec<&str>, &str) = match level {
+        0 => (text.split("\n\n").map(str::trim).collect(), "\n\n"),
+        1 => (sentences(text), " "),
+        2 => (text.split_whitespace().collect(), " "),
+        _ => {
+            // A single word that is longer than the limit.
+            let mut current = String::new();
+            for c in text.chars() {
+                current.push(c);
+                if !limit.fits(&current) {
+                    current.pop();
+                    chunks.push(std::mem::take(&mut current));
+                    current.push(c);
+                }
+            }
+            if !current.is_empty() {
+                chunks.push(current);
+            }
+            return;
+        }
+    };
+    let mut current = String::new();
+    for unit in units.into_iter().filter(|unit| !unit.is_empty()) {
+        let candidate = if current.is_empty() {
+            unit.to_string()
+        } else {
+            format!("{current}{separator}{unit}")
+        };
+        if limit.fits(&candidate) {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if limit.fits(unit) {
+            current = unit.to_string();
+        } else {
+            split_level(unit, level + 1, limit, chunks);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+}
+
+/// Split the text into chunks that are within the limit.
+///
+/// The text is split at paragraph boundaries first, then at sentence
+/// boundaries, and then at spaces. Paragraphs and sentences are combined as
+/// long as the chunk stays within the limit.
+pub fn split_text(text: &str, limit: TextLimit) -> Vec<String> {
+    let mut chunks = vec![];
+    split_level(text, 0, limit, &mut chunks);
+    chunks
+}
+
+#[test]
+fn test_split_text() {
+    let text = "First sentence. Second one!\n\nNew paragraph? Yes.";
+    assert_eq!(split_text(text, TextLimit::Chars(100)), vec![text]);
+    assert_eq!(
+        split_text(text, TextLimit::Chars(30)),
+        vec!["First sentence. Second one!", "New paragraph? Yes."]
+    );
+    assert_eq!(
+        split_text(text, TextLimit::Chars(20)),
+        vec!["First sentence.", "Second one!", "New paragraph? Yes."]
+    );
+    assert_eq!(
+        split_text("Aaaa bbbb cccc", TextLimit::Chars(9)),
+        vec!["Aaaa bbbb", "cccc"]
+    );
+    assert_eq!(
+        split_text("abcdefg", TextLimit::Chars(3)),
+        vec!["abc", "def", "g"]
+    );
+    // Each `é` is two bytes.
+    assert_eq!(split_text("éééé", TextLimit::Bytes(4)), vec!["éé", "éé"]);
+    assert_eq!(split_text("éééé", TextLimit::Chars(4)), vec!["éééé"]);
+    assert_eq!(
+        split_text("  \n\n ", TextLimit::Chars(4)),
+        Vec::<String>::new()
+    );
+}
+
+/// Text-to-speech for texts of any length.
+///
+/// The text is split with [split_text] at the limit of the provider and the
+/// chunks are synthesized with at most `concurrency` requests at the same
+/// time. The audio of the chunks is joined in order with [crate::audio::join].
+///
+/// For ElevenLabs, the neighbouring chunks are sent as `previous_text` and
+/// `next_text` so that the intonation flows across the chunks.
+///
+/// SSML input is rejected since splitting it would break the markup.
+pub async fn tts_long(
+    provider: &Provider,
+    key: &Key,
+    config: &TTSConfig,
+    model: Option<&str>,
+    text: &str,
+    concurrency: usize,
+) -> Result<Speech, Box<dyn Error + Send + Sync>> {
+    if config.ssml {
+        return Err("tts_long does not support SSML; call tts for each SSML document".into());
+    }
+    let limit = match TextLimit::for_provider(provider) {
+        // Leave room for the marks that are added before each word.
+        TextLimit::Bytes(max) if provider == &Provider::Google && config.timestamps => {
+            TextLimit::Bytes(max / 4)
+        }
+        limit => limit,
+    };
+    let chunks = split_text(text, limit);
+    if chunks.is_empty() {
+        return Err("text is empty".into());
+    }
+    tracing::debug!("Synthesizing {} chunks", chunks.len());
+    let configs = chunks.iter().enumerate().map(|(i, chunk)| {
+        let mut config = config.clone();
+        if provider == &Provider::ElevenLabs {
+            if i > 0 {
+                config.previous_text = Some(chunks[i - 1].clone());
+            }
+            if i + 1 < chunks.len() {
+                config.next_text = Some(chunks[i + 1].clone());
+            }
+        }
+        (config, chunk)
+    });
+    let speeches = futures::stream::iter(configs)
+        .map(|(config, chunk)| async move {
+            let resp = tts(provider, key, &config, model, chunk).await?;
+            resp.structured()
+        })
+        .buffered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    let format = speeches[0].format;
+    if let Some(speech) = speeches.iter().find(|speech| speech.format != format) {
+        return Err(format!("received both {format} and {} audio", speech.format).into());
+    }
+    let audio: Vec<Bytes> = speeches.iter().map(|speech| speech.audio.clone()).collect();
+    let audio = crate::audio::join(format, &audio)?;
+    let mut joined = Speech::new(None, format, audio);
+    if speeches
+        .iter()
+        .any(|speech| speech.words.is_some() || speech.marks.is_some())
+    {
+        // The timings of each chunk start at zero.
+        let shifted = |timings: &Option<Vec<Timing>>, offset: f64| -> Vec<Timing> {
+            let timings = timings.iter().flatten();
+            timings.map(|timing| timing.clone().shift(offset)).collect()
+        };
+        let mut offset = Some(0.0);
+        let mut words = vec![];
+        let mut characters = vec![];
+        let mut marks = vec![];
+        for (i, speech) in speeches.iter().enumerate() {
+            let Some(start) = offset else {
+                break;
+            };
+            words.extend(shifted(&speech.words, start));
+            characters.extend(shifted(&speech.characters, start));
+            marks.extend(shifted(&speech.marks, start));
+            if i + 1 < speeches.len() {
+                offset = speech.duration().map(|duration| start + duration);
+            }
+        }
+        if offset.is_some() {
+            joined.words = speeches[0].words.as_ref().map(|_| words);
+            joined.characters = speeches[0].characters.as_ref().map(|_| characters);
+            joined.marks = speeches[0].marks.as_ref().map(|_| marks);
+        } else {
+            tracing::warn!("Dropping the timings since the duration of {format} audio is unknown");
+        }
+    }
+    Ok(joined)
+}
@@ -0,0 +1,149 @@
+extern crate transformrs;
+
+mod common;
+
+use futures_util::stream::StreamExt;
+use serde_json::json;
+use transformrs::cassette;
+use transformrs::cassette::Body;
+use transformrs::cassette::Cassette;
+use transformrs::cassette::Interaction;
+use transformrs::cassette::Mode;
+use transformrs::cassette::RecordedRequest;
+use transformrs::cassette::RecordedResponse;
+use transformrs::chat;
+use transformrs::mock_server::MockServer;
+use transformrs::Key;
+use transformrs::Message;
+use transformrs::Provider;
+
+const MODEL: &str = "meta-llama/Llama-3.3-70B-Instruct";
+
+fn request(stream: bool) -> RecordedRequest {
+    RecordedRequest {
+        method: "POST".to_string(),
+        url: "https://api.deepinfra.com/v1/openai/chat/completions".to_string(),
+        body: Some(Body::Json(json!({
+            "model": MODEL,
+            "messages": [{"role": "user", "content": "Say hello world."}],
+            "stream": stream,
+        }))),
+    }
+}
+
+/// Replay hand-written interactions, with an SSE line split over two chunks,
+/// and then record and replay real ones.
+///
+/// This is a single test because the cassette applies to the whole process.
+#[tokio::test]
+async fn test_cassette() {
+    common::init_tracing();
+    let dir = std::env::temp_dir().join("transformrs-test-cassette");
+    let cassette = Cassette {
+        mode: Mode::Replay,
+        dir: dir.clone(),
+        loopback: true,
+    };
+    let completion = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1,
+        "model": MODEL,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "hello world"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 4, "completion_tokens": 2, "total_tokens": 6}
+    });
+    cassette
+        .save(&Interaction {
+            request: request(false),
+            response: RecordedResponse {
+                status: 200,
+                content_type: Some("application/json".to_string()),
+                chunks: vec![Body::Json(completion)],
+            },
+        })
+        .unwrap();
+    let chunk = |content: &str| {
+        json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": MODEL,
+            "choices": [{"index": 0, "delta": {"content": content}}]
+        })
+        .to_string()
+    };
+    let first = format!("data: {}\n\ndata: {}", chunk("hello"), chunk(" world"));
+    let (first, second) = first.split_at(first.len() - 10);
+    cassette
+        .save(&Interaction {
+            request: request(true),
+            response: RecordedResponse {
+                status: 200,
+                content_type: Some("text/event-stream".to_string()),
+                chunks: vec![
+                    Body::Text(first.to_string()),
+                    Body::Text(format!("{second}\n\ndata: [DONE]\n\n")),
+                ],
+            },
+        })
+        .unwrap();
+
+    cassette::install(Mode::Replay, dir.to_str().unwrap());
+    let provider = Provider::DeepInfra;
+    let key = Key::new(provider.clone(), "replay");
+    let messages = vec![Message::from_str("user", "Say hello world.")];
+
+    let resp = chat::chat_completion(&provider, &key, MODEL, &messages)
+        .await
+        .unwrap();
+    let content = &resp.structured().unwrap().choices[0].message.content;
+    assert_eq!(content.to_string(), "hello world");
+
+    let mut stream = chat::stream_chat_completion(&provider, &key, MODEL, &messages)
+        .await
+        .unwrap();
+    let mut content = String::new();
+    while let Some(resp) = stream.next().await {
+        content += &resp.choices[0].delta.content.clone().unwrap_or_default();
+    }
+    assert_eq!(content, "hello world");
+
+    let other = vec![Message::from_str("user", "Something else.")];
+    let err = chat::chat_completion(&provider, &key, MODEL, &other)
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("no cassette"));
+
+    // Record against the mock server and replay after it is gone.
+    let dir = std::env::temp_dir().join("transformrs-test-cassette-record");
+    let _ = std::fs::remove_dir_all(&dir);
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "sk-secret-value");
+    cassette::install(Mode::Record, dir.to_str().unwrap());
+    let mut stream = chat::stream_chat_completion(&provider, &key, MODEL, &messages)
+        .await
+        .unwrap();
+    while stream.next().await.is_some() {}
+    drop(server);
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(files.len(), 1);
+    let fixture = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+    assert!(!fixture.contains("sk-secret-value"));
+
+    cassette::install(Mode::Replay, dir.to_str().unwrap());
+    let mut stream = chat::stream_chat_completion(&provider, &key, MODEL, &messages)
+        .await
+        .unwrap();
+    let mut content = String::new();
+    while let Some(resp) = stream.next().await {
+        content += &resp.choices[0].delta.content.clone().unwrap_or_default();
+    }
+    assert_eq!(content, "Say hello world.");
+    cassette::uninstall();
+}
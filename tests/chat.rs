@@ -35,9 +35,7 @@ async fn test_chat_completion_no_stream(
     model: &str,
     expected: Option<&str>,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    common::init_tracing();
-    let keys = transformrs::load_keys(".env");
-    let key = keys.for_provider(&provider).expect("no key found");
+    let key = common::key(&provider);
     let messages = messages.clone();
     let resp = chat::chat_completion(&provider, &key, model, &messages).await;
     let resp = match resp {
@@ -230,9 +228,7 @@ async fn chat_completion_stream_helper(
 #[tokio::test]
 async fn test_chat_completion_stream_deepinfra() {
     let provider = Provider::DeepInfra;
-    let key = transformrs::load_keys(".env")
-        .for_provider(&provider)
-        .unwrap();
+    let key = common::key(&provider);
     chat_completion_stream_helper(&provider, &key, MODEL)
         .await
         .unwrap();
@@ -241,9 +237,7 @@ async fn test_chat_completion_stream_deepinfra() {
 #[tokio::test]
 async fn test_chat_completion_stream_google() {
     let provider = Provider::Google;
-    let key = transformrs::load_keys(".env")
-        .for_provider(&provider)
-        .unwrap();
+    let key = common::key(&provider);
     chat_completion_stream_helper(&provider, &key, "gemini-2.0-flash-lite")
         .await
         .unwrap();
@@ -253,9 +247,7 @@ async fn test_chat_completion_stream_google() {
 async fn test_chat_completion_stream_hyperbolic() {
     let provider = Provider::Hyperbolic;
     let model = &hyperbolic_model();
-    let key = transformrs::load_keys(".env")
-        .for_provider(&provider)
-        .unwrap();
+    let key = common::key(&provider);
     chat_completion_stream_helper(&provider, &key, model)
         .await
         .unwrap();
@@ -264,9 +256,7 @@ async fn test_chat_completion_stream_hyperbolic() {
 #[tokio::test]
 async fn test_chat_completion_stream_openai() {
     let provider = Provider::OpenAI;
-    let key = transformrs::load_keys(".env")
-        .for_provider(&provider)
-        .unwrap();
+    let key = common::key(&provider);
     chat_completion_stream_helper(&provider, &key, "gpt-4o-mini")
         .await
         .unwrap();
@@ -1,4 +1,12 @@
+#![allow(dead_code)]
+
 use tracing::subscriber::SetGlobalDefaultError;
+use transformrs::audio::AudioFormat;
+use transformrs::cassette;
+use transformrs::mock_server::MockServer;
+use transformrs::text_to_speech::TTSConfig;
+use transformrs::Key;
+use transformrs::Provider;
 
 /// Initialize logging with the given level.
 pub fn init_subscriber(level: tracing::Level) -> Result<(), SetGlobalDefaultError> {
@@ -21,3 +29,44 @@ pub fn init_tracing() {
         Err(_e) => (),
     }
 }
+
+/// Get the key for the provider.
+///
+/// Also installs the cassette from `TRANSFORMRS_CASSETTE`. When replaying,
+/// no key is needed, so a placeholder is returned if the key is missing.
+pub fn key(provider: &Provider) -> Key {
+    init_tracing();
+    let mode = cassette::install_from_env().expect("invalid cassette config");
+    let keys = transformrs::load_keys(".env");
+    match keys.for_provider(provider) {
+        Some(key) => key,
+        None if mode == Some(cassette::Mode::Replay) => Key::new(provider.clone(), "replay"),
+        None => panic!("no key found for {provider}"),
+    }
+}
+
+/// Synthesize "Hello" via a mock server for `provider` and check the format.
+///
+/// Returns the server so that tests can inspect the request.
+pub async fn mock_tts(provider: Provider, config: TTSConfig, model: Option<&str>) -> MockServer {
+    init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&provider);
+    let resp = transformrs::text_to_speech::tts(&provider, &key, &config, model, "Hello")
+        .await
+        .unwrap();
+    let speech = resp.structured().unwrap();
+    let expected = config.output_format.unwrap_or(AudioFormat::Mp3);
+    let wav_by_default = matches!(
+        provider,
+        Provider::Google | Provider::Groq | Provider::TogetherAI
+    );
+    let expected = if wav_by_default && config.output_format.is_none() {
+        AudioFormat::Wav
+    } else {
+        expected
+    };
+    assert_eq!(speech.format, expected);
+    assert_eq!(speech.file_format, expected.extension());
+    server
+}
@@ -0,0 +1,88 @@
+extern crate transformrs;
+
+mod common;
+
+use std::collections::HashMap;
+use transformrs::audio::AudioFormat;
+use transformrs::dialogue;
+use transformrs::dialogue::DialogueConfig;
+use transformrs::dialogue::Speaker;
+use transformrs::dialogue::Turn;
+use transformrs::mock_server::MockServer;
+use transformrs::Key;
+use transformrs::Provider;
+
+fn turns() -> Vec<Turn> {
+    vec![
+        Turn::new("host", "Welcome to the show."),
+        Turn::new("guest", "Thanks for having me."),
+        Turn::new("host", "Let's get started."),
+    ]
+}
+
+#[tokio::test]
+async fn test_dialogue_openai() {
+    let key = common::key(&Provider::OpenAI);
+    let mut speakers = HashMap::new();
+    speakers.insert("host".to_string(), Speaker::new(&key, "alloy"));
+    speakers.insert("guest".to_string(), Speaker::new(&key, "onyx"));
+    let config = DialogueConfig::default();
+    let dialogue = dialogue::synthesize(&turns(), &speakers, &config)
+        .await
+        .unwrap();
+    assert_eq!(dialogue.speech.format, AudioFormat::Mp3);
+    assert_eq!(dialogue.turns.len(), 3);
+    assert!(dialogue.turns[1].start > dialogue.turns[0].end + 0.4);
+}
+
+#[tokio::test]
+async fn test_dialogue_mock() {
+    let server = MockServer::start().await.unwrap();
+    let key = Key::new(server.provider(), "mock");
+    let mut speakers = HashMap::new();
+    speakers.insert("host".to_string(), Speaker::new(&key, "alloy"));
+    speakers.insert("guest".to_string(), Speaker::new(&key, "onyx"));
+    let config = DialogueConfig {
+        pause: 0.5,
+        concurrency: 2,
+    };
+    let dialogue = dialogue::synthesize(&turns(), &speakers, &config)
+        .await
+        .unwrap();
+    let voices: Vec<String> = server
+        .requests()
+        .iter()
+        .map(|request| {
+            request.json().unwrap()["voice"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(voices.iter().filter(|voice| *voice == "alloy").count(), 2);
+    assert_eq!(voices.iter().filter(|voice| *voice == "onyx").count(), 1);
+
+    // The mock returns one frame and the pause is rounded to 19 frames.
+    let frame = 1152.0 / 44100.0;
+    let starts: Vec<f64> = dialogue.turns.iter().map(|turn| turn.start).collect();
+    let expected = [0.0, 20.0 * frame, 40.0 * frame];
+    for (start, expected) in starts.iter().zip(expected) {
+        assert!((start - expected).abs() < 1e-9);
+    }
+    assert_eq!(dialogue.turns[1].speaker, "guest");
+    assert!((dialogue.turns[2].end - 41.0 * frame).abs() < 1e-9);
+    let duration = dialogue.speech.duration().unwrap();
+    assert!((duration - 41.0 * frame).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_dialogue_unknown_speaker() {
+    let key = Key::new(Provider::OpenAI, "mock");
+    let mut speakers = HashMap::new();
+    speakers.insert("host".to_string(), Speaker::new(&key, "alloy"));
+    let config = DialogueConfig::default();
+    let err = dialogue::synthesize(&turns(), &speakers, &config)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "unknown speaker: guest");
+}
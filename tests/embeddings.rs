@@ -0,0 +1,136 @@
+extern crate transformrs;
+
+mod common;
+
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use transformrs::embeddings::Embeddings;
+use transformrs::embeddings::EmbeddingsConfig;
+use transformrs::embeddings::EncodingFormat;
+use transformrs::mock_server::MockServer;
+use transformrs::Key;
+use transformrs::Provider;
+
+const INPUT: &[&str] = &["The food was delicious.", "The weather is nice."];
+
+async fn embeddings_helper(
+    provider: &Provider,
+    key: &Key,
+    config: &EmbeddingsConfig,
+    model: &str,
+) -> Result<Embeddings, Box<dyn Error + Send + Sync>> {
+    let resp = transformrs::embeddings::embeddings(provider, key, config, model, INPUT).await?;
+    resp.structured()
+}
+
+#[tokio::test]
+async fn test_embeddings_openai() {
+    let provider = Provider::OpenAI;
+    let key = common::key(&provider);
+    let config = EmbeddingsConfig {
+        dimensions: Some(256),
+        ..Default::default()
+    };
+    let model = "text-embedding-3-small";
+    let embeddings = embeddings_helper(&provider, &key, &config, model)
+        .await
+        .unwrap();
+    assert_eq!(embeddings.data.len(), 2);
+    assert_eq!(embeddings.data[0].embedding.len(), 256);
+    assert!(embeddings.usage.is_some());
+}
+
+#[tokio::test]
+async fn test_embeddings_deepinfra() {
+    let provider = Provider::DeepInfra;
+    let key = common::key(&provider);
+    let config = EmbeddingsConfig::default();
+    let model = "BAAI/bge-m3";
+    let embeddings = embeddings_helper(&provider, &key, &config, model)
+        .await
+        .unwrap();
+    assert_eq!(embeddings.data.len(), 2);
+}
+
+#[tokio::test]
+async fn test_embeddings_google() {
+    let provider = Provider::Google;
+    let key = common::key(&provider);
+    let config = EmbeddingsConfig::default();
+    let model = "text-embedding-004";
+    let embeddings = embeddings_helper(&provider, &key, &config, model)
+        .await
+        .unwrap();
+    assert_eq!(embeddings.data.len(), 2);
+    assert_eq!(embeddings.data[1].index, 1);
+}
+
+#[tokio::test]
+async fn test_embeddings_mock_base64() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let float = EmbeddingsConfig {
+        dimensions: Some(4),
+        ..Default::default()
+    };
+    let base64 = EmbeddingsConfig {
+        encoding_format: Some(EncodingFormat::Base64),
+        ..float.clone()
+    };
+    let a = embeddings_helper(&provider, &key, &float, "mock-model")
+        .await
+        .unwrap();
+    let b = embeddings_helper(&provider, &key, &base64, "mock-model")
+        .await
+        .unwrap();
+    assert_eq!(a.data[0].embedding.len(), 4);
+    assert_eq!(a.data, b.data);
+    assert_eq!(a.usage.unwrap().prompt_tokens, 8);
+    let body = server.requests()[1].json().unwrap();
+    assert_eq!(body["encoding_format"], "base64");
+    assert_eq!(body["dimensions"], 4);
+}
+
+#[tokio::test]
+async fn test_embeddings_mock_google() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::Google);
+    let other = HashMap::from([("taskType".to_string(), json!("RETRIEVAL_DOCUMENT"))]);
+    let config = EmbeddingsConfig {
+        dimensions: Some(3),
+        other: Some(other),
+        ..Default::default()
+    };
+    let model = "text-embedding-004";
+    let embeddings =
+        transformrs::embeddings::embeddings(&Provider::Google, &key, &config, model, INPUT)
+            .await
+            .unwrap()
+            .structured()
+            .unwrap();
+    assert_eq!(embeddings.data.len(), 2);
+    assert_eq!(embeddings.data[0].embedding.len(), 3);
+    let request = &server.requests()[0];
+    assert_eq!(
+        request.path,
+        "/v1beta/models/text-embedding-004:batchEmbedContents"
+    );
+    let body = request.json().unwrap();
+    assert_eq!(body["requests"][0]["model"], "models/text-embedding-004");
+    assert_eq!(body["requests"][1]["taskType"], "RETRIEVAL_DOCUMENT");
+    assert_eq!(body.get("taskType"), None);
+
+    let base64 = EmbeddingsConfig {
+        encoding_format: Some(EncodingFormat::Base64),
+        ..Default::default()
+    };
+    let err = transformrs::embeddings::embeddings(&Provider::Google, &key, &base64, model, INPUT)
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("base64"));
+}
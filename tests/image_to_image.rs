@@ -0,0 +1,201 @@
+extern crate transformrs;
+
+mod common;
+
+use base64::prelude::*;
+use bytes::Bytes;
+use serde_json::json;
+use std::collections::HashMap;
+use transformrs::image_to_image::ImageEditConfig;
+use transformrs::mock_server::MockServer;
+use transformrs::mock_server::PNG;
+use transformrs::text_to_image::Image;
+use transformrs::text_to_image::ImageResponseFormat;
+use transformrs::Key;
+use transformrs::Provider;
+
+fn png() -> Image {
+    Image::new(Bytes::from_static(PNG))
+}
+
+fn sunset() -> Image {
+    Image::new(Bytes::from_static(include_bytes!("sunset.jpg")))
+}
+
+#[tokio::test]
+async fn test_edit_image_hyperbolic() {
+    let key = common::key(&Provider::Hyperbolic);
+    let config = ImageEditConfig {
+        model: "SDXL1.0-base".to_string(),
+        strength: Some(0.5),
+        height: Some(1024),
+        width: Some(1024),
+        ..Default::default()
+    };
+    let prompt = "The same sunset in the style of Van Gogh.";
+    let resp = transformrs::image_to_image::edit_image(&key, &config, &sunset(), None, prompt)
+        .await
+        .unwrap();
+    let images = resp.images().await.unwrap();
+    assert!(!images[0].image.is_empty());
+}
+
+#[tokio::test]
+async fn test_image_variations_openai() {
+    let key = common::key(&Provider::OpenAI);
+    let config = ImageEditConfig {
+        model: "dall-e-2".to_string(),
+        size: Some("256x256".to_string()),
+        response_format: Some(ImageResponseFormat::B64Json),
+        ..Default::default()
+    };
+    let resp = transformrs::image_to_image::image_variations(&key, &config, &png())
+        .await
+        .unwrap();
+    let images = resp.images().await.unwrap();
+    assert!(!images[0].image.is_empty());
+}
+
+#[tokio::test]
+async fn test_edit_image_mock() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let config = ImageEditConfig {
+        model: "mock-model".to_string(),
+        n: Some(2),
+        response_format: Some(ImageResponseFormat::B64Json),
+        ..Default::default()
+    };
+    let resp =
+        transformrs::image_to_image::edit_image(&key, &config, &png(), Some(&png()), "Add a cat")
+            .await
+            .unwrap();
+    let images = resp.images().await.unwrap();
+    assert_eq!(images.len(), 2);
+    assert_eq!(&images[0].image[..], PNG);
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/images/edits");
+    assert_eq!(request.form_text("prompt").unwrap(), "Add a cat");
+    assert_eq!(request.form_text("n").unwrap(), "2");
+    let parts = request.form().unwrap();
+    let image = parts.iter().find(|part| part.name == "image").unwrap();
+    assert_eq!(image.file_name.as_deref(), Some("image.png"));
+    assert_eq!(image.content_type.as_deref(), Some("image/png"));
+    assert_eq!(&image.body[..], PNG);
+    assert!(parts.iter().any(|part| part.name == "mask"));
+
+    let config = ImageEditConfig {
+        strength: Some(0.5),
+        ..Default::default()
+    };
+    let result = transformrs::image_to_image::edit_image(&key, &config, &png(), None, "x").await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_image_variations_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let config = ImageEditConfig {
+        response_format: Some(ImageResponseFormat::Url),
+        ..Default::default()
+    };
+    let resp = transformrs::image_to_image::image_variations(&key, &config, &png())
+        .await
+        .unwrap();
+    let images = resp.images().await.unwrap();
+    assert_eq!(&images[0].image[..], PNG);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/v1/images/variations");
+    assert!(requests[0].form_text("model").is_none());
+    assert!(requests[0].form_text("prompt").is_none());
+}
+
+#[tokio::test]
+async fn test_edit_image_options_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let mut other = HashMap::new();
+    other.insert("background".to_string(), json!("transparent"));
+    other.insert("output_compression".to_string(), json!(50));
+    let config = ImageEditConfig {
+        other: Some(other),
+        ..Default::default()
+    };
+    transformrs::image_to_image::edit_image(&key, &config, &png(), None, "Add a cat")
+        .await
+        .unwrap();
+    let request = &server.requests()[0];
+    assert_eq!(request.form_text("background").unwrap(), "transparent");
+    assert_eq!(request.form_text("output_compression").unwrap(), "50");
+    let names: Vec<String> = request
+        .form()
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    let background = names.iter().position(|name| name == "background");
+    let compression = names.iter().position(|name| name == "output_compression");
+    assert!(background < compression);
+
+    let config = ImageEditConfig {
+        steps: Some(20),
+        width: Some(512),
+        ..Default::default()
+    };
+    let err = transformrs::image_to_image::image_variations(&key, &config, &png())
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("options: steps, width"));
+
+    let key = server.key(&Provider::Hyperbolic);
+    let config = ImageEditConfig {
+        n: Some(2),
+        size: Some("512x512".to_string()),
+        ..Default::default()
+    };
+    let err = transformrs::image_to_image::edit_image(&key, &config, &png(), None, "Add a cat")
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Hyperbolic does not support the image-to-image options: size, n"
+    );
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_edit_image_hyperbolic_mock() {
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::Hyperbolic);
+    let config = ImageEditConfig {
+        model: "SDXL1.0-base".to_string(),
+        strength: Some(0.7),
+        ..Default::default()
+    };
+    let image = transformrs::text_to_image::Image::new(bytes::Bytes::from_static(
+        transformrs::mock_server::PNG,
+    ));
+    let resp = transformrs::image_to_image::edit_image(&key, &config, &image, None, "A cat")
+        .await
+        .unwrap();
+    let images = resp.images().await.unwrap();
+    assert_eq!(&images[0].image[..], transformrs::mock_server::PNG);
+    let body = server.requests()[0].json().unwrap();
+    assert_eq!(body["strength"], 0.7);
+    assert_eq!(body["model_name"], "SDXL1.0-base");
+    assert_eq!(
+        body["image"],
+        BASE64_STANDARD.encode(transformrs::mock_server::PNG)
+    );
+
+    let result =
+        transformrs::image_to_image::edit_image(&key, &config, &image, Some(&image), "A cat").await;
+    assert!(result.is_err());
+}
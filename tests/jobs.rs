@@ -0,0 +1,160 @@
+extern crate transformrs;
+
+mod common;
+
+use serde_json::json;
+use std::time::Duration;
+use std::time::Instant;
+use transformrs::jobs;
+use transformrs::jobs::JobStatus;
+use transformrs::jobs::PollConfig;
+use transformrs::mock_server::MockResponse;
+use transformrs::mock_server::MockServer;
+use transformrs::text_to_image::ImageFormat;
+use transformrs::text_to_image::TTIConfig;
+use transformrs::Provider;
+
+#[tokio::test]
+async fn test_run_replicate() {
+    let key = common::key(&Provider::Replicate);
+    let model = "black-forest-labs/flux-schnell";
+    let input = json!({"prompt": "A beautiful sunset over a calm ocean.", "output_format": "png"});
+    let job = jobs::run(&key, model, &input, &PollConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(job.status, JobStatus::Succeeded);
+    let outputs = job.download_outputs().await.unwrap();
+    assert_eq!(ImageFormat::detect(&outputs[0]), Some(ImageFormat::Png));
+}
+
+#[tokio::test]
+async fn test_text_to_image_replicate() {
+    let key = common::key(&Provider::Replicate);
+    let config = TTIConfig {
+        model: "black-forest-labs/flux-schnell".to_string(),
+        n: Some(2),
+        output_format: Some("webp".to_string()),
+        ..Default::default()
+    };
+    let prompt = "A beautiful sunset over a calm ocean.";
+    let resp = transformrs::text_to_image::text_to_image(&key, config, prompt)
+        .await
+        .unwrap();
+    let images = resp.images().await.unwrap();
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0].format, Some(ImageFormat::Webp));
+}
+
+#[tokio::test]
+async fn test_unsupported_provider() {
+    let key = transformrs::Key::new(Provider::OpenAI, "key");
+    let err = jobs::get(&key, "abc").await.unwrap_err();
+    assert_eq!(err.to_string(), "Unsupported jobs provider: OpenAI");
+}
+
+fn fast_polling() -> PollConfig {
+    PollConfig {
+        initial_interval: Duration::from_millis(1),
+        max_interval: Duration::from_millis(4),
+        multiplier: 2.0,
+        timeout: Some(Duration::from_secs(5)),
+    }
+}
+
+#[tokio::test]
+async fn test_replicate_mock() {
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::Replicate);
+    let prediction = |status: &str| {
+        MockResponse::json(
+            200,
+            &json!({"id": "p1", "status": status, "output": null, "error": null}),
+        )
+    };
+    server.enqueue(prediction("starting"));
+    server.enqueue(prediction("processing"));
+    server.enqueue(prediction("processing"));
+    let input = json!({"prompt": "A sunset", "num_outputs": 2});
+    let job = jobs::submit(&key, "owner/model", &input).await.unwrap();
+    assert_eq!(job.status, JobStatus::Starting);
+    let job = jobs::wait(&key, &job, &fast_polling()).await.unwrap();
+    assert_eq!(job.status, JobStatus::Succeeded);
+    let outputs = job.download_outputs().await.unwrap();
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(&outputs[0][..], transformrs::mock_server::PNG);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/v1/models/owner/model/predictions");
+    assert_eq!(requests[0].headers["authorization"], "Bearer mock");
+    assert_eq!(requests[0].json().unwrap()["input"]["num_outputs"], 2);
+    assert_eq!(requests[1].path, "/v1/predictions/p1");
+    // Two processing polls and one that succeeded, then the download.
+    assert_eq!(requests.len(), 5);
+
+    let job = jobs::submit(&key, "owner/model:abc123", &input)
+        .await
+        .unwrap();
+    let request = server.requests().pop().unwrap();
+    assert_eq!(request.path, "/v1/predictions");
+    assert_eq!(request.json().unwrap()["version"], "abc123");
+    let canceled = jobs::cancel(&key, &job.id).await.unwrap();
+    assert_eq!(canceled.status, JobStatus::Canceled);
+
+    server.enqueue(MockResponse::json(
+        200,
+        &json!({"id": "p2", "status": "failed", "error": "NSFW content detected"}),
+    ));
+    let job = jobs::get(&key, "p2").await.unwrap();
+    assert!(job.download_outputs().await.is_err());
+    let err = job.check().unwrap_err().to_string();
+    assert!(err.contains("NSFW content detected"));
+
+    server.enqueue(MockResponse::json(
+        404,
+        &json!({"title": "Not found", "detail": "The requested resource could not be found.", "status": 404}),
+    ));
+    let err = jobs::get(&key, "missing").await.unwrap_err().to_string();
+    assert!(err.contains("could not be found"));
+}
+
+#[tokio::test]
+async fn test_replicate_timeout_mock() {
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::Replicate);
+    for _ in 0..100 {
+        server.enqueue(MockResponse::json(
+            200,
+            &json!({"id": "slow", "status": "processing"}),
+        ));
+    }
+    let job = jobs::get(&key, "slow").await.unwrap();
+    let config = PollConfig {
+        timeout: Some(Duration::from_millis(30)),
+        ..fast_polling()
+    };
+    let start = Instant::now();
+    let err = jobs::wait(&key, &job, &config).await.unwrap_err();
+    assert!(err.to_string().contains("did not finish"));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn test_text_to_image_replicate_mock() {
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::Replicate);
+    let config = TTIConfig {
+        model: "black-forest-labs/flux-schnell".to_string(),
+        n: Some(3),
+        cfg_scale: Some(3.5),
+        ..Default::default()
+    };
+    let resp = transformrs::text_to_image::text_to_image(&key, config, "A sunset")
+        .await
+        .unwrap();
+    let images = resp.images().await.unwrap();
+    assert_eq!(images.len(), 3);
+    assert_eq!(&images[2].image[..], transformrs::mock_server::PNG);
+    let input = server.requests()[0].json().unwrap()["input"].clone();
+    assert_eq!(input["prompt"], "A sunset");
+    assert_eq!(input["num_outputs"], 3);
+    assert_eq!(input["guidance_scale"], 3.5);
+}
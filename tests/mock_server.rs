@@ -0,0 +1,96 @@
+extern crate transformrs;
+
+mod common;
+
+use futures_util::stream::StreamExt;
+use serde_json::json;
+use std::time::Duration;
+use std::time::Instant;
+use transformrs::chat;
+use transformrs::mock_server::MockResponse;
+use transformrs::mock_server::MockServer;
+use transformrs::Key;
+use transformrs::Message;
+
+fn messages() -> Vec<Message> {
+    vec![
+        Message::from_str("system", "You are a helpful assistant."),
+        Message::from_str("user", "hello world"),
+    ]
+}
+
+#[tokio::test]
+async fn test_mock_chat_completion() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let resp = chat::chat_completion(&provider, &key, "mock-model", &messages())
+        .await
+        .unwrap();
+    let resp = resp.structured().unwrap();
+    assert_eq!(resp.choices[0].message.content.to_string(), "hello world");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/v1/chat/completions");
+    assert_eq!(requests[0].headers["authorization"], "Bearer mock");
+    let body = requests[0].json().unwrap();
+    assert_eq!(body["model"], "mock-model");
+    assert_eq!(body["messages"][1]["content"], "hello world");
+}
+
+#[tokio::test]
+async fn test_mock_stream_partial_frames() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let chunk = |content: &str| {
+        json!({
+            "id": "mock",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": "mock-model",
+            "choices": [{"index": 0, "delta": {"content": content}}]
+        })
+    };
+    let response = MockResponse::sse(&[chunk("hello"), chunk(" world")])
+        .split_chunks(7)
+        .with_chunk_delay(Duration::from_millis(1));
+    server.enqueue(response);
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let mut stream = chat::stream_chat_completion(&provider, &key, "mock-model", &messages())
+        .await
+        .unwrap();
+    let mut content = String::new();
+    while let Some(resp) = stream.next().await {
+        content += &resp.choices[0].delta.content.clone().unwrap_or_default();
+    }
+    assert_eq!(content, "hello world");
+}
+
+#[tokio::test]
+async fn test_mock_error_and_latency() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    server.enqueue(MockResponse::error(404, "The model `foo` does not exist"));
+    server.set_latency(Duration::from_millis(50));
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let start = Instant::now();
+    let resp = chat::chat_completion(&provider, &key, "foo", &messages())
+        .await
+        .unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    let err = resp.structured().unwrap_err();
+    assert!(err.to_string().contains("does not exist"));
+}
+
+#[tokio::test]
+async fn test_mock_models() {
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let resp = transformrs::models::models(&provider, &key).await.unwrap();
+    assert!(resp.structured().unwrap().contains("mock-model"));
+}
@@ -1,13 +1,14 @@
 extern crate transformrs;
 
+mod common;
+
 use std::error::Error;
 use transformrs::models::models;
 use transformrs::models::Models;
 use transformrs::Provider;
 
 async fn test_models(provider: Provider) -> Result<Models, Box<dyn Error + Send + Sync>> {
-    let keys = transformrs::load_keys(".env");
-    let key = keys.for_provider(&provider).unwrap();
+    let key = common::key(&provider);
     let resp = models(&provider, &key).await;
     let resp = match resp {
         Ok(resp) => resp,
@@ -0,0 +1,136 @@
+extern crate transformrs;
+
+mod common;
+
+use transformrs::mock_server::MockServer;
+use transformrs::moderation::Category;
+use transformrs::moderation::ModerationInput;
+use transformrs::Key;
+use transformrs::Provider;
+
+const SAFE: &str = "I love sunny days.";
+const VIOLENT: &str = "I am going to kill you.";
+
+#[tokio::test]
+async fn test_moderate_openai() {
+    let provider = Provider::OpenAI;
+    let key = common::key(&provider);
+    let input = [ModerationInput::text(SAFE), ModerationInput::text(VIOLENT)];
+    let model = "omni-moderation-latest";
+    let resp = transformrs::moderation::moderate(&provider, &key, model, &input)
+        .await
+        .unwrap();
+    let moderation = resp.structured().unwrap();
+    assert_eq!(moderation.results.len(), 2);
+    assert!(!moderation.results[0].flagged);
+    assert!(moderation.results[1].flagged);
+    assert!(moderation.results[1]
+        .categories
+        .contains(&Category::Violence));
+}
+
+#[tokio::test]
+async fn test_moderate_openai_image() {
+    let provider = Provider::OpenAI;
+    let key = common::key(&provider);
+    let image = include_bytes!("sunset.jpg");
+    let input = [ModerationInput::image_bytes("jpeg", image)];
+    let model = "omni-moderation-latest";
+    let resp = transformrs::moderation::moderate(&provider, &key, model, &input)
+        .await
+        .unwrap();
+    let moderation = resp.structured().unwrap();
+    assert!(!moderation.flagged());
+}
+
+#[tokio::test]
+async fn test_moderate_groq_llama_guard() {
+    let provider = Provider::Groq;
+    let key = common::key(&provider);
+    let model = "meta-llama/llama-guard-4-12b";
+    let resp = transformrs::moderation::moderate(
+        &provider,
+        &key,
+        model,
+        &[ModerationInput::text(VIOLENT)],
+    )
+    .await
+    .unwrap();
+    let moderation = resp.structured().unwrap();
+    assert!(moderation.flagged());
+    assert!(!moderation.results[0].categories.is_empty());
+}
+
+#[tokio::test]
+async fn test_moderate_deepinfra_llama_guard() {
+    let provider = Provider::DeepInfra;
+    let key = common::key(&provider);
+    let model = "meta-llama/Llama-Guard-4-12B";
+    let resp =
+        transformrs::moderation::moderate(&provider, &key, model, &[ModerationInput::text(SAFE)])
+            .await
+            .unwrap();
+    let moderation = resp.structured().unwrap();
+    assert!(!moderation.flagged());
+}
+
+#[tokio::test]
+async fn test_moderate_mock() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let input = [
+        ModerationInput::text(SAFE),
+        ModerationInput::text(VIOLENT),
+        ModerationInput::image_url("https://example.com/cat.png"),
+    ];
+    let resp = transformrs::moderation::moderate(&provider, &key, "mock-model", &input)
+        .await
+        .unwrap();
+    let moderation = resp.structured().unwrap();
+    assert_eq!(moderation.id.as_deref(), Some("modr-mock"));
+    assert!(moderation.flagged());
+    assert_eq!(moderation.results.len(), 3);
+    assert!(!moderation.results[0].flagged);
+    assert_eq!(moderation.results[1].categories, vec![Category::Violence]);
+    assert_eq!(
+        moderation.results[1].category_scores[&Category::Violence],
+        0.9
+    );
+    let body = server.requests()[0].json().unwrap();
+    assert_eq!(body["input"][0]["type"], "text");
+    assert_eq!(
+        body["input"][2]["image_url"]["url"],
+        "https://example.com/cat.png"
+    );
+}
+
+#[tokio::test]
+async fn test_moderate_llama_guard_mock() {
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::Groq);
+    let model = "meta-llama/llama-guard-4-12b";
+    let input = [ModerationInput::text("I am going to kill you.")];
+    let resp = transformrs::moderation::moderate(&Provider::Groq, &key, model, &input)
+        .await
+        .unwrap();
+    let moderation = resp.structured().unwrap();
+    assert!(moderation.flagged());
+    assert_eq!(
+        moderation.results[0].categories,
+        vec![Category::ViolentCrimes]
+    );
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/openai/v1/chat/completions");
+
+    let input = [ModerationInput::text("Hello!")];
+    let resp = transformrs::moderation::moderate(&Provider::Groq, &key, model, &input)
+        .await
+        .unwrap();
+    assert!(!resp.structured().unwrap().flagged());
+
+    let input = [ModerationInput::image_url("https://example.com/cat.png")];
+    let result = transformrs::moderation::moderate(&Provider::Groq, &key, model, &input).await;
+    assert!(result.is_err());
+}
@@ -0,0 +1,148 @@
+extern crate transformrs;
+
+mod common;
+
+use std::error::Error;
+use transformrs::mock_server::MockServer;
+use transformrs::rerank::Rerank;
+use transformrs::rerank::RerankConfig;
+use transformrs::Key;
+use transformrs::Provider;
+
+const QUERY: &str = "What is the capital of France?";
+
+const DOCUMENTS: &[&str] = &[
+    "Berlin is the capital of Germany.",
+    "Paris is the capital of France.",
+    "Bananas are yellow.",
+];
+
+async fn rerank_helper(
+    provider: &Provider,
+    key: &Key,
+    config: &RerankConfig,
+    model: &str,
+) -> Result<Rerank, Box<dyn Error + Send + Sync>> {
+    let resp = transformrs::rerank::rerank(provider, key, config, model, QUERY, DOCUMENTS).await?;
+    resp.structured()
+}
+
+#[tokio::test]
+async fn test_rerank_togetherai() {
+    let provider = Provider::TogetherAI;
+    let key = common::key(&provider);
+    let config = RerankConfig {
+        top_n: Some(2),
+        return_documents: Some(true),
+        ..Default::default()
+    };
+    let model = "Salesforce/Llama-Rank-V1";
+    let rerank = rerank_helper(&provider, &key, &config, model)
+        .await
+        .unwrap();
+    assert_eq!(rerank.results.len(), 2);
+    assert_eq!(rerank.results[0].index, 1);
+    assert_eq!(rerank.results[0].document.as_deref(), Some(DOCUMENTS[1]));
+}
+
+#[tokio::test]
+async fn test_rerank_deepinfra() {
+    let provider = Provider::DeepInfra;
+    let key = common::key(&provider);
+    let config = RerankConfig::default();
+    let model = "Qwen/Qwen3-Reranker-0.6B";
+    let rerank = rerank_helper(&provider, &key, &config, model)
+        .await
+        .unwrap();
+    assert_eq!(rerank.indices()[0], 1);
+}
+
+#[tokio::test]
+async fn test_rerank_cohere() {
+    let provider = Provider::Cohere;
+    let key = common::key(&provider);
+    let config = RerankConfig {
+        return_documents: Some(true),
+        ..Default::default()
+    };
+    let model = "rerank-v3.5";
+    let rerank = rerank_helper(&provider, &key, &config, model)
+        .await
+        .unwrap();
+    assert_eq!(rerank.results[0].document.as_deref(), Some(DOCUMENTS[1]));
+}
+
+#[tokio::test]
+async fn test_rerank_mock() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let config = RerankConfig {
+        top_n: Some(2),
+        ..Default::default()
+    };
+    let rerank = rerank_helper(&provider, &key, &config, "mock-model")
+        .await
+        .unwrap();
+    assert_eq!(rerank.indices(), vec![1, 0]);
+    assert!(rerank.results[0].relevance_score > rerank.results[1].relevance_score);
+    assert_eq!(rerank.results[0].document, None);
+    let body = server.requests()[0].json().unwrap();
+    assert_eq!(body["top_n"], 2);
+    assert_eq!(body["query"], QUERY);
+}
+
+#[tokio::test]
+async fn test_rerank_base_url_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::OpenAICompatible("http://unreachable.invalid".to_string());
+    let key = Key::new(provider.clone(), "mock").with_base_url(&server.url());
+    let config = RerankConfig::default();
+    let rerank = rerank_helper(&provider, &key, &config, "mock-model")
+        .await
+        .unwrap();
+    assert_eq!(rerank.indices()[0], 1);
+    assert_eq!(server.requests()[0].path, "/rerank");
+}
+
+#[tokio::test]
+async fn test_rerank_unsupported() {
+    let key = Key::new(Provider::Groq, "unused");
+    let config = RerankConfig::default();
+    let err = rerank_helper(&Provider::Groq, &key, &config, "model")
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Unsupported rerank provider"));
+}
+
+#[tokio::test]
+async fn test_rerank_deepinfra_mock() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::DeepInfra);
+    let config = RerankConfig {
+        top_n: Some(1),
+        return_documents: Some(true),
+        ..Default::default()
+    };
+    let documents = ["Bananas are yellow.", "Paris is the capital of France."];
+    let query = "capital of France";
+    let model = "Qwen/Qwen3-Reranker-0.6B";
+    let resp = transformrs::rerank::rerank(
+        &Provider::DeepInfra,
+        &key,
+        &config,
+        model,
+        query,
+        &documents,
+    )
+    .await
+    .unwrap();
+    let rerank = resp.structured().unwrap();
+    assert_eq!(rerank.results.len(), 1);
+    assert_eq!(rerank.results[0].index, 1);
+    assert_eq!(rerank.results[0].document.as_deref(), Some(documents[1]));
+    let body = server.requests()[0].json().unwrap();
+    assert_eq!(body["queries"][0], query);
+}
@@ -0,0 +1,114 @@
+extern crate transformrs;
+
+use futures_util::stream::StreamExt;
+use transformrs::chat;
+use transformrs::chat::ChatCompletionChunk;
+use transformrs::chat::ChunkChoice;
+use transformrs::chat::Delta;
+use transformrs::mock_server::PNG;
+use transformrs::scripted::ScriptedProvider;
+use transformrs::text_to_image::TTIConfig;
+use transformrs::text_to_speech::TTSConfig;
+use transformrs::Message;
+
+fn chunk(content: &str) -> ChatCompletionChunk {
+    ChatCompletionChunk {
+        id: None,
+        object: "chat.completion.chunk".to_string(),
+        created: 0,
+        model: "scripted".to_string(),
+        system_fingerprint: None,
+        choices: vec![ChunkChoice {
+            index: 0,
+            delta: Delta {
+                role: None,
+                content: Some(content.to_string()),
+            },
+            finish_reason: None,
+        }],
+    }
+}
+
+#[tokio::test]
+async fn test_scripted_chat() {
+    let scripted = ScriptedProvider::new();
+    scripted.push_chat_reply("hello world");
+    scripted.push_chat_stream(&[chunk("hello"), chunk(" world")]);
+    let provider = scripted.provider();
+    let key = scripted.key();
+    let messages = vec![
+        Message::from_str("system", "You are a helpful assistant."),
+        Message::from_str("user", "Say hello world."),
+    ];
+
+    let resp = chat::chat_completion(&provider, &key, "model-a", &messages)
+        .await
+        .unwrap();
+    let content = &resp.structured().unwrap().choices[0].message.content;
+    assert_eq!(content.to_string(), "hello world");
+
+    let mut stream = chat::stream_chat_completion(&provider, &key, "model-b", &messages)
+        .await
+        .unwrap();
+    let mut content = String::new();
+    while let Some(resp) = stream.next().await {
+        content += &resp.choices[0].delta.content.clone().unwrap_or_default();
+    }
+    assert_eq!(content, "hello world");
+
+    let requests = scripted.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].path, "/chat/completions");
+    let body = requests[0].json().unwrap();
+    assert_eq!(body["model"], "model-a");
+    assert_eq!(body["stream"], false);
+    let sent = requests[1].messages().unwrap();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[1].role, "user");
+    assert_eq!(sent[1].content.to_string(), "Say hello world.");
+}
+
+#[tokio::test]
+async fn test_scripted_errors() {
+    let scripted = ScriptedProvider::new();
+    scripted.push_error(404, "The model `foo` does not exist");
+    let provider = scripted.provider();
+    let messages = vec![Message::from_str("user", "Hi")];
+    let resp = chat::chat_completion(&provider, &scripted.key(), "foo", &messages)
+        .await
+        .unwrap();
+    let err = resp.structured().unwrap_err();
+    assert!(err.to_string().contains("does not exist"));
+
+    let resp = chat::chat_completion(&provider, &scripted.key(), "foo", &messages)
+        .await
+        .unwrap();
+    let err = resp.structured().unwrap_err();
+    assert!(err.to_string().contains("no scripted response left"));
+}
+
+#[tokio::test]
+async fn test_scripted_speech_and_images() {
+    let scripted = ScriptedProvider::new();
+    scripted.push_speech(b"ID3audio");
+    scripted.push_images(&[bytes::Bytes::from_static(PNG)]);
+    let provider = scripted.provider();
+    let key = scripted.key();
+    let config = TTSConfig {
+        voice: Some("alloy".to_string()),
+        ..Default::default()
+    };
+    let resp = transformrs::text_to_speech::tts(&provider, &key, &config, Some("tts-1"), "Hi")
+        .await
+        .unwrap();
+    assert_eq!(&resp.structured().unwrap().audio[..], b"ID3audio");
+    let body = scripted.requests()[0].json().unwrap();
+    assert_eq!(body["input"], "Hi");
+    assert_eq!(body["voice"], "alloy");
+
+    let resp = transformrs::text_to_image::text_to_image(&key, TTIConfig::default(), "A sunset")
+        .await
+        .unwrap();
+    let images = resp.structured().unwrap();
+    assert_eq!(&images.images[0].base64_decode().unwrap().image[..], PNG);
+}
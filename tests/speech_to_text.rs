@@ -0,0 +1,297 @@
+extern crate transformrs;
+
+mod common;
+
+use bytes::Bytes;
+use transformrs::mock_server::MockResponse;
+use transformrs::mock_server::MockServer;
+use transformrs::speech_to_text::AudioFile;
+use transformrs::speech_to_text::ResponseFormat;
+use transformrs::speech_to_text::STTConfig;
+use transformrs::speech_to_text::TimestampGranularity;
+use transformrs::speech_to_text::Transcription;
+use transformrs::subtitles::Cue;
+use transformrs::subtitles::SubtitleConfig;
+use transformrs::text_to_speech::TTSConfig;
+use transformrs::Key;
+use transformrs::Provider;
+
+/// Speech to transcribe, generated with OpenAI.
+async fn hello_world() -> AudioFile {
+    let provider = Provider::OpenAI;
+    let key = common::key(&provider);
+    let config = TTSConfig {
+        voice: Some("alloy".to_string()),
+        ..Default::default()
+    };
+    let resp =
+        transformrs::text_to_speech::tts(&provider, &key, &config, Some("tts-1"), "Hello, world!")
+            .await
+            .unwrap();
+    let speech = resp.structured().unwrap();
+    AudioFile::new("hello.mp3", &speech.audio)
+}
+
+async fn transcribe_live(provider: &Provider, config: &STTConfig, model: &str) -> Transcription {
+    let key = common::key(provider);
+    let file = hello_world().await;
+    let resp = transformrs::speech_to_text::transcribe(provider, &key, config, model, &file)
+        .await
+        .unwrap();
+    resp.structured().unwrap()
+}
+
+fn assert_hello_world(text: &str) {
+    let text = text.to_lowercase();
+    assert!(text.contains("hello"), "unexpected transcription: {text}");
+}
+
+#[tokio::test]
+async fn test_transcribe_openai() {
+    let config = STTConfig::default();
+    let transcription = transcribe_live(&Provider::OpenAI, &config, "whisper-1").await;
+    assert_hello_world(&transcription.text);
+}
+
+#[tokio::test]
+async fn test_transcribe_groq_verbose() {
+    let config = STTConfig {
+        language: Some("en".to_string()),
+        response_format: Some(ResponseFormat::VerboseJson),
+        ..Default::default()
+    };
+    let model = "whisper-large-v3-turbo";
+    let transcription = transcribe_live(&Provider::Groq, &config, model).await;
+    assert_hello_world(&transcription.text);
+    assert!(!transcription.segments.is_empty());
+}
+
+#[tokio::test]
+async fn test_transcribe_deepinfra() {
+    let config = STTConfig::default();
+    let model = "openai/whisper-large-v3-turbo";
+    let transcription = transcribe_live(&Provider::DeepInfra, &config, model).await;
+    assert_hello_world(&transcription.text);
+}
+
+#[tokio::test]
+async fn test_transcribe_elevenlabs() {
+    let config = STTConfig::default();
+    let transcription = transcribe_live(&Provider::ElevenLabs, &config, "scribe_v1").await;
+    assert_hello_world(&transcription.text);
+    assert!(!transcription.words.is_empty());
+}
+
+#[tokio::test]
+async fn test_translate_openai() {
+    let provider = Provider::OpenAI;
+    let key = common::key(&provider);
+    let config = STTConfig::default();
+    let file = hello_world().await;
+    let resp = transformrs::speech_to_text::translate(&provider, &key, &config, "whisper-1", &file)
+        .await
+        .unwrap();
+    assert_hello_world(&resp.text().unwrap());
+}
+
+#[tokio::test]
+async fn test_translate_groq() {
+    let provider = Provider::Groq;
+    let key = common::key(&provider);
+    let config = STTConfig {
+        response_format: Some(ResponseFormat::VerboseJson),
+        ..Default::default()
+    };
+    let file = hello_world().await;
+    let model = "whisper-large-v3";
+    let resp = transformrs::speech_to_text::translate(&provider, &key, &config, model, &file)
+        .await
+        .unwrap();
+    let translation = resp.structured().unwrap();
+    assert_hello_world(&translation.text);
+    assert!(!translation.segments.is_empty());
+}
+
+fn mock_file() -> AudioFile {
+    AudioFile::new("speech.mp3", &transformrs::mock_server::mp3())
+}
+
+#[tokio::test]
+async fn test_transcribe_mock_formats() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let file = mock_file();
+    let expected = "Transcription of 417 bytes.";
+
+    let config = STTConfig {
+        language: Some("en".to_string()),
+        prompt: Some("A test.".to_string()),
+        temperature: Some(0.2),
+        ..Default::default()
+    };
+    let resp = transformrs::speech_to_text::transcribe(&provider, &key, &config, "m", &file)
+        .await
+        .unwrap();
+    assert_eq!(resp.structured().unwrap().text, expected);
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/audio/transcriptions");
+    assert_eq!(request.form_text("model").unwrap(), "m");
+    assert_eq!(request.form_text("language").unwrap(), "en");
+    assert_eq!(request.form_text("prompt").unwrap(), "A test.");
+    assert_eq!(request.form_text("temperature").unwrap(), "0.2");
+    let parts = request.form().unwrap();
+    let upload = parts.iter().find(|part| part.name == "file").unwrap();
+    assert_eq!(upload.file_name.as_deref(), Some("speech.mp3"));
+    assert_eq!(upload.content_type.as_deref(), Some("audio/mpeg"));
+    assert_eq!(upload.body, file.audio);
+
+    let config = STTConfig {
+        response_format: Some(ResponseFormat::Text),
+        ..Default::default()
+    };
+    let resp = transformrs::speech_to_text::transcribe(&provider, &key, &config, "m", &file)
+        .await
+        .unwrap();
+    assert_eq!(resp.structured().unwrap().text, expected);
+
+    let config = STTConfig {
+        response_format: Some(ResponseFormat::Srt),
+        ..Default::default()
+    };
+    let resp = transformrs::speech_to_text::transcribe(&provider, &key, &config, "m", &file)
+        .await
+        .unwrap();
+    assert!(resp
+        .text()
+        .unwrap()
+        .contains("00:00:00,000 --> 00:00:01,000"));
+    assert!(resp.structured().is_err());
+    let cues = resp.cues(&SubtitleConfig::default()).unwrap();
+    assert_eq!(cues, vec![Cue::new(0.0, 1.0, expected)]);
+}
+
+#[tokio::test]
+async fn test_transcribe_mock_verbose_json() {
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let config = STTConfig {
+        response_format: Some(ResponseFormat::VerboseJson),
+        timestamp_granularities: Some(vec![
+            TimestampGranularity::Segment,
+            TimestampGranularity::Word,
+        ]),
+        ..Default::default()
+    };
+    let resp = transformrs::speech_to_text::transcribe(&provider, &key, &config, "m", &mock_file())
+        .await
+        .unwrap();
+    let transcription = resp.structured().unwrap();
+    assert_eq!(transcription.language.as_deref(), Some("english"));
+    assert_eq!(transcription.duration, Some(1.0));
+    assert_eq!(transcription.segments.len(), 1);
+    assert_eq!(transcription.segments[0].end, 1.0);
+    assert_eq!(transcription.words.len(), 4);
+    assert_eq!(transcription.words[0].word, "Transcription");
+    assert_eq!(transcription.words[3].end, 1.0);
+    let srt = transformrs::subtitles::to_srt(&resp.cues(&SubtitleConfig::default()).unwrap());
+    assert!(srt.starts_with("1\n00:00:00,000 --> 00:00:01,000\nTranscription of"));
+}
+
+#[tokio::test]
+async fn test_transcribe_mock_error() {
+    let server = MockServer::start().await.unwrap();
+    server.enqueue(MockResponse::error(400, "Invalid file format."));
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let file = AudioFile::new("speech.txt", &Bytes::from_static(b"not audio"));
+    let config = STTConfig::default();
+    let resp = transformrs::speech_to_text::transcribe(&provider, &key, &config, "m", &file)
+        .await
+        .unwrap();
+    let err = resp.structured().unwrap_err();
+    assert!(err.to_string().contains("Invalid file format."));
+    let parts = server.requests()[0].form().unwrap();
+    let upload = parts.iter().find(|part| part.name == "file").unwrap();
+    assert_eq!(
+        upload.content_type.as_deref(),
+        Some("application/octet-stream")
+    );
+}
+
+#[tokio::test]
+async fn test_translate_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let config = STTConfig {
+        response_format: Some(ResponseFormat::VerboseJson),
+        prompt: Some("Voicemail.".to_string()),
+        ..Default::default()
+    };
+    let resp = transformrs::speech_to_text::translate(&provider, &key, &config, "m", &mock_file())
+        .await
+        .unwrap();
+    assert_eq!(resp.raw_value().unwrap()["task"], "translate");
+    let translation = resp.structured().unwrap();
+    assert_eq!(translation.text, "Transcription of 417 bytes.");
+    assert_eq!(translation.segments.len(), 1);
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/audio/translations");
+    assert_eq!(request.form_text("prompt").unwrap(), "Voicemail.");
+    assert_eq!(
+        request.form_text("response_format").unwrap(),
+        "verbose_json"
+    );
+
+    let config = STTConfig {
+        language: Some("de".to_string()),
+        ..Default::default()
+    };
+    let result =
+        transformrs::speech_to_text::translate(&provider, &key, &config, "m", &mock_file()).await;
+    assert!(result.is_err());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_transcribe_elevenlabs_mock() {
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::ElevenLabs);
+    let config = STTConfig {
+        language: Some("en".to_string()),
+        timestamp_granularities: Some(vec![TimestampGranularity::Word]),
+        ..Default::default()
+    };
+    let file = AudioFile::new("speech.mp3", &transformrs::mock_server::mp3());
+    let resp = transformrs::speech_to_text::transcribe(
+        &Provider::ElevenLabs,
+        &key,
+        &config,
+        "scribe_v1",
+        &file,
+    )
+    .await
+    .unwrap();
+    let transcription = resp.structured().unwrap();
+    assert_eq!(transcription.text, "Transcription of 417 bytes.");
+    assert_eq!(transcription.language.as_deref(), Some("en"));
+    assert_eq!(transcription.words.len(), 4);
+    assert_eq!(transcription.words[0].speaker.as_deref(), Some("speaker_0"));
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/speech-to-text");
+    assert_eq!(request.headers["xi-api-key"], "mock");
+    assert_eq!(request.form_text("model_id").unwrap(), "scribe_v1");
+    assert_eq!(request.form_text("timestamps_granularity").unwrap(), "word");
+
+    let config = STTConfig {
+        prompt: Some("unsupported".to_string()),
+        ..Default::default()
+    };
+    let result =
+        transformrs::speech_to_text::transcribe(&Provider::ElevenLabs, &key, &config, "m", &file)
+            .await;
+    assert!(result.is_err());
+}
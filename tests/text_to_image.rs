@@ -5,16 +5,20 @@ mod common;
 use std::error::Error;
 use std::fs::File;
 use std::io::Write;
+use transformrs::mock_server::MockServer;
+use transformrs::text_to_image::ImageResponseFormat;
 use transformrs::text_to_image::Images;
+use transformrs::text_to_image::TTIConfig;
+use transformrs::Key;
 use transformrs::Provider;
 
 #[tokio::test]
 async fn text_to_image_hyperbolic() {
-    common::init_tracing();
-    let keys = transformrs::load_keys(".env");
-    let key = keys.for_provider(&Provider::Hyperbolic).expect("no key");
-    let mut config = transformrs::text_to_image::TTIConfig::default();
-    config.model = "FLUX.1-dev".to_string();
+    let key = common::key(&Provider::Hyperbolic);
+    let config = transformrs::text_to_image::TTIConfig {
+        model: "FLUX.1-dev".to_string(),
+        ..Default::default()
+    };
     let prompt = "A beautiful sunset over a calm ocean.";
     let resp = transformrs::text_to_image::text_to_image(&key, config, prompt)
         .await
@@ -31,11 +35,11 @@ async fn text_to_image_helper(
     model: &str,
     prompt: &str,
 ) -> Result<Images, Box<dyn Error + Send + Sync>> {
-    common::init_tracing();
-    let keys = transformrs::load_keys(".env");
-    let key = keys.for_provider(&provider).expect("no key");
-    let mut config = transformrs::text_to_image::TTIConfig::default();
-    config.model = model.to_string();
+    let key = common::key(&provider);
+    let config = transformrs::text_to_image::TTIConfig {
+        model: model.to_string(),
+        ..Default::default()
+    };
     let resp = transformrs::text_to_image::text_to_image(&key, config, prompt)
         .await
         .unwrap();
@@ -66,3 +70,141 @@ async fn text_to_image_deepinfra_error() {
     .unwrap_err();
     assert!(resp.to_string().contains("Model is not available"));
 }
+
+#[tokio::test]
+async fn text_to_image_openai() {
+    let key = common::key(&Provider::OpenAI);
+    let config = TTIConfig {
+        model: "dall-e-3".to_string(),
+        size: Some("1024x1024".to_string()),
+        quality: Some("standard".to_string()),
+        style: Some("natural".to_string()),
+        response_format: Some(ImageResponseFormat::Url),
+        ..Default::default()
+    };
+    let prompt = "A beautiful sunset over a calm ocean.";
+    let resp = transformrs::text_to_image::text_to_image(&key, config, prompt)
+        .await
+        .unwrap();
+    let images = resp.images().await.unwrap();
+    assert_eq!(images.len(), 1);
+    assert!(!images[0].image.is_empty());
+}
+
+#[tokio::test]
+async fn text_to_image_togetherai() {
+    let key = common::key(&Provider::TogetherAI);
+    let config = TTIConfig {
+        model: "black-forest-labs/FLUX.1-schnell-Free".to_string(),
+        steps: Some(4),
+        height: Some(512),
+        width: Some(512),
+        response_format: Some(ImageResponseFormat::B64Json),
+        ..Default::default()
+    };
+    let prompt = "A beautiful sunset over a calm ocean.";
+    let resp = transformrs::text_to_image::text_to_image(&key, config, prompt)
+        .await
+        .unwrap();
+    let images = resp.images().await.unwrap();
+    assert!(!images[0].image.is_empty());
+}
+
+#[tokio::test]
+async fn text_to_image_mock_images_generations() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let provider = server.provider();
+    let key = Key::new(provider.clone(), "mock");
+    let config = TTIConfig {
+        model: "mock-model".to_string(),
+        n: Some(2),
+        width: Some(512),
+        height: Some(512),
+        response_format: Some(ImageResponseFormat::Url),
+        ..Default::default()
+    };
+    let resp = transformrs::text_to_image::text_to_image(&key, config, "A sunset")
+        .await
+        .unwrap();
+    let structured = resp.structured().unwrap();
+    assert_eq!(structured.images.len(), 2);
+    assert!(structured.images[1]
+        .url
+        .as_ref()
+        .unwrap()
+        .ends_with("/files/image-1.png"));
+    assert!(structured.images[0].base64_decode().is_err());
+    let images = resp.images().await.unwrap();
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0].filetype, "png");
+    assert_eq!(images[0].dimensions().unwrap(), (1, 1));
+    assert_eq!(&images[0].image[..], transformrs::mock_server::PNG);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/v1/images/generations");
+    let body = requests[0].json().unwrap();
+    assert_eq!(body["model"], "mock-model");
+    assert_eq!(body["n"], 2);
+    assert_eq!(body["response_format"], "url");
+    assert_eq!(body["width"], 512);
+    assert_eq!(requests[1].method, "GET");
+
+    let config = TTIConfig {
+        response_format: Some(ImageResponseFormat::B64Json),
+        ..Default::default()
+    };
+    let resp = transformrs::text_to_image::text_to_image(&key, config, "A sunset")
+        .await
+        .unwrap();
+    let images = resp.images().await.unwrap();
+    assert_eq!(&images[0].image[..], transformrs::mock_server::PNG);
+}
+
+#[tokio::test]
+async fn text_to_image_unsupported_provider() {
+    let key = Key::new(Provider::Groq, "unused");
+    let result =
+        transformrs::text_to_image::text_to_image(&key, TTIConfig::default(), "A sunset").await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_text_to_image_hyperbolic_mock() {
+    common::init_tracing();
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::Hyperbolic);
+    let resp = transformrs::text_to_image::text_to_image(&key, TTIConfig::default(), "A sunset")
+        .await
+        .unwrap();
+    let images = resp.structured().unwrap();
+    let image = images.images[0].base64_decode().unwrap();
+    assert_eq!(&image.image[1..4], b"PNG");
+}
+
+#[tokio::test]
+async fn test_text_to_image_openai_mock() {
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::OpenAI);
+    let config = TTIConfig {
+        model: "dall-e-3".to_string(),
+        size: Some("1024x1792".to_string()),
+        quality: Some("hd".to_string()),
+        style: Some("vivid".to_string()),
+        response_format: Some(ImageResponseFormat::B64Json),
+        ..Default::default()
+    };
+    let resp = transformrs::text_to_image::text_to_image(&key, config, "A sunset")
+        .await
+        .unwrap();
+    let images = resp.images().await.unwrap();
+    assert_eq!(&images[0].image[..], transformrs::mock_server::PNG);
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/images/generations");
+    let body = request.json().unwrap();
+    assert_eq!(body["size"], "1024x1792");
+    assert_eq!(body["quality"], "hd");
+    assert_eq!(body["style"], "vivid");
+    assert_eq!(body["response_format"], "b64_json");
+    assert!(body.get("width").is_none());
+    assert!(body.get("steps").is_none());
+}
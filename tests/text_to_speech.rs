@@ -2,13 +2,21 @@ extern crate transformrs;
 
 mod common;
 
+use futures_util::stream::StreamExt;
 use serde_json::json;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
 use std::io::Write;
+use transformrs::audio::AudioFormat;
+use transformrs::mock_server::MockResponse;
+use transformrs::mock_server::MockServer;
+use transformrs::text_to_speech::PronunciationDictionaryLocator;
 use transformrs::text_to_speech::Speech;
 use transformrs::text_to_speech::TTSConfig;
+use transformrs::text_to_speech::VoiceGender;
+use transformrs::text_to_speech::VoiceSettings;
+use transformrs::Key;
 use transformrs::Provider;
 
 /// Ensure that calling clone compiles.
@@ -25,9 +33,7 @@ async fn tts_helper(
     config: &TTSConfig,
     model: Option<&str>,
 ) -> Result<Speech, Box<dyn Error + Send + Sync>> {
-    common::init_tracing();
-    let keys = transformrs::load_keys(".env");
-    let key = keys.for_provider(&provider).expect("key not found");
+    let key = common::key(provider);
     let msg = "Hello, world!";
     let resp = transformrs::text_to_speech::tts(provider, &key, config, model, msg)
         .await
@@ -39,7 +45,7 @@ async fn tts_helper(
 async fn test_tts_deepinfra() {
     let config = transformrs::text_to_speech::TTSConfig {
         voice: Some("am_echo".to_string()),
-        output_format: Some("mp3".to_string()),
+        output_format: Some(AudioFormat::Mp3),
         ..Default::default()
     };
     let model = Some("hexgrad/Kokoro-82M");
@@ -47,7 +53,7 @@ async fn test_tts_deepinfra() {
     let speech = tts_helper(&provider, &config, model).await.unwrap();
     assert_eq!(speech.file_format, "mp3");
     let bytes = speech.audio.clone();
-    assert!(bytes.len() > 0);
+    assert!(!bytes.is_empty());
 
     // Can be used to manually verify the output.
     let mut file = File::create("tests/tmp-deepinfra.mp3").unwrap();
@@ -68,7 +74,7 @@ async fn test_tts_deepinfra_error() {
 async fn test_tts_deepinfra_opus() {
     let config = transformrs::text_to_speech::TTSConfig {
         voice: Some("am_echo".to_string()),
-        output_format: Some("opus".to_string()),
+        output_format: Some(AudioFormat::Opus),
         ..Default::default()
     };
     let model = Some("hexgrad/Kokoro-82M");
@@ -76,7 +82,7 @@ async fn test_tts_deepinfra_opus() {
     let speech = tts_helper(&provider, &config, model).await.unwrap();
     assert_eq!(speech.file_format, "opus");
     let bytes = speech.audio.clone();
-    assert!(bytes.len() > 0);
+    assert!(!bytes.is_empty());
 
     // Can be used to manually verify the output.
     let mut file = File::create("tests/tmp-deepinfra.opus").unwrap();
@@ -88,7 +94,7 @@ async fn test_tts_deepinfra_other() {
     let mut other = HashMap::new();
     other.insert("seed".to_string(), json!(42));
     let config = transformrs::text_to_speech::TTSConfig {
-        output_format: Some("mp3".to_string()),
+        output_format: Some(AudioFormat::Mp3),
         other: Some(other),
         ..Default::default()
     };
@@ -108,7 +114,7 @@ async fn test_tts_hyperbolic() {
     let mut file = File::create("tests/tmp").unwrap();
     file.write_all(&speech.audio.clone()).unwrap();
     let bytes = speech.audio.clone();
-    assert!(bytes.len() > 0);
+    assert!(!bytes.is_empty());
 
     // Can be used to manually verify the output.
     let mut file = File::create("tests/tmp-hyperbolic.mp3").unwrap();
@@ -139,7 +145,7 @@ async fn test_tts_openai_error() {
     let speech = tts_helper(&provider, &config, model).await;
     let err = speech.unwrap_err();
     println!("err: {}", err);
-    assert!(err.to_string().contains("model_not_found"));
+    assert!(err.to_string().contains("does not exist"));
 }
 
 #[tokio::test]
@@ -187,6 +193,85 @@ async fn test_tts_elevenlabs() {
     file.write_all(&speech.audio.clone()).unwrap();
 }
 
+#[tokio::test]
+async fn test_voices_elevenlabs() {
+    let provider = Provider::ElevenLabs;
+    let key = common::key(&provider);
+    let voices = transformrs::text_to_speech::voices(&provider, &key, None)
+        .await
+        .unwrap();
+    assert!(voices
+        .iter()
+        .any(|voice| voice.id == "nPczCjzI2devNBz1zQrb"));
+}
+
+#[tokio::test]
+async fn test_voices_google() {
+    let provider = Provider::Google;
+    let key = common::key(&provider);
+    let voices = transformrs::text_to_speech::voices(&provider, &key, Some("en-US"))
+        .await
+        .unwrap();
+    assert!(voices.iter().any(|voice| voice.id == "en-US-Studio-Q"));
+    assert!(voices.iter().all(|voice| voice.speaks("en-US")));
+}
+
+#[tokio::test]
+async fn test_voices_unsupported() {
+    let provider = Provider::Cohere;
+    let key = Key::new(provider.clone(), "mock");
+    let err = transformrs::text_to_speech::voices(&provider, &key, None)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Unsupported voices provider: Cohere");
+}
+
+#[tokio::test]
+async fn test_tts_groq() {
+    let config = transformrs::text_to_speech::TTSConfig {
+        voice: Some("Fritz-PlayAI".to_string()),
+        ..Default::default()
+    };
+    let provider = Provider::Groq;
+    let speech = tts_helper(&provider, &config, Some("playai-tts"))
+        .await
+        .unwrap();
+    assert_eq!(speech.format, AudioFormat::Wav);
+    let mut file = File::create("tests/tmp-groq.wav").unwrap();
+    file.write_all(&speech.audio.clone()).unwrap();
+}
+
+#[tokio::test]
+async fn test_tts_togetherai() {
+    let config = transformrs::text_to_speech::TTSConfig {
+        voice: Some("laidback woman".to_string()),
+        output_format: Some(AudioFormat::Mp3),
+        ..Default::default()
+    };
+    let provider = Provider::TogetherAI;
+    let speech = tts_helper(&provider, &config, Some("cartesia/sonic"))
+        .await
+        .unwrap();
+    assert_eq!(speech.format, AudioFormat::Mp3);
+    let mut file = File::create("tests/tmp-togetherai.mp3").unwrap();
+    file.write_all(&speech.audio.clone()).unwrap();
+}
+
+#[tokio::test]
+async fn test_tts_azure() {
+    let region = std::env::var("AZURE_REGION").unwrap_or("westeurope".to_string());
+    let config = transformrs::text_to_speech::TTSConfig {
+        voice: Some("en-US-AvaMultilingualNeural".to_string()),
+        region: Some(region),
+        ..Default::default()
+    };
+    let provider = Provider::Azure;
+    let speech = tts_helper(&provider, &config, None).await.unwrap();
+    assert_eq!(speech.format, AudioFormat::Mp3);
+    let mut file = File::create("tests/tmp-azure.mp3").unwrap();
+    file.write_all(&speech.audio.clone()).unwrap();
+}
+
 #[tokio::test]
 async fn test_tts_google_error() {
     let config = transformrs::text_to_speech::TTSConfig::default();
@@ -195,5 +280,558 @@ async fn test_tts_google_error() {
     let speech = tts_helper(&provider, &config, model).await;
     let err = speech.unwrap_err();
     println!("err: {}", err);
-    assert!(err.to_string().contains("INVALID_ARGUMENT"));
+    assert!(err.to_string().contains("Unknown name"));
+}
+
+#[tokio::test]
+async fn test_tts_stream_openai() {
+    let provider = Provider::OpenAI;
+    let key = common::key(&provider);
+    let config = TTSConfig {
+        voice: Some("alloy".to_string()),
+        ..Default::default()
+    };
+    let msg = "Hello, world!";
+    let mut stream =
+        transformrs::text_to_speech::tts_stream(&provider, &key, &config, Some("tts-1"), msg)
+            .await
+            .unwrap();
+    let mut audio = vec![];
+    while let Some(chunk) = stream.next().await {
+        audio.extend_from_slice(&chunk.unwrap());
+    }
+    assert!(!audio.is_empty());
+}
+
+#[tokio::test]
+async fn test_tts_stream_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::OpenAICompatible(server.url());
+    let key = Key::new(provider.clone(), "mock");
+    let config = TTSConfig {
+        voice: Some("alloy".to_string()),
+        ..Default::default()
+    };
+    let stream =
+        transformrs::text_to_speech::tts_stream(&provider, &key, &config, Some("tts-1"), "Hi")
+            .await
+            .unwrap();
+    let chunks: Vec<_> = stream.map(|chunk| chunk.unwrap()).collect().await;
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), transformrs::mock_server::mp3());
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/audio/speech");
+    assert_eq!(request.json().unwrap()["input"], "Hi");
+
+    server.enqueue(MockResponse::error(400, "Invalid voice"));
+    let result =
+        transformrs::text_to_speech::tts_stream(&provider, &key, &config, Some("tts-1"), "Hi")
+            .await;
+    let err = result.err().unwrap().to_string();
+    assert!(err.contains("400"));
+    assert!(err.contains("Invalid voice"));
+}
+
+#[tokio::test]
+async fn test_tts_stream_unsupported_provider() {
+    let provider = Provider::Google;
+    let key = Key::new(provider.clone(), "key");
+    let config = TTSConfig::default();
+    let result =
+        transformrs::text_to_speech::tts_stream(&provider, &key, &config, None, "Hi").await;
+    let err = result.err().unwrap().to_string();
+    assert_eq!(err, "Unsupported TTS streaming provider: Google");
+}
+
+#[tokio::test]
+async fn test_tts_long_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::OpenAICompatible(server.url());
+    let key = Key::new(provider.clone(), "mock");
+    let config = TTSConfig {
+        voice: Some("alloy".to_string()),
+        ..Default::default()
+    };
+    let paragraph = "This sentence is repeated many times. ".repeat(50);
+    let text = [paragraph.trim(); 5].join("\n\n");
+    let speech = transformrs::text_to_speech::tts_long(&provider, &key, &config, None, &text, 2)
+        .await
+        .unwrap();
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    let mut inputs: Vec<String> = requests
+        .iter()
+        .map(|request| {
+            request.json().unwrap()["input"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    assert!(inputs.iter().all(|input| input.chars().count() <= 4096));
+    inputs.sort();
+    assert_eq!(inputs.concat().len(), text.len() - 2 * "\n\n".len());
+    let frame = transformrs::mock_server::mp3();
+    assert_eq!(speech.audio, [&frame[..], &frame[..], &frame[..]].concat());
+}
+
+#[tokio::test]
+async fn test_tts_long_ssml_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::Google;
+    let key = server.key(&provider);
+    let config = TTSConfig {
+        ssml: true,
+        ..Default::default()
+    };
+    let text = "<speak>Hello.</speak>";
+    let result =
+        transformrs::text_to_speech::tts_long(&provider, &key, &config, None, text, 2).await;
+    let err = result.err().unwrap().to_string();
+    assert!(err.contains("does not support SSML"));
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_tts_format_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::OpenAICompatible(server.url());
+    let key = Key::new(provider.clone(), "mock");
+    let config = TTSConfig {
+        voice: Some("alloy".to_string()),
+        output_format: Some(AudioFormat::Wav),
+        ..Default::default()
+    };
+    let resp = transformrs::text_to_speech::tts(&provider, &key, &config, None, "Hi")
+        .await
+        .unwrap();
+    let speech = resp.structured().unwrap();
+    assert_eq!(speech.format, AudioFormat::Wav);
+    assert_eq!(speech.file_format, "wav");
+    let request = &server.requests()[0];
+    assert_eq!(request.json().unwrap()["response_format"], "wav");
+
+    // The format of the audio takes precedence over the requested format.
+    server.enqueue(MockResponse::bytes(
+        "audio/mpeg",
+        &transformrs::mock_server::mp3(),
+    ));
+    let resp = transformrs::text_to_speech::tts(&provider, &key, &config, None, "Hi")
+        .await
+        .unwrap();
+    assert_eq!(resp.structured().unwrap().format, AudioFormat::Mp3);
+
+    let config = TTSConfig {
+        output_format: Some(AudioFormat::Pcm { sample_rate: 24000 }),
+        ..config
+    };
+    let text = "First paragraph.\n\nSecond paragraph.";
+    let speech = transformrs::text_to_speech::tts_long(&provider, &key, &config, None, text, 2)
+        .await
+        .unwrap();
+    assert_eq!(speech.format, AudioFormat::Pcm { sample_rate: 24000 });
+}
+
+#[tokio::test]
+async fn test_tts_deepinfra_mock() {
+    let config = TTSConfig {
+        voice: Some("am_echo".to_string()),
+        output_format: Some(AudioFormat::Mp3),
+        ..Default::default()
+    };
+    common::mock_tts(Provider::DeepInfra, config, Some("hexgrad/Kokoro-82M")).await;
+}
+
+#[tokio::test]
+async fn test_tts_hyperbolic_mock() {
+    common::mock_tts(Provider::Hyperbolic, TTSConfig::default(), None).await;
+}
+
+#[tokio::test]
+async fn test_tts_elevenlabs_mock() {
+    let config = TTSConfig {
+        voice: Some("nPczCjzI2devNBz1zQrb".to_string()),
+        ..Default::default()
+    };
+    let server = common::mock_tts(Provider::ElevenLabs, config, Some("eleven_turbo_v2_5")).await;
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/text-to-speech/nPczCjzI2devNBz1zQrb");
+    assert_eq!(request.headers["xi-api-key"], "mock");
+}
+
+#[tokio::test]
+async fn test_tts_google_mock() {
+    let config = TTSConfig {
+        voice: Some("en-US-Studio-Q".to_string()),
+        language_code: Some("en-US".to_string()),
+        ..Default::default()
+    };
+    let server = common::mock_tts(Provider::Google, config, None).await;
+    let request = &server.requests()[0];
+    assert_eq!(request.headers["x-goog-api-key"], "mock");
+    assert!(!request.path.contains("key="));
+}
+
+#[tokio::test]
+async fn test_tts_openai_compatible_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::OpenAICompatible(server.url());
+    let key = Key::new(provider.clone(), "mock");
+    let config = TTSConfig {
+        voice: Some("alloy".to_string()),
+        ..Default::default()
+    };
+    let resp = transformrs::text_to_speech::tts(&provider, &key, &config, Some("tts-1"), "Hi")
+        .await
+        .unwrap();
+    assert!(!resp.structured().unwrap().audio.is_empty());
+}
+
+#[tokio::test]
+async fn test_tts_stream_elevenlabs_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::ElevenLabs;
+    let key = server.key(&provider);
+    let config = TTSConfig {
+        voice: Some("nPczCjzI2devNBz1zQrb".to_string()),
+        ..Default::default()
+    };
+    let model = Some("eleven_turbo_v2_5");
+    let stream = transformrs::text_to_speech::tts_stream(&provider, &key, &config, model, "Hello")
+        .await
+        .unwrap();
+    let chunks: Vec<_> = stream.map(|chunk| chunk.unwrap()).collect().await;
+    assert_eq!(chunks.concat(), transformrs::mock_server::mp3());
+    let request = &server.requests()[0];
+    assert_eq!(
+        request.path,
+        "/v1/text-to-speech/nPczCjzI2devNBz1zQrb/stream"
+    );
+    assert_eq!(request.json().unwrap()["model_id"], "eleven_turbo_v2_5");
+
+    server.enqueue(MockResponse::json(
+        401,
+        &json!({"detail": {"status": "invalid_api_key", "message": "Invalid API key"}}),
+    ));
+    let result =
+        transformrs::text_to_speech::tts_stream(&provider, &key, &config, model, "Hello").await;
+    assert!(result
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("Invalid API key"));
+}
+
+#[tokio::test]
+async fn test_tts_formats_mock() {
+    let config = TTSConfig {
+        voice: Some("nPczCjzI2devNBz1zQrb".to_string()),
+        output_format: Some(AudioFormat::Pcm { sample_rate: 16000 }),
+        ..Default::default()
+    };
+    let server = common::mock_tts(Provider::ElevenLabs, config, None).await;
+    let request = &server.requests()[0];
+    let path = "/v1/text-to-speech/nPczCjzI2devNBz1zQrb?output_format=pcm_16000";
+    assert_eq!(request.path, path);
+
+    let config = TTSConfig {
+        voice: Some("en-US-Studio-Q".to_string()),
+        output_format: Some(AudioFormat::Mp3),
+        ..Default::default()
+    };
+    let server = common::mock_tts(Provider::Google, config, None).await;
+    let body = server.requests()[0].json().unwrap();
+    assert_eq!(body["audioConfig"]["audioEncoding"], "MP3");
+
+    let config = TTSConfig {
+        voice: Some("am_echo".to_string()),
+        output_format: Some(AudioFormat::Wav),
+        ..Default::default()
+    };
+    let server = common::mock_tts(Provider::DeepInfra, config, Some("hexgrad/Kokoro-82M")).await;
+    assert_eq!(server.requests()[0].json().unwrap()["output_format"], "wav");
+
+    let provider = Provider::Hyperbolic;
+    let key = Key::new(provider.clone(), "mock");
+    let config = TTSConfig {
+        output_format: Some(AudioFormat::Wav),
+        ..Default::default()
+    };
+    let result = transformrs::text_to_speech::tts(&provider, &key, &config, None, "Hi").await;
+    let err = result.err().unwrap().to_string();
+    assert_eq!(err, "Hyperbolic does not support wav output");
+}
+
+#[tokio::test]
+async fn test_tts_google_ssml_mock() {
+    let config = TTSConfig {
+        voice: Some("en-US-Studio-Q".to_string()),
+        language_code: Some("en-US".to_string()),
+        ssml: true,
+        speed: Some(0.9),
+        ..Default::default()
+    };
+    let server = MockServer::start().await.unwrap();
+    let key = server.key(&Provider::Google);
+    let text = r#"<speak>Call <say-as interpret-as="telephone">555-0100</say-as>.</speak>"#;
+    let resp = transformrs::text_to_speech::tts(&Provider::Google, &key, &config, None, text)
+        .await
+        .unwrap();
+    assert_eq!(resp.structured().unwrap().format, AudioFormat::Wav);
+    let body = server.requests()[0].json().unwrap();
+    assert_eq!(body["input"], json!({ "ssml": text }));
+    assert_eq!(body["audioConfig"]["speakingRate"], 0.9);
+    assert!(body.get("speed").is_none());
+}
+
+#[tokio::test]
+async fn test_tts_timestamps_elevenlabs_mock() {
+    let config = TTSConfig {
+        voice: Some("nPczCjzI2devNBz1zQrb".to_string()),
+        timestamps: true,
+        ..Default::default()
+    };
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::ElevenLabs;
+    let key = server.key(&provider);
+    let resp = transformrs::text_to_speech::tts(&provider, &key, &config, None, "Hi there")
+        .await
+        .unwrap();
+    let speech = resp.structured().unwrap();
+    assert_eq!(speech.format, AudioFormat::Mp3);
+    assert_eq!(speech.characters.unwrap().len(), 8);
+    let words = speech.words.unwrap();
+    assert_eq!(words.len(), 2);
+    assert_eq!(words[1].text, "there");
+    assert!((words[1].start - 0.15).abs() < 1e-9);
+    assert!((words[1].end.unwrap() - 0.4).abs() < 1e-9);
+    let request = &server.requests()[0];
+    assert_eq!(
+        request.path,
+        "/v1/text-to-speech/nPczCjzI2devNBz1zQrb/with-timestamps"
+    );
+}
+
+#[tokio::test]
+async fn test_tts_timestamps_google_mock() {
+    let config = TTSConfig {
+        voice: Some("en-US-Studio-Q".to_string()),
+        language_code: Some("en-US".to_string()),
+        timestamps: true,
+        ..Default::default()
+    };
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::Google;
+    let key = server.key(&provider);
+    let resp = transformrs::text_to_speech::tts(&provider, &key, &config, None, "Hello <world>")
+        .await
+        .unwrap();
+    let speech = resp.structured().unwrap();
+    let words = speech.words.clone().unwrap();
+    assert_eq!(words[0].text, "Hello");
+    assert_eq!(words[0].end, Some(0.25));
+    assert_eq!(words[1].text, "<world>");
+    assert_eq!(words[1].end, speech.duration());
+    assert!(speech.characters.is_none());
+    let body = server.requests()[0].json().unwrap();
+    let ssml = r#"<speak><mark name="0"/>Hello <mark name="1"/>&lt;world&gt;</speak>"#;
+    assert_eq!(body["input"]["ssml"], ssml);
+
+    // The timings of later chunks start after the audio of earlier chunks.
+    let text = "Word. ".repeat(400);
+    let speech = transformrs::text_to_speech::tts_long(&provider, &key, &config, None, &text, 2)
+        .await
+        .unwrap();
+    let words = speech.words.unwrap();
+    assert_eq!(words.len(), 400);
+    // Each chunk is 10 ms of audio and starts with a mark at zero.
+    let starts: Vec<usize> = (0..words.len())
+        .filter(|i| [0.0, 0.01].contains(&words[*i].start))
+        .collect();
+    assert_eq!(starts.len(), 2);
+    assert_eq!(starts[0], 0);
+    assert_eq!(words[starts[1]].start, 0.01);
+
+    let config = TTSConfig {
+        ssml: true,
+        ..config
+    };
+    let result = transformrs::text_to_speech::tts(&provider, &key, &config, None, "<speak/>").await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_tts_voice_settings_elevenlabs_mock() {
+    let config = TTSConfig {
+        voice: Some("nPczCjzI2devNBz1zQrb".to_string()),
+        speed: Some(1.1),
+        voice_settings: Some(VoiceSettings {
+            stability: Some(0.5),
+            ..Default::default()
+        }),
+        pronunciation_dictionary_locators: Some(vec![PronunciationDictionaryLocator {
+            pronunciation_dictionary_id: "dict".to_string(),
+            version_id: None,
+        }]),
+        ..Default::default()
+    };
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::ElevenLabs;
+    let key = server.key(&provider);
+    let first = "The first paragraph. ".repeat(150);
+    let second = "The second paragraph. ".repeat(150);
+    let (first, second) = (first.trim(), second.trim());
+    let text = format!("{first}\n\n{second}");
+    let speech = transformrs::text_to_speech::tts_long(&provider, &key, &config, None, &text, 2)
+        .await
+        .unwrap();
+    assert_eq!(speech.format, AudioFormat::Mp3);
+    let mut bodies: Vec<_> = server
+        .requests()
+        .iter()
+        .map(|request| request.json().unwrap())
+        .collect();
+    bodies.sort_by_key(|body| body["text"] != json!(first));
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(bodies[0]["voice_settings"]["stability"], json!(0.5));
+    assert_eq!(bodies[0]["voice_settings"]["speed"], json!(1.1));
+    assert_eq!(
+        bodies[0]["pronunciation_dictionary_locators"][0]["pronunciation_dictionary_id"],
+        json!("dict")
+    );
+    assert_eq!(bodies[0]["previous_text"], json!(null));
+    assert_eq!(bodies[0]["next_text"], json!(second));
+    assert_eq!(bodies[1]["previous_text"], json!(first));
+    assert_eq!(bodies[1]["next_text"], json!(null));
+}
+
+#[tokio::test]
+async fn test_voices_elevenlabs_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::ElevenLabs;
+    let key = server.key(&provider);
+    let voices = transformrs::text_to_speech::voices(&provider, &key, None)
+        .await
+        .unwrap();
+    assert_eq!(voices.len(), 2);
+    assert_eq!(voices[0].id, "nPczCjzI2devNBz1zQrb");
+    assert_eq!(voices[0].languages, vec!["en-US"]);
+    assert_eq!(voices[1].gender, Some(VoiceGender::Female));
+    assert_eq!(
+        voices[1].preview_url.as_deref(),
+        Some("https://example.com/matilda.mp3")
+    );
+    let voices = transformrs::text_to_speech::voices(&provider, &key, Some("de"))
+        .await
+        .unwrap();
+    assert_eq!(voices.len(), 1);
+    assert_eq!(voices[0].name, "Matilda");
+    assert_eq!(server.requests()[0].headers["xi-api-key"], "mock");
+}
+
+#[tokio::test]
+async fn test_voices_google_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::Google;
+    let key = server.key(&provider);
+    let voices = transformrs::text_to_speech::voices(&provider, &key, Some("nl-NL"))
+        .await
+        .unwrap();
+    assert_eq!(voices.len(), 2);
+    assert_eq!(voices[1].id, "nl-NL-Studio-Q");
+    assert_eq!(voices[1].gender, Some(VoiceGender::Male));
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/voices?languageCode=nl-NL");
+
+    transformrs::text_to_speech::voices(&provider, &key, Some("nl&key=x"))
+        .await
+        .unwrap();
+    let request = &server.requests()[1];
+    assert_eq!(request.path, "/v1/voices?languageCode=nl%26key%3Dx");
+}
+
+#[tokio::test]
+async fn test_tts_hyperbolic_voice_mock() {
+    let provider = Provider::Hyperbolic;
+    let key = Key::new(provider.clone(), "mock");
+    let voices = transformrs::text_to_speech::voices(&provider, &key, Some("en-GB"))
+        .await
+        .unwrap();
+    let ids: Vec<&str> = voices.iter().map(|voice| voice.id.as_str()).collect();
+    assert_eq!(ids, vec!["EN-BR", "EN-Default"]);
+    let config = TTSConfig {
+        voice: Some(voices[0].id.clone()),
+        ..Default::default()
+    };
+    let server = common::mock_tts(provider, config, None).await;
+    let body = server.requests()[0].json().unwrap();
+    assert_eq!(body["speaker"], json!("EN-BR"));
+    assert_eq!(body["language"], json!("EN"));
+}
+
+#[tokio::test]
+async fn test_tts_groq_mock() {
+    let config = TTSConfig {
+        voice: Some("Fritz-PlayAI".to_string()),
+        ..Default::default()
+    };
+    let server = common::mock_tts(Provider::Groq, config, None).await;
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/openai/v1/audio/speech");
+    let body = request.json().unwrap();
+    assert_eq!(body["model"], json!("playai-tts"));
+    assert_eq!(body["response_format"], json!("wav"));
+}
+
+#[tokio::test]
+async fn test_tts_togetherai_mock() {
+    let config = TTSConfig {
+        voice: Some("laidback woman".to_string()),
+        output_format: Some(AudioFormat::Mp3),
+        ..Default::default()
+    };
+    let server = common::mock_tts(Provider::TogetherAI, config, None).await;
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/audio/speech");
+    let body = request.json().unwrap();
+    assert_eq!(body["model"], json!("cartesia/sonic"));
+    assert_eq!(body["input"], json!("Hello"));
+}
+
+#[tokio::test]
+async fn test_tts_azure_mock() {
+    let config = TTSConfig {
+        voice: Some("en-US-AvaMultilingualNeural".to_string()),
+        output_format: Some(AudioFormat::Wav),
+        region: Some("westeurope".to_string()),
+        ..Default::default()
+    };
+    let server = common::mock_tts(Provider::Azure, config, None).await;
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/cognitiveservices/v1");
+    assert_eq!(request.headers["ocp-apim-subscription-key"], "mock");
+    assert_eq!(
+        request.headers["x-microsoft-outputformat"],
+        "riff-24khz-16bit-mono-pcm"
+    );
+    assert_eq!(request.headers["content-type"], "application/ssml+xml");
+    let body = String::from_utf8(request.body.to_vec()).unwrap();
+    assert!(body.contains("<voice name=\"en-US-AvaMultilingualNeural\">Hello</voice>"));
+}
+
+#[tokio::test]
+async fn test_tts_azure_error_mock() {
+    let server = MockServer::start().await.unwrap();
+    let provider = Provider::Azure;
+    let mut response = MockResponse::bytes("text/plain", b"");
+    response.status = 401;
+    server.enqueue(response);
+    let key = Key::new(provider.clone(), "wrong").with_base_url(&server.url());
+    let config = TTSConfig {
+        voice: Some("en-US-AvaMultilingualNeural".to_string()),
+        ..Default::default()
+    };
+    let result = transformrs::text_to_speech::tts(&provider, &key, &config, None, "Hi").await;
+    let err = result.err().unwrap().to_string();
+    assert_eq!(err, "TTS request failed with status 401 Unauthorized: ");
 }